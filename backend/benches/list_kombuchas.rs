@@ -0,0 +1,209 @@
+//! Lists a seeded database of a few thousand kombuchas: a page of summaries
+//! as the list in the frontend loads it, every page after another, and every
+//! kombucha in full, once with the batched `get_all_kombuchas` and once the
+//! way it used to be done, with a round of queries per kombucha.
+//!
+//! Runs on a SQLite file, and on Postgres as well when
+//! `BENCH_DATABASE_URL` points to an empty database, which is emptied again
+//! afterwards.
+//!
+//! Run with `cargo bench -p kombucha-tracker-server`
+
+use chrono::{Duration, Utc};
+use data_types::{
+    FermentationStatus, KombuchaId, KombuchaQuery, KombuchaSort,
+    NewFermentation,
+};
+use kombucha_tracker_server::{
+    migrate::{self, Migrate},
+    KombuchaStore, PostgresStore, SqliteStore,
+};
+use sqlx::{PgPool, SqlitePool};
+use std::time::Instant;
+
+const KOMBUCHAS: usize = 2_000;
+const ENTRIES_PER_KOMBUCHA: usize = 5;
+const FERMENTATIONS_PER_KOMBUCHA: i64 = 2;
+/// As the frontend requests them
+const PAGE_SIZE: u32 = 100;
+const ROUNDS: u32 = 5;
+
+/// Goes through the store, so that both databases get the same rows
+async fn seed(store: &impl KombuchaStore) -> Result<(), anyhow::Error> {
+    let start = Utc::now() - Duration::days(365);
+
+    for kombucha in 1..=KOMBUCHAS {
+        let id = store
+            .create_kombucha(&format!("Batch #{}", kombucha))
+            .await?;
+
+        for _ in 0..ENTRIES_PER_KOMBUCHA {
+            store.create_entry(id).await?;
+        }
+
+        for fermentation in 0..FERMENTATIONS_PER_KOMBUCHA {
+            let start_date = start + Duration::days(fermentation * 7);
+            let status = if fermentation == 0 {
+                FermentationStatus::Primary
+            } else {
+                FermentationStatus::Secondary
+            };
+
+            store
+                .create_fermentation(
+                    id,
+                    &NewFermentation {
+                        start_date: Some(start_date),
+                        est_end_date: Some(start_date + Duration::days(7)),
+                        status,
+                    },
+                )
+                .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The best of a few rounds, so that a cold cache doesn't skew the result
+async fn best_of<F, Fut>(
+    expected: usize,
+    mut list: F,
+) -> Result<std::time::Duration, anyhow::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<usize, anyhow::Error>>,
+{
+    let mut best = None;
+
+    for _ in 0..ROUNDS {
+        let started = Instant::now();
+        let listed = list().await?;
+        let elapsed = started.elapsed();
+
+        assert_eq!(listed, expected);
+        best =
+            Some(best.map_or(elapsed, |best: std::time::Duration| {
+                best.min(elapsed)
+            }));
+    }
+
+    Ok(best.unwrap_or_default())
+}
+
+fn page_query(cursor: Option<String>) -> KombuchaQuery {
+    KombuchaQuery {
+        limit: Some(PAGE_SIZE),
+        cursor,
+        sort: KombuchaSort::NextMilestone,
+        ..KombuchaQuery::default()
+    }
+}
+
+async fn bench(
+    name: &str,
+    store: &(impl KombuchaStore + Migrate),
+) -> Result<(), anyhow::Error> {
+    migrate::up(store, false).await?;
+
+    if !store.get_all_kombuchas().await?.is_empty() {
+        anyhow::bail!("The {} database has to be empty", name);
+    }
+
+    println!(
+        "Seeding {} with {} kombuchas with {} entries and {} fermentations each",
+        name, KOMBUCHAS, ENTRIES_PER_KOMBUCHA, FERMENTATIONS_PER_KOMBUCHA
+    );
+    seed(store).await?;
+
+    let first_page = best_of(PAGE_SIZE as usize, || async {
+        Ok(store
+            .list_kombuchas(&page_query(None))
+            .await?
+            .kombuchas
+            .len())
+    })
+    .await?;
+
+    let all_pages = best_of(KOMBUCHAS, || async {
+        let mut listed = 0;
+        let mut cursor = None;
+
+        loop {
+            let page = store.list_kombuchas(&page_query(cursor)).await?;
+            listed += page.kombuchas.len();
+
+            match page.next_cursor {
+                Some(next_cursor) => cursor = Some(next_cursor),
+                None => return Ok(listed),
+            }
+        }
+    })
+    .await?;
+
+    let batched = best_of(KOMBUCHAS, || async {
+        Ok(store.get_all_kombuchas().await?.len())
+    })
+    .await?;
+
+    let ids: Vec<KombuchaId> = store
+        .get_all_kombuchas()
+        .await?
+        .iter()
+        .map(|kombucha| kombucha.id)
+        .collect();
+    let per_kombucha = best_of(KOMBUCHAS, || async {
+        let mut listed = 0;
+        for &id in &ids {
+            if store.get_kombucha(id).await?.is_some() {
+                listed += 1;
+            }
+        }
+
+        Ok(listed)
+    })
+    .await?;
+
+    println!("{}", name);
+    println!("  first page:   {:>10.2?}", first_page);
+    println!("  all pages:    {:>10.2?}", all_pages);
+    println!("  batched:      {:>10.2?}", batched);
+    println!("  per kombucha: {:>10.2?}", per_kombucha);
+    println!(
+        "  speedup:      {:>10.1}x",
+        per_kombucha.as_secs_f64() / batched.as_secs_f64()
+    );
+
+    for id in ids {
+        store.delete_kombucha(id).await?;
+    }
+    store.purge_deleted(Utc::now()).await?;
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let dir = std::env::temp_dir()
+        .join(format!("kombucha-tracker-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let pool = SqlitePool::new(&format!(
+        "sqlite://{}",
+        dir.join("kombucha.db").display()
+    ))
+    .await?;
+    bench("SQLite", &SqliteStore::new(pool)).await?;
+
+    std::fs::remove_dir_all(&dir)?;
+
+    match std::env::var("BENCH_DATABASE_URL") {
+        Ok(url) => {
+            let pool = PgPool::new(&url).await?;
+            bench("Postgres", &PostgresStore::new(pool)).await?;
+        }
+        Err(_) => println!("BENCH_DATABASE_URL isn't set, skipping Postgres"),
+    }
+
+    Ok(())
+}
@@ -1,31 +1,198 @@
-use super::App;
-use data_types::KombuchaId;
-use sqlx::prelude::*;
+use super::{images, App};
+use crate::{
+    error::{Error, Result},
+    store::next_status,
+};
+use chrono::{Duration, Utc};
+use data_types::{
+    EntryId, FermentationId, FermentationStatus, ImageId, KombuchaId,
+    MeasurementId, NewFermentation, NewKombucha, NewMeasurement,
+    NewRecipeTemplate, PlannedStage, TemplateId,
+};
 
 impl App {
+    /// Copies the recipe of the template or the earlier batch into the new
+    /// one, which starts its primary fermentation right away when the
+    /// recipe plans one
     pub async fn create_new_kombucha(
         &self,
-    ) -> Result<KombuchaId, anyhow::Error> {
-        let (id,) = sqlx::query_as::<_, (KombuchaId,)>(
-            "INSERT INTO kombucha (name, added) VALUES ('', NOW()) RETURNING id",
-        )
-        .fetch_one(&self.db)
-        .await?;
+        new_kombucha: &NewKombucha,
+    ) -> Result<KombuchaId> {
+        let (name, recipe) =
+            match (new_kombucha.template_id, new_kombucha.clone_from) {
+                (Some(_), Some(_)) => {
+                    return Err(Error::Validation(
+                        "A kombucha is brewed either from a template or from an earlier one"
+                            .to_string(),
+                    ))
+                }
+                (Some(template_id), None) => {
+                    let template = self
+                        .store
+                        .get_template(template_id)
+                        .await?
+                        .ok_or_else(|| {
+                            Error::Validation(format!(
+                                "Template {} doesn't exist",
+                                template_id
+                            ))
+                        })?;
+
+                    (template.name, Some(template.recipe))
+                }
+                (None, Some(kombucha_id)) => {
+                    let kombucha = self
+                        .store
+                        .get_kombucha(kombucha_id)
+                        .await?
+                        .ok_or_else(|| {
+                            Error::Validation(format!(
+                                "Kombucha {} doesn't exist",
+                                kombucha_id
+                            ))
+                        })?;
+
+                    // Batches brewed before stages could be planned get
+                    // them from how long their fermentations were meant to
+                    // take
+                    let fermentations = &kombucha.fermentations;
+                    let recipe = kombucha.recipe.clone().map(|mut recipe| {
+                        if recipe.stages.is_empty() {
+                            recipe.stages =
+                                PlannedStage::from_fermentations(fermentations);
+                        }
+                        recipe
+                    });
+
+                    (kombucha.name, recipe)
+                }
+                (None, None) => (String::new(), None),
+            };
+        let name = new_kombucha.name.clone().unwrap_or(name);
+
+        let recipe = match recipe {
+            Some(recipe) => recipe,
+            None => return self.store.create_kombucha(&name).await,
+        };
+        let fermentation = recipe
+            .planned_days(FermentationStatus::Primary)
+            .map(|days| {
+                let now = Utc::now();
+                NewFermentation {
+                    start_date: Some(now),
+                    est_end_date: Some(now + Duration::days(days.into())),
+                    status: FermentationStatus::Primary,
+                }
+            });
+
+        self.store
+            .create_kombucha_with_recipe(&name, &recipe, fermentation.as_ref())
+            .await
+    }
+
+    pub async fn create_recipe_template(
+        &self,
+        template: &NewRecipeTemplate,
+    ) -> Result<TemplateId> {
+        if template.name.trim().is_empty() {
+            return Err(Error::Validation(
+                "A template needs a name".to_string(),
+            ));
+        }
+        template
+            .recipe
+            .check()
+            .map_err(|err| Error::Validation(err.to_string()))?;
 
-        Ok(id)
+        self.store.create_template(template).await
     }
 
     pub async fn create_new_kombucha_entry(
         &self,
         kombucha_id: KombuchaId,
-    ) -> Result<KombuchaId, anyhow::Error> {
-        let (id,) = sqlx::query_as::<_, (KombuchaId,)>(
-            "INSERT INTO kombucha_entry (kombucha_id, content, added) VALUES ($1, '', NOW()) RETURNING id",
+    ) -> Result<EntryId> {
+        self.store.create_entry(kombucha_id).await
+    }
+
+    /// A kombucha is in one stage at a time, so a fermentation is only added
+    /// once the last one is over and has to be a stage that one moves on to
+    pub async fn create_new_kombucha_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &NewFermentation,
+    ) -> Result<FermentationId> {
+        let last = self.last_fermentation(kombucha_id).await?;
+        if let Some(last) = &last {
+            if last.is_active() && !last.status.is_terminal() {
+                return Err(Error::Validation(format!(
+                    "Kombucha {} is still in {}",
+                    kombucha_id, last.status
+                )));
+            }
+        }
+        next_status(last.map(|last| last.status), Some(fermentation.status))?;
+
+        self.store
+            .create_fermentation(kombucha_id, fermentation)
+            .await
+    }
+
+    pub async fn create_new_kombucha_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &NewMeasurement,
+    ) -> Result<MeasurementId> {
+        self.check_measurement(
+            kombucha_id,
+            measurement.kind,
+            measurement.value,
+            measurement.unit(),
+            measurement.fermentation_id,
         )
-        .bind(kombucha_id)
-        .fetch_one(&self.db)
         .await?;
 
-        Ok(id)
+        self.store
+            .create_measurement(kombucha_id, measurement)
+            .await
+    }
+
+    pub async fn create_kombucha_image(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: Option<EntryId>,
+        data: Vec<u8>,
+    ) -> Result<ImageId> {
+        let (extension, content_type) = images::format_of(&data)?;
+
+        if let Some(entry_id) = entry_id {
+            if self
+                .get_kombucha_entry(kombucha_id, entry_id)
+                .await?
+                .is_none()
+            {
+                return Err(Error::not_found(format!(
+                    "Entry {} of kombucha {}",
+                    entry_id, kombucha_id
+                )));
+            }
+        }
+
+        let image = self
+            .store
+            .create_image(kombucha_id, entry_id, content_type, &|id| {
+                images::file_name(kombucha_id, id, extension)
+            })
+            .await?;
+
+        // The row goes away again when the file can't be stored, most likely
+        // because it isn't a valid image
+        if let Err(err) =
+            images::store(self.image_dir.clone(), image.file_name, data).await
+        {
+            self.store.delete_image(kombucha_id, image.id).await?;
+            return Err(err);
+        }
+
+        Ok(image.id)
     }
 }
@@ -1,41 +1,70 @@
-use super::App;
-use data_types::{EntryId, KombuchaId};
-use sqlx::prelude::*;
+use super::{images, App};
+use crate::error::{Error, Result};
+use data_types::{
+    EntryId, FermentationId, ImageId, KombuchaId, MeasurementId, TemplateId,
+};
 
 impl App {
     pub async fn delete_kombucha_entry(
         &self,
         kombucha_id: KombuchaId,
         entry_id: EntryId,
-    ) -> Result<(), anyhow::Error> {
-        let query = sqlx::query(
-            "DELETE FROM kombucha_entry WHERE kombucha_id = $1 and id = $2",
-        )
-        .bind(kombucha_id)
-        .bind(entry_id);
+    ) -> Result<()> {
+        self.store.delete_entry(kombucha_id, entry_id).await
+    }
 
-        self.db.acquire().await?.execute(query).await?;
+    pub async fn delete_kombucha_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        self.store
+            .delete_fermentation(kombucha_id, fermentation_id)
+            .await
+    }
 
-        Ok(())
+    pub async fn delete_kombucha_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<()> {
+        self.store
+            .delete_measurement(kombucha_id, measurement_id)
+            .await
     }
 
-    pub async fn delete_kombucha(
+    pub async fn delete_kombucha_recipe(
         &self,
         kombucha_id: KombuchaId,
-    ) -> Result<(), anyhow::Error> {
-        let mut transaction = self.db.begin().await?;
+    ) -> Result<()> {
+        self.store.delete_recipe(kombucha_id).await
+    }
+
+    /// Batches brewed from the template keep their copy of the recipe
+    pub async fn delete_recipe_template(&self, id: TemplateId) -> Result<()> {
+        self.store.delete_template(id).await
+    }
 
-        let delete_entries_query =
-            sqlx::query("DELETE FROM kombucha_entry WHERE kombucha_id = $1")
-                .bind(kombucha_id);
+    /// Moves the kombucha to the trash, its images are kept until it's
+    /// purged
+    pub async fn delete_kombucha(&self, kombucha_id: KombuchaId) -> Result<()> {
+        self.store.delete_kombucha(kombucha_id).await
+    }
 
-        let delete_kombucha_query =
-            sqlx::query("DELETE FROM kombucha WHERE id = $1").bind(kombucha_id);
+    pub async fn delete_kombucha_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<()> {
+        let db_image = self
+            .store
+            .get_image(kombucha_id, image_id)
+            .await?
+            .ok_or_else(|| Error::not_found("image"))?;
 
-        transaction.execute(delete_entries_query).await?;
-        transaction.execute(delete_kombucha_query).await?;
+        self.store.delete_image(kombucha_id, image_id).await?;
 
-        transaction.commit().await?;
+        images::remove(self.image_dir.clone(), db_image.file_name).await?;
 
         Ok(())
     }
@@ -1,147 +1,155 @@
-use super::App;
-use data_types::db::{
-    Entry as DbEntry, Fermentation as DbFermentation, Kombucha as DbKombucha,
+use super::{images, App};
+use crate::{
+    error::{Error, Result},
+    series,
+};
+use data_types::{
+    Entry, EntryId, Fermentation, FermentationId, Image, ImageId, Kombucha,
+    KombuchaId, KombuchaPage, KombuchaQuery, Measurement, MeasurementId,
+    MeasurementSeries, Recipe, RecipeTemplate, SearchHit, SearchQuery,
+    SeriesQuery, TemplateId,
 };
-use data_types::{Entry, EntryId, Kombucha, KombuchaId};
-use sqlx::prelude::*;
 
 impl App {
-    async fn get_all_db_kombuchas(
-        &self,
-    ) -> Result<Vec<DbKombucha>, anyhow::Error> {
-        let row = sqlx::query_as::<_, DbKombucha>(
-            "SELECT id, name, added FROM kombucha ORDER BY id",
-        )
-        .fetch_all(&self.db)
-        .await?;
-
-        Ok(row)
+    pub async fn get_all_kombuchas(&self) -> Result<Vec<Kombucha>> {
+        self.store.get_all_kombuchas().await
     }
 
-    pub async fn get_all_kombuchas(
+    pub async fn list_kombuchas(
         &self,
-    ) -> Result<Vec<Kombucha>, anyhow::Error> {
-        let db_kombuchas = self.get_all_db_kombuchas().await?;
-
-        let mut kombuchas = Vec::with_capacity(db_kombuchas.len());
-
-        for db_kombucha in db_kombuchas.into_iter() {
-            let db_entries =
-                self.get_db_entries_for_kombucha(db_kombucha.id).await?;
+        query: &KombuchaQuery,
+    ) -> Result<KombuchaPage> {
+        self.store.list_kombuchas(query).await
+    }
 
-            let DbKombucha {
-                id, name, added, ..
-            } = db_kombucha;
+    pub async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        self.store.search(query).await
+    }
 
-            let entries = db_entries.into_iter().map(Entry::from).collect();
+    pub async fn get_kombucha(
+        &self,
+        id: KombuchaId,
+    ) -> Result<Option<Kombucha>> {
+        self.store.get_kombucha(id).await
+    }
 
-            kombuchas.push(Kombucha {
-                id,
-                name,
-                added,
-                entries,
-                fermentations: vec![],
-            });
-        }
+    pub async fn get_kombucha_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<Option<Entry>> {
+        self.store.get_entry(kombucha_id, entry_id).await
+    }
 
-        Ok(kombuchas)
+    pub async fn get_kombucha_entries(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Entry>> {
+        self.store.get_entries(kombucha_id).await
     }
 
-    async fn get_db_kombucha(
+    pub async fn get_kombucha_fermentation(
         &self,
-        id: KombuchaId,
-    ) -> Result<Option<DbKombucha>, anyhow::Error> {
-        let row = sqlx::query_as::<_, DbKombucha>(
-            "SELECT id, name, added FROM kombucha WHERE id  = $1",
-        )
-        .bind(id)
-        .fetch_optional(&self.db)
-        .await?;
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<Option<Fermentation>> {
+        self.store
+            .get_fermentation(kombucha_id, fermentation_id)
+            .await
+    }
 
-        Ok(row)
+    pub async fn get_kombucha_fermentations(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Fermentation>> {
+        self.store.get_fermentations(kombucha_id).await
     }
 
-    pub async fn get_kombucha(
+    pub async fn get_kombucha_measurement(
         &self,
-        id: KombuchaId,
-    ) -> Result<Option<Kombucha>, anyhow::Error> {
-        if let Some(DbKombucha {
-            id, name, added, ..
-        }) = self.get_db_kombucha(id).await?
-        {
-            let entries = self.get_db_entries_for_kombucha(id).await?;
-            let entries = entries.into_iter().map(Entry::from).collect();
-
-            Ok(Some(Kombucha {
-                id,
-                name,
-                added,
-                entries,
-                fermentations: vec![],
-            }))
-        } else {
-            Ok(None)
-        }
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<Option<Measurement>> {
+        self.store
+            .get_measurement(kombucha_id, measurement_id)
+            .await
     }
 
-    pub async fn get_kombucha_entry(
+    pub async fn get_kombucha_measurements(
         &self,
         kombucha_id: KombuchaId,
-        entry_id: EntryId,
-    ) -> Result<Option<Entry>, anyhow::Error> {
-        let maybe_entry = sqlx::query_as::<_, DbEntry>(
-            "SELECT id, kombucha_id, content, added FROM kombucha_entry WHERE id = $1 AND kombucha_id = $2"
-        )
-        .bind(entry_id)
-        .bind(kombucha_id)
-        .fetch_optional(&self.db)
-        .await?;
+    ) -> Result<Vec<Measurement>> {
+        self.store.get_measurements(kombucha_id).await
+    }
 
-        Ok(maybe_entry.map(Entry::from))
+    /// A series per measured kind, downsampled to at most `query.points`
+    pub async fn get_kombucha_measurement_series(
+        &self,
+        kombucha_id: KombuchaId,
+        query: &SeriesQuery,
+    ) -> Result<Vec<MeasurementSeries>> {
+        let points = series::points(query)?;
+
+        self.store
+            .get_kombucha_row(kombucha_id)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found(format!("Kombucha {}", kombucha_id))
+            })?;
+        let measurements = self.store.get_measurements(kombucha_id).await?;
+
+        Ok(series::series(&measurements, points))
     }
 
-    pub async fn get_kombucha_entries(
+    pub async fn get_kombucha_recipe(
         &self,
         kombucha_id: KombuchaId,
-    ) -> Result<Vec<Entry>, anyhow::Error> {
-        let entries = sqlx::query_as::<_, DbEntry>(
-            "SELECT id, kombucha_id, content, added FROM kombucha_entry WHERE kombucha_id = $1"
-        )
-        .bind(kombucha_id)
-        .fetch_all(&self.db)
-        .await?
-        .into_iter()
-        .map(Entry::from)
-        .collect();
+    ) -> Result<Option<Recipe>> {
+        self.store.get_recipe(kombucha_id).await
+    }
 
-        Ok(entries)
+    pub async fn get_recipe_templates(&self) -> Result<Vec<RecipeTemplate>> {
+        self.store.get_templates().await
     }
 
-    async fn get_db_entries_for_kombucha(
+    pub async fn get_recipe_template(
         &self,
-        kombucha_id: KombuchaId,
-    ) -> Result<Vec<DbEntry>, anyhow::Error> {
-        let row = sqlx::query_as::<_, DbEntry>(
-            "SELECT id, kombucha_id, content, added FROM kombucha_entry WHERE kombucha_id = $1",
-        )
-        .bind(kombucha_id)
-        .fetch_all(&self.db)
-        .await?;
+        id: TemplateId,
+    ) -> Result<Option<RecipeTemplate>> {
+        self.store.get_template(id).await
+    }
 
-        Ok(row)
+    pub async fn get_kombucha_images(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Image>> {
+        self.store.get_images(kombucha_id).await
     }
 
-    async fn _get_db_fermentations_for_kombucha(
+    /// Returns the content type and contents of an image or its thumbnail
+    pub async fn get_kombucha_image_data(
         &self,
         kombucha_id: KombuchaId,
-    ) -> Result<Vec<DbFermentation>, anyhow::Error> {
-        let rows = sqlx::query_as::<_, DbFermentation>(
-            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE kombucha_id = $1"
-        )
-        .bind(kombucha_id)
-        .fetch_all(&self.db)
-        .await?;
-
-        Ok(rows)
+        image_id: ImageId,
+        thumbnail: bool,
+    ) -> Result<Option<(String, Vec<u8>)>> {
+        let db_image = match self.store.get_image(kombucha_id, image_id).await?
+        {
+            Some(db_image) => db_image,
+            None => return Ok(None),
+        };
+
+        let (content_type, file_name) = if thumbnail {
+            (
+                "image/jpeg".to_string(),
+                images::thumbnail_file_name(&db_image.file_name),
+            )
+        } else {
+            (db_image.content_type, db_image.file_name)
+        };
+
+        let data = images::load(self.image_dir.clone(), file_name).await?;
+
+        Ok(Some((content_type, data)))
     }
 }
@@ -0,0 +1,122 @@
+//! On-disk storage of uploaded images, every image is stored next to a JPEG
+//! thumbnail under `<image dir>/<kombucha id>/`
+
+use crate::error::{Error, Result};
+use data_types::{ImageId, KombuchaId};
+use image::{
+    imageops::FilterType, DynamicImage, ImageFormat, ImageOutputFormat,
+};
+use std::{fs, path::PathBuf};
+
+const THUMBNAIL_SIZE: u32 = 256;
+
+/// The extension and content type of an image, taken from its contents
+/// rather than from the content type it was uploaded with
+pub fn format_of(data: &[u8]) -> Result<(&'static str, &'static str)> {
+    match image::guess_format(data) {
+        Ok(ImageFormat::Jpeg) => Ok(("jpg", "image/jpeg")),
+        Ok(ImageFormat::Png) => Ok(("png", "image/png")),
+        Ok(ImageFormat::Gif) => Ok(("gif", "image/gif")),
+        Ok(format) => Err(Error::Validation(format!(
+            "Unsupported image type {:?}",
+            format
+        ))),
+        Err(_) => Err(Error::Validation(
+            "Uploaded file is not an image".to_string(),
+        )),
+    }
+}
+
+pub fn file_name(
+    kombucha_id: KombuchaId,
+    image_id: ImageId,
+    extension: &str,
+) -> String {
+    format!("{}/{}.{}", kombucha_id, image_id, extension)
+}
+
+pub fn thumbnail_file_name(file_name: &str) -> String {
+    let stem = file_name.rsplitn(2, '.').last().unwrap_or(file_name);
+
+    format!("{}_thumb.jpg", stem)
+}
+
+/// Decodes the image, so that only valid images are ever stored, and writes
+/// it together with its thumbnail
+pub async fn store(
+    dir: PathBuf,
+    file_name: String,
+    data: Vec<u8>,
+) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let image = image::load_from_memory(&data).map_err(|err| {
+            Error::Validation(format!(
+                "Uploaded file is not a valid image: {}",
+                err
+            ))
+        })?;
+
+        let thumbnail = DynamicImage::ImageRgb8(
+            image
+                .resize(THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Triangle)
+                .to_rgb8(),
+        );
+
+        let mut thumbnail_data = Vec::new();
+        thumbnail
+            .write_to(&mut thumbnail_data, ImageOutputFormat::Jpeg(80))
+            .map_err(|err| Error::Internal(err.into()))?;
+
+        let path = dir.join(&file_name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(&path, data)?;
+        fs::write(dir.join(thumbnail_file_name(&file_name)), thumbnail_data)?;
+
+        Ok(())
+    })
+    .await?
+}
+
+pub async fn load(dir: PathBuf, file_name: String) -> Result<Vec<u8>> {
+    let data =
+        tokio::task::spawn_blocking(move || fs::read(dir.join(file_name)))
+            .await??;
+
+    Ok(data)
+}
+
+/// Removes the image and its thumbnail, missing files are not an error
+pub async fn remove(dir: PathBuf, file_name: String) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        for path in &[
+            dir.join(&file_name),
+            dir.join(thumbnail_file_name(&file_name)),
+        ] {
+            match fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(err.into());
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    })
+    .await?
+}
+
+/// Removes every image of a kombucha
+pub async fn remove_all(dir: PathBuf, kombucha_id: KombuchaId) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        match fs::remove_dir_all(dir.join(kombucha_id.to_string())) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(err.into())
+            }
+            _ => Ok(()),
+        }
+    })
+    .await?
+}
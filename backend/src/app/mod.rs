@@ -1,17 +1,27 @@
-use sqlx::PgPool;
+use crate::store::KombuchaStore;
+use std::{path::PathBuf, sync::Arc};
 
 mod create;
 mod delete;
 mod get;
+mod images;
+mod trash;
 mod update;
 
 #[derive(Clone, Debug)]
 pub struct App {
-    db: PgPool,
+    store: Arc<dyn KombuchaStore>,
+    image_dir: PathBuf,
 }
 
 impl App {
-    pub fn new(db: PgPool) -> Self {
-        Self { db }
+    pub fn new(
+        store: impl KombuchaStore + 'static,
+        image_dir: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            store: Arc::new(store),
+            image_dir: image_dir.into(),
+        }
     }
 }
@@ -0,0 +1,50 @@
+use super::{images, App};
+use crate::error::Result;
+use chrono::{Duration, Utc};
+use data_types::{EntryId, FermentationId, KombuchaId, Trash};
+
+impl App {
+    pub async fn get_trash(&self) -> Result<Trash> {
+        self.store.get_trash().await
+    }
+
+    pub async fn restore_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<()> {
+        self.store.restore_kombucha(kombucha_id).await
+    }
+
+    pub async fn restore_kombucha_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        self.store.restore_entry(kombucha_id, entry_id).await
+    }
+
+    pub async fn restore_kombucha_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        self.store
+            .restore_fermentation(kombucha_id, fermentation_id)
+            .await
+    }
+
+    /// Deletes for good whatever has been in the trash for longer than
+    /// `retention`, returns the kombuchas that are gone
+    pub async fn purge_trash(
+        &self,
+        retention: Duration,
+    ) -> Result<Vec<KombuchaId>> {
+        let purged = self.store.purge_deleted(Utc::now() - retention).await?;
+
+        for &kombucha_id in &purged {
+            images::remove_all(self.image_dir.clone(), kombucha_id).await?;
+        }
+
+        Ok(purged)
+    }
+}
@@ -1,34 +1,290 @@
 use super::App;
-use data_types::Kombucha;
-use sqlx::prelude::*;
+use crate::{
+    error::{Error, Result},
+    store::next_status,
+};
+use chrono::{DateTime, Duration, Utc};
+use data_types::{
+    AdvanceFermentation, Entry, EntryId, EntryPatch, Fermentation,
+    FermentationId, Kombucha, KombuchaId, KombuchaPatch, Measurement,
+    MeasurementKind, Recipe, RecipeWarning,
+};
 
 impl App {
-    pub async fn update_kombucha(
+    /// Returns the version the kombucha is at afterwards
+    pub async fn update_kombucha(&self, kombucha: &Kombucha) -> Result<i32> {
+        self.check_kombucha_added(
+            kombucha.id,
+            kombucha.added,
+            &kombucha.entries,
+        )
+        .await?;
+
+        self.store.update_kombucha(kombucha).await
+    }
+
+    /// Updates the fields of the patch if the kombucha is still at `version`,
+    /// returns the kombucha as it is afterwards
+    pub async fn patch_kombucha(
         &self,
-        kombucha: &Kombucha,
-    ) -> Result<(), anyhow::Error> {
-        let mut transaction = self.db.begin().await?;
+        id: KombuchaId,
+        version: i32,
+        patch: &KombuchaPatch,
+    ) -> Result<Kombucha> {
+        if let Some(added) = patch.added {
+            self.check_kombucha_added(id, added, &[]).await?;
+        }
 
-        let query = sqlx::query(
-            "UPDATE kombucha SET (name, added) = ($1, $2) WHERE id = $3",
-        )
-        .bind(&kombucha.name)
-        .bind(&kombucha.added)
-        .bind(kombucha.id);
+        self.store.patch_kombucha(id, version, patch).await?;
+
+        self.store
+            .get_kombucha(id)
+            .await?
+            .ok_or_else(|| Error::not_found(format!("Kombucha {}", id)))
+    }
+
+    /// Updates the content and the time an entry was added, returns the
+    /// version it's at afterwards
+    pub async fn update_kombucha_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry: &Entry,
+    ) -> Result<i32> {
+        self.check_entry_patch(kombucha_id, &EntryPatch::from(entry))
+            .await?;
+
+        self.store
+            .patch_entry(kombucha_id, entry.id, entry.version, &entry.into())
+            .await
+    }
+
+    /// Updates the fields of the patch if the entry is still at `version`,
+    /// returns the entry as it is afterwards
+    pub async fn patch_kombucha_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+        version: i32,
+        patch: &EntryPatch,
+    ) -> Result<Entry> {
+        self.check_entry_patch(kombucha_id, patch).await?;
+
+        self.store
+            .patch_entry(kombucha_id, entry_id, version, patch)
+            .await?;
 
-        transaction.execute(query).await?;
+        self.store
+            .get_entry(kombucha_id, entry_id)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found(format!(
+                    "Entry {} of kombucha {}",
+                    entry_id, kombucha_id
+                ))
+            })
+    }
 
-        for entry in &kombucha.entries {
-            let query = sqlx::query("UPDATE kombucha_entry SET (added, content) = ($1, $2) WHERE id = $3")
-                .bind(&entry.added)
-                .bind(&entry.content)
-                .bind(entry.id);
+    /// A kombucha can't be added after its first entry, taking the time of
+    /// the entries that are `updated` along with it
+    async fn check_kombucha_added(
+        &self,
+        id: KombuchaId,
+        added: DateTime<Utc>,
+        updated: &[Entry],
+    ) -> Result<()> {
+        let first_entry = self
+            .store
+            .get_entries(id)
+            .await?
+            .iter()
+            .map(|entry| {
+                updated
+                    .iter()
+                    .find(|updated| updated.id == entry.id)
+                    .unwrap_or(entry)
+                    .added
+            })
+            .min();
 
-            transaction.execute(query).await?;
+        match first_entry {
+            Some(first_entry) if added > first_entry => {
+                Err(Error::Validation(format!(
+                    "Kombucha can't be added after its first entry ({})",
+                    first_entry
+                )))
+            }
+            _ => Ok(()),
         }
+    }
 
-        transaction.commit().await?;
+    /// An entry can't predate its kombucha
+    async fn check_entry_patch(
+        &self,
+        kombucha_id: KombuchaId,
+        patch: &EntryPatch,
+    ) -> Result<()> {
+        let kombucha =
+            self.store.get_kombucha_row(kombucha_id).await?.ok_or_else(
+                || Error::not_found(format!("Kombucha {}", kombucha_id)),
+            )?;
+
+        match patch.added {
+            Some(added) if added < kombucha.added => {
+                Err(Error::Validation(format!(
+                    "Entry can't be added before its kombucha ({})",
+                    kombucha.added
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// A changed status has to be one the stored one can move to
+    pub async fn update_kombucha_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &Fermentation,
+    ) -> Result<()> {
+        let stored = self
+            .store
+            .get_fermentation(kombucha_id, fermentation.id)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found(format!(
+                    "Fermentation {} of kombucha {}",
+                    fermentation.id, kombucha_id
+                ))
+            })?;
+        if stored.status != fermentation.status {
+            stored
+                .status
+                .transition_to(fermentation.status)
+                .map_err(|err| Error::Validation(err.to_string()))?;
+        }
+
+        self.store
+            .update_fermentation(kombucha_id, fermentation)
+            .await
+    }
+
+    /// Closes the current fermentation of a kombucha and opens one in the
+    /// next stage, terminal stages are opened already closed. Without an
+    /// estimated end the new stage lasts as long as the recipe plans it to
+    pub async fn advance_kombucha_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        advance: &AdvanceFermentation,
+    ) -> Result<Fermentation> {
+        let mut advance = advance.clone();
+        if advance.est_end_date.is_none() {
+            let current = self.last_fermentation(kombucha_id).await?;
+            let status = next_status(
+                current.map(|current| current.status),
+                advance.status,
+            )?;
+            let planned_days = self
+                .store
+                .get_recipe(kombucha_id)
+                .await?
+                .and_then(|recipe| recipe.planned_days(status));
+
+            if let Some(days) = planned_days.filter(|_| !status.is_terminal()) {
+                let now = Utc::now();
+                // Should another advance get in first, the store turns this
+                // one down rather than estimating the wrong stage
+                advance.status = Some(status);
+                advance.start_date = Some(now);
+                advance.est_end_date = Some(now + Duration::days(days.into()));
+            }
+        }
+
+        self.store.advance_fermentation(kombucha_id, &advance).await
+    }
+
+    pub async fn update_kombucha_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        self.check_measurement(
+            kombucha_id,
+            measurement.kind,
+            measurement.value,
+            &measurement.unit,
+            measurement.fermentation_id,
+        )
+        .await?;
+
+        self.store
+            .update_measurement(kombucha_id, measurement)
+            .await
+    }
+
+    /// Saves the recipe of a kombucha in place of the one it had, returns
+    /// what might give a poor brew about it
+    pub async fn set_kombucha_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+        recipe: &Recipe,
+    ) -> Result<Vec<RecipeWarning>> {
+        recipe
+            .check()
+            .map_err(|err| Error::Validation(err.to_string()))?;
+
+        self.store.set_recipe(kombucha_id, recipe).await?;
+
+        Ok(recipe.warnings())
+    }
+
+    /// The value has to be plausible in its unit and the fermentation, if
+    /// any, has to be one of the kombucha's
+    pub(super) async fn check_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        kind: MeasurementKind,
+        value: f64,
+        unit: &str,
+        fermentation_id: Option<FermentationId>,
+    ) -> Result<()> {
+        kind.check(value, unit)
+            .map_err(|err| Error::Validation(err.to_string()))?;
+
+        self.store
+            .get_kombucha_row(kombucha_id)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found(format!("Kombucha {}", kombucha_id))
+            })?;
+
+        if let Some(fermentation_id) = fermentation_id {
+            if self
+                .store
+                .get_fermentation(kombucha_id, fermentation_id)
+                .await?
+                .is_none()
+            {
+                return Err(Error::Validation(format!(
+                    "Fermentation {} isn't one of kombucha {}",
+                    fermentation_id, kombucha_id
+                )));
+            }
+        }
 
         Ok(())
     }
+
+    /// The fermentation the kombucha is in, or was in last
+    pub(super) async fn last_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Fermentation>> {
+        Ok(self
+            .store
+            .get_fermentations(kombucha_id)
+            .await?
+            .into_iter()
+            .max_by_key(|fermentation| {
+                (fermentation.start_date, fermentation.id)
+            }))
+    }
 }
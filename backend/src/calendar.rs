@@ -0,0 +1,218 @@
+//! RFC 5545 calendar feed with an event at the end of every fermentation
+
+use chrono::{DateTime, Duration, Utc};
+use data_types::{Fermentation, Kombucha};
+
+const PRODID: &str = "-//kombucha-tracker//kombucha-tracker//EN";
+const EVENT_DURATION_MINUTES: i64 = 30;
+const MAX_LINE_OCTETS: usize = 75;
+
+/// Renders a calendar for the given kombuchas, `name` is shown by calendar
+/// apps as the name of the subscribed calendar
+pub fn render(
+    name: &str,
+    kombuchas: &[Kombucha],
+    now: DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+
+    for kombucha in kombuchas {
+        for fermentation in &kombucha.fermentations {
+            lines.extend(event(kombucha, fermentation, now));
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in lines {
+        calendar.push_str(&fold(&line));
+        calendar.push_str("\r\n");
+    }
+
+    calendar
+}
+
+/// An event at the (estimated) end of a fermentation, reminders are only
+/// attached to fermentations that haven't ended yet
+fn event(
+    kombucha: &Kombucha,
+    fermentation: &Fermentation,
+    now: DateTime<Utc>,
+) -> Vec<String> {
+    let date = match (fermentation.end_date, fermentation.est_end_date) {
+        (Some(end_date), _) => end_date,
+        (None, Some(est_end_date)) => est_end_date,
+        (None, None) => return vec![],
+    };
+
+    let name = if kombucha.name.is_empty() {
+        "Kombucha"
+    } else {
+        &kombucha.name
+    };
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        // The fermentation id is stable, so calendar apps update the event
+        // in place when the fermentation changes
+        format!("UID:fermentation-{}@kombucha-tracker", fermentation.id),
+        format!("DTSTAMP:{}", format_date_time(now)),
+        format!("DTSTART:{}", format_date_time(date)),
+        format!(
+            "DTEND:{}",
+            format_date_time(date + Duration::minutes(EVENT_DURATION_MINUTES))
+        ),
+        format!(
+            "SUMMARY:{}",
+            escape(&format!("{}: end of {}", name, fermentation.status))
+        ),
+        format!(
+            "DESCRIPTION:{}",
+            escape(&format!(
+                "{} fermentation of {} started on {}",
+                fermentation.status,
+                name,
+                fermentation.start_date.format("%Y-%m-%d")
+            ))
+        ),
+    ];
+
+    if fermentation.is_active() {
+        lines.push("STATUS:CONFIRMED".to_string());
+        lines.extend(alarm("-P1D", name, fermentation));
+        lines.extend(alarm("PT0S", name, fermentation));
+    }
+
+    lines.push("END:VEVENT".to_string());
+
+    lines
+}
+
+fn alarm(
+    trigger: &str,
+    name: &str,
+    fermentation: &Fermentation,
+) -> Vec<String> {
+    vec![
+        "BEGIN:VALARM".to_string(),
+        "ACTION:DISPLAY".to_string(),
+        format!("TRIGGER:{}", trigger),
+        format!(
+            "DESCRIPTION:{}",
+            escape(&format!("{}: end of {}", name, fermentation.status))
+        ),
+        "END:VALARM".to_string(),
+    ]
+}
+
+fn format_date_time(date: DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Folds lines longer than 75 octets, continuation lines start with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_octets = 0;
+
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+
+        line_octets += c.len_utf8();
+        folded.push(c);
+    }
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape, fold, MAX_LINE_OCTETS};
+
+    #[test]
+    fn escapes_text() {
+        let cases = [
+            ("Green, black", "Green\\, black"),
+            ("Primary; day 3", "Primary\\; day 3"),
+            ("C:\\scoby", "C:\\\\scoby"),
+            ("First line\nsecond line", "First line\\nsecond line"),
+            ("Windows\r\nline", "Windows\\nline"),
+            ("Plain text", "Plain text"),
+        ];
+
+        for &(text, escaped) in &cases {
+            assert_eq!(escape(text), escaped);
+        }
+    }
+
+    fn folded_lines(line: &str) -> Vec<String> {
+        fold(line).split("\r\n").map(str::to_string).collect()
+    }
+
+    #[test]
+    fn leaves_short_lines_alone() {
+        let line = "x".repeat(MAX_LINE_OCTETS);
+
+        assert_eq!(fold(&line), line);
+    }
+
+    #[test]
+    fn folds_long_lines() {
+        let line = "x".repeat(2 * MAX_LINE_OCTETS);
+        let lines = folded_lines(&line);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), MAX_LINE_OCTETS);
+        assert_eq!(lines[1].len(), MAX_LINE_OCTETS);
+        // The space counts towards the continuation line
+        assert_eq!(lines[2], " x");
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        assert_eq!(lines.concat().replace(' ', ""), line);
+    }
+
+    #[test]
+    fn folds_between_multibyte_characters() {
+        // 74 octets, the two byte `é` doesn't fit on the first line anymore
+        let line =
+            format!("{}é{}", "x".repeat(MAX_LINE_OCTETS - 1), "ü".repeat(40));
+        let lines = folded_lines(&line);
+
+        assert_eq!(lines[0], "x".repeat(MAX_LINE_OCTETS - 1));
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(lines[1].starts_with(" é"));
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| line.trim_start_matches(' '))
+                .collect::<String>(),
+            line
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use data_types::{ApiError, ErrorCode};
+use derive_more::Display;
+use sqlx::error::DatabaseError;
+use std::convert::Infallible;
+use warp::{
+    http::StatusCode,
+    reject::{
+        InvalidQuery, LengthRequired, MethodNotAllowed, PayloadTooLarge, Reject,
+    },
+    Rejection, Reply,
+};
+
+#[derive(Debug, Display)]
+pub enum Error {
+    /// A request that couldn't be read at all
+    #[display(fmt = "{}", _0)]
+    BadRequest(String),
+    #[display(fmt = "{}", _0)]
+    NotFound(String),
+    #[display(fmt = "{}", _0)]
+    Validation(String),
+    #[display(fmt = "{}", _0)]
+    Conflict(String),
+    /// An update based on a version that isn't the current one anymore
+    #[display(fmt = "{}", _0)]
+    PreconditionFailed(String),
+    /// An update that didn't say which version it's based on
+    #[display(fmt = "{}", _0)]
+    PreconditionRequired(String),
+    #[display(fmt = "Database error: {}", _0)]
+    Database(sqlx::Error),
+    #[display(fmt = "Internal error: {}", _0)]
+    Internal(anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn not_found(what: impl std::fmt::Display) -> Self {
+        Error::NotFound(format!("{} doesn't exist", what))
+    }
+
+    /// For an update of `version` that matched no row, `current` is the
+    /// version of the row when it exists at all
+    pub fn stale(
+        what: impl std::fmt::Display,
+        version: i32,
+        current: Option<i32>,
+    ) -> Self {
+        match current {
+            Some(current) => Error::PreconditionFailed(format!(
+                "{} was changed, it's at version {} instead of {}",
+                what, current, version
+            )),
+            None => Error::not_found(what),
+        }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Error::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Conflict(_) => StatusCode::CONFLICT,
+            Error::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            Error::PreconditionRequired(_) => StatusCode::PRECONDITION_REQUIRED,
+            Error::Database(_) | Error::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn to_api_error(&self) -> ApiError {
+        let (code, message, details) = match self {
+            Error::BadRequest(message) => {
+                (ErrorCode::BadRequest, message.clone(), None)
+            }
+            Error::NotFound(message) => {
+                (ErrorCode::NotFound, message.clone(), None)
+            }
+            Error::Validation(message) => {
+                (ErrorCode::Validation, message.clone(), None)
+            }
+            Error::Conflict(message) => {
+                (ErrorCode::Conflict, message.clone(), None)
+            }
+            Error::PreconditionFailed(message) => {
+                (ErrorCode::PreconditionFailed, message.clone(), None)
+            }
+            Error::PreconditionRequired(message) => {
+                (ErrorCode::PreconditionRequired, message.clone(), None)
+            }
+            // Only logged, they tell more about the server than the client
+            // should know
+            Error::Database(_) => {
+                (ErrorCode::Database, "Database error".to_string(), None)
+            }
+            Error::Internal(_) => {
+                (ErrorCode::Internal, "Internal error".to_string(), None)
+            }
+        };
+
+        ApiError {
+            code,
+            message,
+            details,
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Reject for Error {}
+
+impl From<sqlx::Error> for Error {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::RowNotFound => {
+                Error::NotFound("Row doesn't exist".to_string())
+            }
+            sqlx::Error::Database(err) if is_foreign_key_violation(&*err) => {
+                Error::NotFound("Referenced row doesn't exist".to_string())
+            }
+            sqlx::Error::Database(err) if is_unique_violation(&*err) => {
+                Error::Conflict("Row already exists".to_string())
+            }
+            err => Error::Database(err),
+        }
+    }
+}
+
+/// Postgres tells by SQLSTATE, SQLite only by the message, its extended
+/// codes aren't known to every version
+fn is_foreign_key_violation(err: &dyn DatabaseError) -> bool {
+    err.code() == Some("23503")
+        || err.message().starts_with("FOREIGN KEY constraint failed")
+}
+
+fn is_unique_violation(err: &dyn DatabaseError) -> bool {
+    err.code() == Some("23505")
+        || err.message().starts_with("UNIQUE constraint failed")
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Error::Internal(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Internal(err.into())
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Error::Internal(err.into())
+    }
+}
+
+/// Renders every rejection as an `ApiError` with a matching status code
+pub async fn handle_rejection(
+    rejection: Rejection,
+) -> std::result::Result<impl Reply, Infallible> {
+    let (status, api_error) = if let Some(err) = rejection.find::<Error>() {
+        if let Error::Database(_) | Error::Internal(_) = err {
+            log::error!("{}", err);
+        }
+
+        (err.status(), err.to_api_error())
+    } else if rejection.is_not_found() {
+        (
+            StatusCode::NOT_FOUND,
+            ApiError {
+                code: ErrorCode::NotFound,
+                message: "Not found".to_string(),
+                details: None,
+            },
+        )
+    } else if let Some(err) =
+        rejection.find::<warp::filters::body::BodyDeserializeError>()
+    {
+        (
+            StatusCode::BAD_REQUEST,
+            ApiError {
+                code: ErrorCode::BadRequest,
+                message: "Invalid request body".to_string(),
+                details: Some(err.to_string()),
+            },
+        )
+    } else if let Some(err) = rejection.find::<InvalidQuery>() {
+        (
+            StatusCode::BAD_REQUEST,
+            ApiError {
+                code: ErrorCode::BadRequest,
+                message: "Invalid query string".to_string(),
+                details: Some(err.to_string()),
+            },
+        )
+    } else if rejection.find::<PayloadTooLarge>().is_some() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError {
+                code: ErrorCode::PayloadTooLarge,
+                message: "Request body is too large".to_string(),
+                details: None,
+            },
+        )
+    } else if rejection.find::<LengthRequired>().is_some() {
+        (
+            StatusCode::LENGTH_REQUIRED,
+            ApiError {
+                code: ErrorCode::BadRequest,
+                message: "Content-Length is required".to_string(),
+                details: None,
+            },
+        )
+    } else if rejection.find::<MethodNotAllowed>().is_some() {
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            ApiError {
+                code: ErrorCode::BadRequest,
+                message: "Method not allowed".to_string(),
+                details: None,
+            },
+        )
+    } else {
+        log::error!("Unhandled rejection: {:?}", rejection);
+
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError {
+                code: ErrorCode::Internal,
+                message: "Internal error".to_string(),
+                details: None,
+            },
+        )
+    };
+
+    Ok(warp::reply::with_status(
+        warp::reply::json(&api_error),
+        status,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use sqlx::{Executor, SqlitePool};
+
+    async fn violation(statement: &str) -> Error {
+        let pool = SqlitePool::new("sqlite::memory:").await.unwrap();
+        let mut connection = pool.acquire().await.unwrap();
+        connection
+            .execute(
+                "PRAGMA foreign_keys = ON;
+                CREATE TABLE kombucha (id INTEGER PRIMARY KEY, name TEXT UNIQUE);
+                CREATE TABLE entry (kombucha_id INTEGER REFERENCES kombucha(id));
+                INSERT INTO kombucha (id, name) VALUES (1, 'Green');",
+            )
+            .await
+            .unwrap();
+
+        connection.execute(statement).await.unwrap_err().into()
+    }
+
+    #[tokio::test]
+    async fn maps_constraint_violations() {
+        let err =
+            violation("INSERT INTO entry (kombucha_id) VALUES (42)").await;
+        assert!(matches!(err, Error::NotFound(_)), "{:?}", err);
+
+        let err =
+            violation("INSERT INTO kombucha (id, name) VALUES (2, 'Green')")
+                .await;
+        assert!(matches!(err, Error::Conflict(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn hides_database_details() {
+        let err = violation("SELECT * FROM nothing").await;
+        assert!(matches!(err, Error::Database(_)), "{:?}", err);
+
+        let api_error = err.to_api_error();
+        assert_eq!(api_error.message, "Database error");
+        assert_eq!(api_error.details, None);
+        assert_eq!(
+            Error::Internal(anyhow::anyhow!("disk full"))
+                .to_api_error()
+                .details,
+            None
+        );
+    }
+}
@@ -1,16 +1,44 @@
-use crate::AppType;
-use data_types::{EntryId, Kombucha, KombuchaId};
-use warp::Rejection;
+use crate::{
+    calendar,
+    error::{self, Error},
+    AppType,
+};
+use bytes::Buf;
+use chrono::Utc;
+use data_types::{
+    AdvanceFermentation, Entry, EntryId, EntryPatch, Fermentation,
+    FermentationId, ImageId, Kombucha, KombuchaId, KombuchaPatch,
+    KombuchaQuery, Measurement, MeasurementId, NewFermentation, NewKombucha,
+    NewMeasurement, NewRecipeTemplate, Recipe, SearchQuery, SeriesQuery,
+    TemplateId,
+};
+use futures::TryStreamExt;
+use warp::{
+    http::{header, Response},
+    multipart::{FormData, Part},
+    Rejection,
+};
 
 // GET
 
-pub async fn get_all_kombuchas(
+pub async fn list_kombuchas(
     app: AppType,
+    query: KombuchaQuery,
 ) -> Result<impl warp::Reply, Rejection> {
-    app.get_all_kombuchas()
+    app.list_kombuchas(&query)
+        .await
+        .map(|page| warp::reply::json(&page))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn search(
+    app: AppType,
+    query: SearchQuery,
+) -> Result<impl warp::Reply, Rejection> {
+    app.search(&query)
         .await
-        .map(|kombuchas| warp::reply::json(&kombuchas))
-        .map_err(|_| warp::reject::not_found())
+        .map(|hits| warp::reply::json(&hits))
+        .map_err(warp::reject::custom)
 }
 
 pub async fn get_kombucha(
@@ -19,8 +47,11 @@ pub async fn get_kombucha(
 ) -> Result<impl warp::Reply, Rejection> {
     app.get_kombucha(id)
         .await
-        .map(|kombucha| warp::reply::json(&kombucha))
-        .map_err(|_| warp::reject::not_found())
+        .and_then(|kombucha| found(kombucha, "kombucha"))
+        .map(|kombucha| {
+            with_etag(warp::reply::json(&kombucha), kombucha.version)
+        })
+        .map_err(warp::reject::custom)
 }
 
 pub async fn get_kombucha_entries(
@@ -30,7 +61,7 @@ pub async fn get_kombucha_entries(
     app.get_kombucha_entries(id)
         .await
         .map(|entries| warp::reply::json(&entries))
-        .map_err(|_| warp::reject::not_found())
+        .map_err(warp::reject::custom)
 }
 
 pub async fn get_kombucha_entry(
@@ -40,31 +71,348 @@ pub async fn get_kombucha_entry(
 ) -> Result<impl warp::Reply, Rejection> {
     app.get_kombucha_entry(id, entry_id)
         .await
-        .map(|entry| warp::reply::json(&entry))
-        .map_err(|_| warp::reject::not_found())
+        .and_then(|entry| found(entry, "entry"))
+        .map(|entry| with_etag(warp::reply::json(&entry), entry.version))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_fermentations(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_fermentations(id)
+        .await
+        .map(|fermentations| warp::reply::json(&fermentations))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_fermentation(
+    app: AppType,
+    id: KombuchaId,
+    fermentation_id: FermentationId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_fermentation(id, fermentation_id)
+        .await
+        .and_then(|fermentation| found(fermentation, "fermentation"))
+        .map(|fermentation| warp::reply::json(&fermentation))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_measurements(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_measurements(id)
+        .await
+        .map(|measurements| warp::reply::json(&measurements))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_measurement(
+    app: AppType,
+    id: KombuchaId,
+    measurement_id: MeasurementId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_measurement(id, measurement_id)
+        .await
+        .and_then(|measurement| found(measurement, "measurement"))
+        .map(|measurement| warp::reply::json(&measurement))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_measurement_series(
+    app: AppType,
+    id: KombuchaId,
+    query: SeriesQuery,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_measurement_series(id, &query)
+        .await
+        .map(|series| warp::reply::json(&series))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_recipe(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_recipe(id)
+        .await
+        .and_then(|recipe| found(recipe, "recipe"))
+        .map(|recipe| warp::reply::json(&recipe))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_recipe_templates(
+    app: AppType,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_recipe_templates()
+        .await
+        .map(|templates| warp::reply::json(&templates))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_recipe_template(
+    app: AppType,
+    id: TemplateId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_recipe_template(id)
+        .await
+        .and_then(|template| found(template, "template"))
+        .map(|template| warp::reply::json(&template))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_images(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha_images(id)
+        .await
+        .map(|images| warp::reply::json(&images))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_image(
+    app: AppType,
+    id: KombuchaId,
+    image_id: ImageId,
+) -> Result<impl warp::Reply, Rejection> {
+    image_response(app.get_kombucha_image_data(id, image_id, false).await)
+}
+
+pub async fn get_kombucha_image_thumbnail(
+    app: AppType,
+    id: KombuchaId,
+    image_id: ImageId,
+) -> Result<impl warp::Reply, Rejection> {
+    image_response(app.get_kombucha_image_data(id, image_id, true).await)
+}
+
+fn image_response(
+    image: error::Result<Option<(String, Vec<u8>)>>,
+) -> Result<Response<Vec<u8>>, Rejection> {
+    let (content_type, data) = image
+        .and_then(|image| found(image, "image"))
+        .map_err(warp::reject::custom)?;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        // Images are never modified, only deleted
+        .header(header::CACHE_CONTROL, "public, max-age=31536000")
+        .body(data)
+        .map_err(|err| warp::reject::custom(Error::Internal(err.into())))
+}
+
+fn found<T>(value: Option<T>, what: &str) -> error::Result<T> {
+    value.ok_or_else(|| Error::not_found(what))
+}
+
+pub async fn get_calendar(app: AppType) -> Result<impl warp::Reply, Rejection> {
+    app.get_all_kombuchas()
+        .await
+        .map(|kombuchas| {
+            calendar_response(calendar::render(
+                "Kombuchas",
+                &kombuchas,
+                Utc::now(),
+            ))
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn get_kombucha_calendar(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.get_kombucha(id)
+        .await
+        .and_then(|kombucha| found(kombucha, "kombucha"))
+        .map(|kombucha| {
+            calendar_response(calendar::render(
+                &kombucha.name,
+                std::slice::from_ref(&kombucha),
+                Utc::now(),
+            ))
+        })
+        .map_err(warp::reject::custom)
+}
+
+fn calendar_response(calendar: String) -> impl warp::Reply {
+    warp::reply::with_header(
+        calendar,
+        header::CONTENT_TYPE,
+        "text/calendar; charset=utf-8",
+    )
+}
+
+/// Versions are handed out as strong ETags, e.g. `"3"`
+fn with_etag(reply: impl warp::Reply, version: i32) -> impl warp::Reply {
+    warp::reply::with_header(reply, header::ETAG, format!("\"{}\"", version))
+}
+
+/// The version an update is based on, from its `If-Match` header. Anything
+/// but an ETag handed out by `with_etag` can't match
+fn if_match(if_match: Option<String>) -> Result<i32, Rejection> {
+    let if_match = if_match.ok_or_else(|| {
+        warp::reject::custom(Error::PreconditionRequired(
+            "Updates need the ETag they're based on in `If-Match`".to_string(),
+        ))
+    })?;
+
+    if_match
+        .trim()
+        .strip_prefix('"')
+        .and_then(|etag| etag.strip_suffix('"'))
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(|| {
+            warp::reject::custom(Error::PreconditionFailed(format!(
+                "{} isn't a current ETag",
+                if_match
+            )))
+        })
 }
 
 // UPDATE
 
 pub async fn update_kombucha(
     app: AppType,
+    version: Option<String>,
     kombucha: Kombucha,
 ) -> Result<impl warp::Reply, Rejection> {
+    let kombucha = Kombucha {
+        version: if_match(version)?,
+        ..kombucha
+    };
+
     app.update_kombucha(&kombucha)
+        .await
+        .map(|version| with_etag(warp::reply::reply(), version))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn update_kombucha_entry(
+    app: AppType,
+    id: KombuchaId,
+    entry_id: EntryId,
+    version: Option<String>,
+    entry: Entry,
+) -> Result<impl warp::Reply, Rejection> {
+    let entry = Entry {
+        id: entry_id,
+        version: if_match(version)?,
+        ..entry
+    };
+
+    app.update_kombucha_entry(id, &entry)
+        .await
+        .map(|version| with_etag(warp::reply::reply(), version))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn patch_kombucha(
+    app: AppType,
+    id: KombuchaId,
+    version: Option<String>,
+    patch: KombuchaPatch,
+) -> Result<impl warp::Reply, Rejection> {
+    app.patch_kombucha(id, if_match(version)?, &patch)
+        .await
+        .map(|kombucha| {
+            with_etag(warp::reply::json(&kombucha), kombucha.version)
+        })
+        .map_err(warp::reject::custom)
+}
+
+pub async fn patch_kombucha_entry(
+    app: AppType,
+    id: KombuchaId,
+    entry_id: EntryId,
+    version: Option<String>,
+    patch: EntryPatch,
+) -> Result<impl warp::Reply, Rejection> {
+    app.patch_kombucha_entry(id, entry_id, if_match(version)?, &patch)
+        .await
+        .map(|entry| with_etag(warp::reply::json(&entry), entry.version))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn update_kombucha_fermentation(
+    app: AppType,
+    id: KombuchaId,
+    fermentation_id: FermentationId,
+    fermentation: Fermentation,
+) -> Result<impl warp::Reply, Rejection> {
+    let fermentation = Fermentation {
+        id: fermentation_id,
+        ..fermentation
+    };
+
+    app.update_kombucha_fermentation(id, &fermentation)
         .await
         .map(|_| warp::reply::reply())
-        .map_err(|_| warp::reject::not_found())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn advance_kombucha_fermentation(
+    app: AppType,
+    kombucha_id: KombuchaId,
+    advance: AdvanceFermentation,
+) -> Result<impl warp::Reply, Rejection> {
+    app.advance_kombucha_fermentation(kombucha_id, &advance)
+        .await
+        .map(|fermentation| warp::reply::json(&fermentation))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn update_kombucha_measurement(
+    app: AppType,
+    id: KombuchaId,
+    measurement_id: MeasurementId,
+    measurement: Measurement,
+) -> Result<impl warp::Reply, Rejection> {
+    let measurement = Measurement {
+        id: measurement_id,
+        ..measurement
+    };
+
+    app.update_kombucha_measurement(id, &measurement)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+/// Answers with the warnings about the recipe, it's saved regardless
+pub async fn update_kombucha_recipe(
+    app: AppType,
+    id: KombuchaId,
+    recipe: Recipe,
+) -> Result<impl warp::Reply, Rejection> {
+    app.set_kombucha_recipe(id, &recipe)
+        .await
+        .map(|warnings| warp::reply::json(&warnings))
+        .map_err(warp::reject::custom)
 }
 
 // CREATE
 
 pub async fn create_kombucha(
     app: AppType,
+    new_kombucha: NewKombucha,
 ) -> Result<impl warp::Reply, Rejection> {
-    app.create_new_kombucha()
+    app.create_new_kombucha(&new_kombucha)
         .await
         .map(|new_kombucha_id| warp::reply::json(&new_kombucha_id))
-        .map_err(|_| warp::reject::not_found())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn create_recipe_template(
+    app: AppType,
+    template: NewRecipeTemplate,
+) -> Result<impl warp::Reply, Rejection> {
+    app.create_recipe_template(&template)
+        .await
+        .map(|id| warp::reply::json(&id))
+        .map_err(warp::reject::custom)
 }
 
 pub async fn create_kombucha_entry(
@@ -74,7 +422,96 @@ pub async fn create_kombucha_entry(
     app.create_new_kombucha_entry(kombucha_id)
         .await
         .map(|id| warp::reply::json(&id))
-        .map_err(|_| warp::reject::not_found())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn create_kombucha_fermentation(
+    app: AppType,
+    kombucha_id: KombuchaId,
+    fermentation: NewFermentation,
+) -> Result<impl warp::Reply, Rejection> {
+    app.create_new_kombucha_fermentation(kombucha_id, &fermentation)
+        .await
+        .map(|id| warp::reply::json(&id))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn create_kombucha_measurement(
+    app: AppType,
+    kombucha_id: KombuchaId,
+    measurement: NewMeasurement,
+) -> Result<impl warp::Reply, Rejection> {
+    app.create_new_kombucha_measurement(kombucha_id, &measurement)
+        .await
+        .map(|id| warp::reply::json(&id))
+        .map_err(warp::reject::custom)
+}
+
+/// Expects a `file` part with the image and an optional `entry_id` part
+/// linking the image to an entry, the type of the image is detected from its
+/// contents
+pub async fn create_kombucha_image(
+    app: AppType,
+    kombucha_id: KombuchaId,
+    form: FormData,
+) -> Result<impl warp::Reply, Rejection> {
+    let parts: Vec<Part> =
+        form.try_collect().await.map_err(|err| invalid_form(&err))?;
+
+    let mut file = None;
+    let mut entry_id = None;
+
+    for part in parts {
+        match part.name() {
+            "file" => file = Some(read_part(part).await?),
+            "entry_id" => entry_id = parse_entry_id(&read_part(part).await?)?,
+            _ => (),
+        }
+    }
+
+    let data = file.ok_or_else(|| {
+        warp::reject::custom(Error::Validation(
+            "Missing `file` part".to_string(),
+        ))
+    })?;
+
+    app.create_kombucha_image(kombucha_id, entry_id, data)
+        .await
+        .map(|id| warp::reply::json(&id))
+        .map_err(warp::reject::custom)
+}
+
+async fn read_part(part: Part) -> Result<Vec<u8>, Rejection> {
+    part.stream()
+        .try_fold(Vec::new(), |mut data, buf| async move {
+            data.extend_from_slice(buf.bytes());
+            Ok(data)
+        })
+        .await
+        .map_err(|err| invalid_form(&err))
+}
+
+/// An empty `entry_id` part ties the image to no entry
+fn parse_entry_id(data: &[u8]) -> Result<Option<EntryId>, Rejection> {
+    let id = String::from_utf8_lossy(data);
+    let id = id.trim();
+    if id.is_empty() {
+        return Ok(None);
+    }
+
+    id.parse().map(Some).map_err(|_| {
+        warp::reject::custom(Error::Validation(format!(
+            "Invalid entry_id `{}`",
+            id
+        )))
+    })
+}
+
+fn invalid_form(err: &warp::Error) -> Rejection {
+    warp::reject::custom(Error::Validation(format!(
+        "Invalid multipart form: {}",
+        err
+    )))
 }
 
 // DELETE
@@ -87,7 +524,7 @@ pub async fn delete_kombucha_entry(
     app.delete_kombucha_entry(id, entry_id)
         .await
         .map(|_| warp::reply::reply())
-        .map_err(|_| warp::reject::not_found())
+        .map_err(warp::reject::custom)
 }
 
 pub async fn delete_kombucha(
@@ -97,5 +534,99 @@ pub async fn delete_kombucha(
     app.delete_kombucha(id)
         .await
         .map(|_| warp::reply::reply())
-        .map_err(|_| warp::reject::not_found())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_kombucha_fermentation(
+    app: AppType,
+    id: KombuchaId,
+    fermentation_id: FermentationId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.delete_kombucha_fermentation(id, fermentation_id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_kombucha_measurement(
+    app: AppType,
+    id: KombuchaId,
+    measurement_id: MeasurementId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.delete_kombucha_measurement(id, measurement_id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_kombucha_recipe(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.delete_kombucha_recipe(id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_recipe_template(
+    app: AppType,
+    id: TemplateId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.delete_recipe_template(id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn delete_kombucha_image(
+    app: AppType,
+    id: KombuchaId,
+    image_id: ImageId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.delete_kombucha_image(id, image_id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+// TRASH
+
+pub async fn get_trash(app: AppType) -> Result<impl warp::Reply, Rejection> {
+    app.get_trash()
+        .await
+        .map(|trash| warp::reply::json(&trash))
+        .map_err(warp::reject::custom)
+}
+
+pub async fn restore_kombucha(
+    app: AppType,
+    id: KombuchaId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.restore_kombucha(id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn restore_kombucha_entry(
+    app: AppType,
+    id: KombuchaId,
+    entry_id: EntryId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.restore_kombucha_entry(id, entry_id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
+}
+
+pub async fn restore_kombucha_fermentation(
+    app: AppType,
+    id: KombuchaId,
+    fermentation_id: FermentationId,
+) -> Result<impl warp::Reply, Rejection> {
+    app.restore_kombucha_fermentation(id, fermentation_id)
+        .await
+        .map(|_| warp::reply::reply())
+        .map_err(warp::reject::custom)
 }
@@ -1,10 +1,17 @@
 use std::sync::Arc;
 
 mod app;
+mod calendar;
+mod error;
 mod handlers;
+pub mod migrate;
 mod routes;
+mod series;
+mod store;
 
 pub use self::app::App;
+pub use self::error::Error;
 pub use self::routes::routes;
+pub use self::store::{KombuchaStore, MemoryStore, PostgresStore, SqliteStore};
 
 type AppType = Arc<App>;
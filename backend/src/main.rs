@@ -1,22 +1,152 @@
-use kombucha_tracker_server::{routes, App};
-use sqlx::postgres::PgPool;
-use std::{env, sync::Arc};
+#![recursion_limit = "256"]
+
+use kombucha_tracker_server::{
+    migrate::{self, Migrate},
+    routes, App, KombuchaStore, PostgresStore, SqliteStore,
+};
+use sqlx::{PgPool, SqlitePool};
+use std::{env, sync::Arc, time::Duration};
+use structopt::StructOpt;
 
 const PORT: u16 = 8080;
+/// How long deleted kombuchas, entries and fermentations can be restored
+const DEFAULT_TRASH_RETENTION_DAYS: i64 = 30;
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum Command {
+    /// Applies pending migrations and serves the app, the default
+    Serve,
+    /// Applies pending migrations, or reverts the latest one, and exits
+    Migrate {
+        #[structopt(subcommand)]
+        direction: Option<Direction>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(rename_all = "kebab-case")]
+enum Direction {
+    /// Applies every pending migration, the default
+    Up {
+        /// Only lists the migrations that would run
+        #[structopt(long)]
+        dry_run: bool,
+    },
+    /// Reverts the latest applied migration
+    Down {
+        /// Only shows the migration that would be reverted
+        #[structopt(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+struct Opt {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
     dotenv::dotenv().ok();
     pretty_env_logger::init();
 
-    let app_dir = env::var("KOMBUCHA_APP_DIR")?;
+    let command = Opt::from_args().command.unwrap_or(Command::Serve);
+
+    let database_url = env::var("DATABASE_URL")?;
+
+    // `sqlite:kombucha.db` runs on an embedded database file, anything else is
+    // handed to Postgres
+    if database_url.starts_with("sqlite:") {
+        let pool = SqlitePool::builder()
+            .max_size(5) // maximum number of connections in the pool
+            .build(&database_url)
+            .await?;
+
+        run(SqliteStore::new(pool), command).await
+    } else {
+        let pool = PgPool::builder()
+            .max_size(5) // maximum number of connections in the pool
+            .build(&database_url)
+            .await?;
+
+        run(PostgresStore::new(pool), command).await
+    }
+}
+
+async fn run(
+    store: impl KombuchaStore + Migrate + 'static,
+    command: Command,
+) -> Result<(), anyhow::Error> {
+    match command {
+        Command::Serve => {
+            migrate::up(&store, false).await?;
+            serve(store).await
+        }
+        Command::Migrate { direction } => match direction {
+            None => migrate_up(&store, false).await,
+            Some(Direction::Up { dry_run }) => {
+                migrate_up(&store, dry_run).await
+            }
+            Some(Direction::Down { dry_run }) => {
+                migrate_down(&store, dry_run).await
+            }
+        },
+    }
+}
+
+async fn migrate_up(
+    store: &(impl Migrate + Sync),
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    let applied = migrate::up(store, dry_run).await?;
+    if applied.is_empty() {
+        println!("No pending migrations");
+    }
+    for migration in applied {
+        if dry_run {
+            println!("Would apply {}", migration.version);
+        } else {
+            println!("Applied {}", migration.version);
+        }
+    }
+
+    Ok(())
+}
+
+async fn migrate_down(
+    store: &(impl Migrate + Sync),
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    match migrate::down(store, dry_run).await? {
+        Some(migration) if dry_run => {
+            println!("Would revert {}", migration.version)
+        }
+        Some(migration) => println!("Reverted {}", migration.version),
+        None => println!("No applied migrations"),
+    }
+
+    Ok(())
+}
 
-    let pool = PgPool::builder()
-        .max_size(5) // maximum number of connections in the pool
-        .build(&env::var("DATABASE_URL")?)
-        .await?;
+async fn serve(
+    store: impl KombuchaStore + 'static,
+) -> Result<(), anyhow::Error> {
+    let app_dir = env::var("KOMBUCHA_APP_DIR")?;
+    let image_dir =
+        env::var("KOMBUCHA_IMAGE_DIR").unwrap_or_else(|_| "images".to_string());
+    let retention_days = match env::var("KOMBUCHA_TRASH_RETENTION_DAYS") {
+        Ok(days) => days.parse()?,
+        Err(_) => DEFAULT_TRASH_RETENTION_DAYS,
+    };
 
-    let app = Arc::new(App::new(pool));
+    let app = Arc::new(App::new(store, image_dir));
+    tokio::spawn(purge_trash(
+        app.clone(),
+        chrono::Duration::days(retention_days),
+    ));
     let routes = routes(app, app_dir);
 
     log::info!("Listening on port {}", PORT);
@@ -24,3 +154,21 @@ async fn main() -> Result<(), anyhow::Error> {
 
     Ok(())
 }
+
+/// Empties the trash of everything deleted longer than `retention` ago, once
+/// at startup and then every `PURGE_INTERVAL`
+async fn purge_trash(app: Arc<App>, retention: chrono::Duration) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match app.purge_trash(retention).await {
+            Ok(purged) if !purged.is_empty() => {
+                log::info!("Purged {} kombuchas from the trash", purged.len())
+            }
+            Ok(_) => {}
+            Err(err) => log::error!("Failed to purge the trash: {}", err),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Migrations of both databases are embedded into the binary, applied versions
+//! are tracked in the `schema_migration` table of the database itself
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+
+#[derive(Debug)]
+pub struct Migration {
+    pub version: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+macro_rules! migrations {
+    ($dir:literal: $($version:literal),* $(,)?) => {
+        &[$(
+            Migration {
+                version: $version,
+                up: include_str!(concat!(
+                    "../../db/", $dir, "/", $version, "/up.sql"
+                )),
+                down: include_str!(concat!(
+                    "../../db/", $dir, "/", $version, "/down.sql"
+                )),
+            },
+        )*]
+    };
+}
+
+/// Every directory of `db/migrations`, oldest first
+pub const POSTGRES: &[Migration] = migrations!("migrations":
+    "20200417211947_init",
+    "20200421172246_fermentation",
+    "20261018120000_fermentation_stages",
+    "20261018130000_image",
+    "20261018140000_search",
+    "20261018150000_soft_delete",
+    "20261018160000_cascade_delete",
+    "20261018170000_version",
+    "20261018180000_measurement",
+    "20261018190000_recipe",
+    "20261018200000_template",
+    "20261018210000_kombucha_id_indexes",
+    "20261018220000_updated_at",
+    "20261018230000_flavouring",
+);
+
+/// Every directory of `db/sqlite/migrations`, oldest first
+pub const SQLITE: &[Migration] = migrations!("sqlite/migrations":
+    "20200417211947_init",
+    "20200421172246_fermentation",
+    "20261018120000_fermentation_stages",
+    "20261018130000_image",
+    "20261018150000_soft_delete",
+    "20261018160000_cascade_delete",
+    "20261018170000_version",
+    "20261018180000_measurement",
+    "20261018190000_recipe",
+    "20261018200000_template",
+    "20261018210000_kombucha_id_indexes",
+    "20261018220000_updated_at",
+    "20261018230000_flavouring",
+);
+
+/// A database that migrations can be applied to
+#[async_trait]
+pub trait Migrate {
+    /// The migrations embedded for this database, oldest first
+    fn migrations(&self) -> &'static [Migration];
+
+    /// Versions applied so far, creates the tracking table on first use and
+    /// adopts the history of databases migrated with `migrant`
+    async fn applied_versions(&self) -> Result<Vec<String>>;
+
+    /// Runs the `up` script and records the version in one transaction
+    async fn apply(&self, migration: &Migration) -> Result<()>;
+
+    /// Runs the `down` script and forgets the version in one transaction
+    async fn revert(&self, migration: &Migration) -> Result<()>;
+}
+
+/// Where the schema of a database stands compared to the binary
+#[derive(Debug)]
+pub struct Status {
+    pub applied: Vec<&'static Migration>,
+    pub pending: Vec<&'static Migration>,
+    /// Applied versions that this binary doesn't know about, the schema is
+    /// newer than the binary when there are any
+    pub unknown: Vec<String>,
+}
+
+pub async fn status(db: &(impl Migrate + Sync)) -> Result<Status> {
+    let applied_versions = db.applied_versions().await?;
+
+    let (applied, pending) = db.migrations().iter().partition(|migration| {
+        applied_versions.iter().any(|v| v == migration.version)
+    });
+
+    let unknown = applied_versions
+        .into_iter()
+        .filter(|version| {
+            !db.migrations()
+                .iter()
+                .any(|migration| migration.version == version)
+        })
+        .collect();
+
+    Ok(Status {
+        applied,
+        pending,
+        unknown,
+    })
+}
+
+/// Applies every pending migration, refuses to touch a schema that is ahead
+/// of the binary. Returns the migrations that were (or with `dry_run` would
+/// be) applied
+pub async fn up(
+    db: &(impl Migrate + Sync),
+    dry_run: bool,
+) -> Result<Vec<&'static Migration>> {
+    let status = status(db).await?;
+
+    if !status.unknown.is_empty() {
+        return Err(Error::Conflict(format!(
+            "Database schema is ahead of this binary, unknown migrations: {}",
+            status.unknown.join(", ")
+        )));
+    }
+
+    if !dry_run {
+        for migration in &status.pending {
+            log::info!("Applying migration {}", migration.version);
+            db.apply(migration).await?;
+        }
+    }
+
+    Ok(status.pending)
+}
+
+/// Reverts the latest applied migration, returns it or `None` when nothing
+/// was applied
+pub async fn down(
+    db: &(impl Migrate + Sync),
+    dry_run: bool,
+) -> Result<Option<&'static Migration>> {
+    let status = status(db).await?;
+
+    if !status.unknown.is_empty() {
+        return Err(Error::Conflict(format!(
+            "Can't revert {}, this binary doesn't know how",
+            status.unknown.join(", ")
+        )));
+    }
+
+    let latest = match status.applied.last() {
+        Some(latest) => *latest,
+        None => return Ok(None),
+    };
+
+    if !dry_run {
+        log::info!("Reverting migration {}", latest.version);
+        db.revert(latest).await?;
+    }
+
+    Ok(Some(latest))
+}
@@ -1,9 +1,20 @@
 use super::handlers;
-use crate::AppType;
-use data_types::{EntryId, KombuchaId};
+use crate::{
+    error::{self, Error},
+    AppType,
+};
+use data_types::{
+    EntryId, FermentationId, ImageId, KombuchaId, MeasurementId, TemplateId,
+};
+use serde::de::DeserializeOwned;
 use std::convert::Infallible;
 use warp::{Filter, Rejection, Reply};
 
+const MAX_IMAGE_SIZE: u64 = 16 * 1024 * 1024;
+/// Bodies of every other request, a kombucha with all its entries is the
+/// largest of them
+const MAX_JSON_SIZE: u64 = 1024 * 1024;
+
 pub fn routes(
     app: AppType,
     dir: String,
@@ -11,6 +22,7 @@ pub fn routes(
     let api_routes = get_routes(app.clone())
         .or(post_routes(app.clone()))
         .or(put_routes(app.clone()))
+        .or(patch_routes(app.clone()))
         .or(delete_routes(app));
 
     let api_routes = warp::path!("api" / "1" / ..).and(api_routes);
@@ -19,10 +31,11 @@ pub fn routes(
 
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_methods(vec!["GET", "POST", "PUT"]);
+        .allow_methods(vec!["GET", "POST", "PUT", "PATCH"]);
 
     api_routes
         .or(static_routes)
+        .recover(error::handle_rejection)
         .with(cors)
         .with(warp::log("kombucha_tracker_server"))
 }
@@ -38,14 +51,85 @@ fn get_routes(
         .or(base
             .clone()
             .and(warp::path!("kombucha"))
-            .and_then(handlers::get_all_kombuchas))
+            .and(warp::query())
+            .and_then(handlers::list_kombuchas))
         .or(base
             .clone()
             .and(warp::path!("kombucha" / KombuchaId / "entry" / EntryId))
             .and_then(handlers::get_kombucha_entry))
         .or(base
+            .clone()
             .and(warp::path!("kombucha" / KombuchaId / "entry"))
             .and_then(handlers::get_kombucha_entries))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "fermentation" / FermentationId
+            ))
+            .and_then(handlers::get_kombucha_fermentation))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "fermentation"))
+            .and_then(handlers::get_kombucha_fermentations))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "measurement" / "series"
+            ))
+            .and(warp::query())
+            .and_then(handlers::get_kombucha_measurement_series))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "measurement" / MeasurementId
+            ))
+            .and_then(handlers::get_kombucha_measurement))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "measurement"))
+            .and_then(handlers::get_kombucha_measurements))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "recipe"))
+            .and_then(handlers::get_kombucha_recipe))
+        .or(base
+            .clone()
+            .and(warp::path!("template"))
+            .and_then(handlers::get_recipe_templates))
+        .or(base
+            .clone()
+            .and(warp::path!("template" / TemplateId))
+            .and_then(handlers::get_recipe_template))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "image"))
+            .and_then(handlers::get_kombucha_images))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "image" / ImageId))
+            .and_then(handlers::get_kombucha_image))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "image" / ImageId / "thumbnail"
+            ))
+            .and_then(handlers::get_kombucha_image_thumbnail))
+        .or(base
+            .clone()
+            .and(warp::path!("trash"))
+            .and_then(handlers::get_trash))
+        .or(base
+            .clone()
+            .and(warp::path!("search"))
+            .and(warp::query())
+            .and_then(handlers::search))
+        .or(base
+            .clone()
+            .and(warp::path!("calendar.ics"))
+            .and_then(handlers::get_calendar))
+        .or(base
+            .and(warp::path!("kombucha" / KombuchaId / "calendar.ics"))
+            .and_then(handlers::get_kombucha_calendar))
 }
 
 fn post_routes(
@@ -55,10 +139,59 @@ fn post_routes(
 
     base.clone()
         .and(warp::path!("kombucha"))
+        .and(optional_json())
         .and_then(handlers::create_kombucha)
         .or(base
+            .clone()
+            .and(warp::path!("template"))
+            .and(json_body())
+            .and_then(handlers::create_recipe_template))
+        .or(base
+            .clone()
             .and(warp::path!("kombucha" / KombuchaId / "entry"))
             .and_then(handlers::create_kombucha_entry))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "fermentation"))
+            .and(json_body())
+            .and_then(handlers::create_kombucha_fermentation))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "fermentation" / "advance"
+            ))
+            .and(json_body())
+            .and_then(handlers::advance_kombucha_fermentation))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "measurement"))
+            .and(json_body())
+            .and_then(handlers::create_kombucha_measurement))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "restore"))
+            .and_then(handlers::restore_kombucha))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "entry" / EntryId / "restore"
+            ))
+            .and_then(handlers::restore_kombucha_entry))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha"
+                    / KombuchaId
+                    / "fermentation"
+                    / FermentationId
+                    / "restore"
+            ))
+            .and_then(handlers::restore_kombucha_fermentation))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "image"))
+            .and(warp::multipart::form().max_length(MAX_IMAGE_SIZE))
+            .and_then(handlers::create_kombucha_image))
 }
 
 fn put_routes(
@@ -66,9 +199,52 @@ fn put_routes(
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
     let base = warp::put().and(with_app(app));
 
-    base.and(warp::body::json())
+    base.clone()
         .and(warp::path!("kombucha"))
+        .and(warp::header::optional("if-match"))
+        .and(json_body())
         .and_then(handlers::update_kombucha)
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "entry" / EntryId))
+            .and(warp::header::optional("if-match"))
+            .and(json_body())
+            .and_then(handlers::update_kombucha_entry))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "fermentation" / FermentationId
+            ))
+            .and(json_body())
+            .and_then(handlers::update_kombucha_fermentation))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "measurement" / MeasurementId
+            ))
+            .and(json_body())
+            .and_then(handlers::update_kombucha_measurement))
+        .or(base
+            .and(warp::path!("kombucha" / KombuchaId / "recipe"))
+            .and(json_body())
+            .and_then(handlers::update_kombucha_recipe))
+}
+
+fn patch_routes(
+    app: AppType,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let base = warp::patch().and(with_app(app));
+
+    base.clone()
+        .and(warp::path!("kombucha" / KombuchaId))
+        .and(warp::header::optional("if-match"))
+        .and(json_body())
+        .and_then(handlers::patch_kombucha)
+        .or(base
+            .and(warp::path!("kombucha" / KombuchaId / "entry" / EntryId))
+            .and(warp::header::optional("if-match"))
+            .and(json_body())
+            .and_then(handlers::patch_kombucha_entry))
 }
 
 fn delete_routes(
@@ -80,8 +256,32 @@ fn delete_routes(
         .and(warp::path!("kombucha" / KombuchaId))
         .and_then(handlers::delete_kombucha)
         .or(base
+            .clone()
             .and(warp::path!("kombucha" / KombuchaId / "entry" / EntryId))
             .and_then(handlers::delete_kombucha_entry))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "fermentation" / FermentationId
+            ))
+            .and_then(handlers::delete_kombucha_fermentation))
+        .or(base
+            .clone()
+            .and(warp::path!(
+                "kombucha" / KombuchaId / "measurement" / MeasurementId
+            ))
+            .and_then(handlers::delete_kombucha_measurement))
+        .or(base
+            .clone()
+            .and(warp::path!("kombucha" / KombuchaId / "recipe"))
+            .and_then(handlers::delete_kombucha_recipe))
+        .or(base
+            .clone()
+            .and(warp::path!("template" / TemplateId))
+            .and_then(handlers::delete_recipe_template))
+        .or(base
+            .and(warp::path!("kombucha" / KombuchaId / "image" / ImageId))
+            .and_then(handlers::delete_kombucha_image))
 }
 
 fn with_app(
@@ -90,6 +290,42 @@ fn with_app(
     warp::any().map(move || app.clone())
 }
 
+fn json_body<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::body::content_length_limit(MAX_JSON_SIZE).and(warp::body::json())
+}
+
+/// A JSON body that may be left out, an empty body is the default value
+fn optional_json<T: DeserializeOwned + Default + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    let body = warp::body::content_length_limit(MAX_JSON_SIZE)
+        .and(warp::body::bytes());
+    // A request without a body may leave out `Content-Length` too
+    let no_body = warp::header::optional::<u64>("content-length")
+        .and(warp::header::optional::<String>("transfer-encoding"))
+        .and_then(|length: Option<u64>, encoding: Option<String>| async move {
+            match (length, encoding) {
+                (None, None) => Ok(bytes::Bytes::new()),
+                _ => Err(warp::reject()),
+            }
+        });
+
+    body.or(no_body)
+        .unify()
+        .and_then(|body: bytes::Bytes| async move {
+            if body.is_empty() {
+                return Ok(T::default());
+            }
+
+            serde_json::from_slice(&body).map_err(|err| {
+                warp::reject::custom(Error::BadRequest(format!(
+                    "Invalid request body: {}",
+                    err
+                )))
+            })
+        })
+}
+
 fn static_routes(
     dir: String,
 ) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
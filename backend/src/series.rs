@@ -0,0 +1,112 @@
+//! Time series of measurements for charts. Long-running batches collect more
+//! readings than a chart has pixels, so series are downsampled with
+//! Largest-Triangle-Three-Buckets, which keeps peaks and the overall shape
+
+use crate::error::{Error, Result};
+use data_types::{
+    Measurement, MeasurementKind, MeasurementSeries, SeriesPoint, SeriesQuery,
+};
+
+pub const DEFAULT_POINTS: u32 = 200;
+pub const MIN_POINTS: u32 = 3;
+pub const MAX_POINTS: u32 = 1000;
+
+pub fn points(query: &SeriesQuery) -> Result<u32> {
+    match query.points {
+        None => Ok(DEFAULT_POINTS),
+        Some(points) if (MIN_POINTS..=MAX_POINTS).contains(&points) => {
+            Ok(points)
+        }
+        Some(points) => Err(Error::Validation(format!(
+            "Points {} isn't between {} and {}",
+            points, MIN_POINTS, MAX_POINTS
+        ))),
+    }
+}
+
+/// A series for every kind that was measured, in the order of
+/// `MeasurementKind::ALL`. `measurements` have to be sorted oldest first
+pub fn series(
+    measurements: &[Measurement],
+    points: u32,
+) -> Vec<MeasurementSeries> {
+    MeasurementKind::ALL
+        .iter()
+        .filter_map(|&kind| {
+            let readings: Vec<_> = measurements
+                .iter()
+                .filter(|measurement| measurement.kind == kind)
+                .map(|measurement| SeriesPoint {
+                    taken_at: measurement.taken_at,
+                    value: kind
+                        .to_default_unit(measurement.value, &measurement.unit),
+                })
+                .collect();
+
+            if readings.is_empty() {
+                return None;
+            }
+
+            Some(MeasurementSeries {
+                kind,
+                unit: kind.default_unit().to_string(),
+                total: readings.len() as u32,
+                points: downsample(&readings, points as usize),
+            })
+        })
+        .collect()
+}
+
+/// Keeps the first and the last point and from every bucket in between the
+/// one forming the largest triangle with the point kept before it and the
+/// average of the next bucket
+fn downsample(points: &[SeriesPoint], threshold: usize) -> Vec<SeriesPoint> {
+    if threshold >= points.len() || threshold < MIN_POINTS as usize {
+        return points.to_vec();
+    }
+
+    let start = points[0].taken_at;
+    let x = |point: &SeriesPoint| {
+        (point.taken_at - start).num_milliseconds() as f64
+    };
+
+    let every = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let bucket_start = |bucket: usize| (bucket as f64 * every) as usize + 1;
+
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(points[0]);
+    let mut previous = 0;
+
+    for bucket in 0..threshold - 2 {
+        let next = &points[bucket_start(bucket + 1)
+            ..bucket_start(bucket + 2).min(points.len())];
+        let next_x = next.iter().map(x).sum::<f64>() / next.len() as f64;
+        let next_y = next.iter().map(|point| point.value).sum::<f64>()
+            / next.len() as f64;
+
+        let (previous_x, previous_y) =
+            (x(&points[previous]), points[previous].value);
+        let area = |point: &SeriesPoint| {
+            ((previous_x - next_x) * (point.value - previous_y)
+                - (previous_x - x(point)) * (next_y - previous_y))
+                .abs()
+        };
+
+        let (largest, _) = (bucket_start(bucket)..bucket_start(bucket + 1))
+            .map(|i| (i, area(&points[i])))
+            .fold((previous, -1.0), |largest, candidate| {
+                if candidate.1 > largest.1 {
+                    candidate
+                } else {
+                    largest
+                }
+            });
+
+        sampled.push(points[largest]);
+        previous = largest;
+    }
+
+    sampled.push(points[points.len() - 1]);
+
+    sampled
+}
@@ -0,0 +1,303 @@
+//! Paging through summaries of kombuchas. The SQL is shared by both databases,
+//! they only differ in placeholders and in how dates are bound
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use data_types::{
+    FermentationId, FermentationStatus, KombuchaId, KombuchaPage,
+    KombuchaQuery, KombuchaSort, KombuchaSummary, Milestone,
+};
+use std::fmt::Write;
+
+pub const DEFAULT_LIMIT: u32 = 50;
+pub const MAX_LIMIT: u32 = 200;
+
+/// Columns every store selects for a summary, `s` is the latest fermentation
+/// and `m` the active one the next milestone comes from. Deleted rows don't
+/// count
+const SELECT_SUMMARIES: &str = "\
+SELECT k.id, k.name, k.added, s.status, m.id, m.status, m.est_end_date, \
+(SELECT COUNT(*) FROM kombucha_entry e WHERE e.kombucha_id = k.id AND e.deleted_at IS NULL) \
+FROM kombucha k \
+LEFT JOIN kombucha_fermentation s ON s.id = (\
+SELECT id FROM kombucha_fermentation WHERE kombucha_id = k.id AND deleted_at IS NULL \
+ORDER BY start_date DESC, id DESC LIMIT 1) \
+LEFT JOIN kombucha_fermentation m ON m.est_end_date IS NOT NULL AND m.id = (\
+SELECT id FROM kombucha_fermentation WHERE kombucha_id = k.id AND end_date IS NULL AND deleted_at IS NULL \
+ORDER BY start_date DESC, id DESC LIMIT 1)";
+
+pub fn limit(query: &KombuchaQuery) -> Result<u32> {
+    match query.limit {
+        None => Ok(DEFAULT_LIMIT),
+        Some(limit) if (1..=MAX_LIMIT).contains(&limit) => Ok(limit),
+        Some(limit) => Err(Error::Validation(format!(
+            "Limit {} isn't between 1 and {}",
+            limit, MAX_LIMIT
+        ))),
+    }
+}
+
+/// Where a summary falls in the requested order
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortKey {
+    Name(String),
+    Added(DateTime<Utc>),
+    Due(DateTime<Utc>),
+    /// Sorts after every `Due`
+    NotDue,
+}
+
+/// The last summary of a page, handed out hex encoded so that clients treat
+/// it as opaque and it needs no escaping in a query string
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub key: SortKey,
+    pub id: KombuchaId,
+}
+
+impl Cursor {
+    pub fn of(sort: KombuchaSort, summary: &KombuchaSummary) -> Self {
+        let key = match sort {
+            KombuchaSort::Name => SortKey::Name(summary.name.clone()),
+            KombuchaSort::Added => SortKey::Added(summary.added),
+            KombuchaSort::NextMilestone => match &summary.next_milestone {
+                Some(milestone) => SortKey::Due(milestone.date),
+                None => SortKey::NotDue,
+            },
+        };
+
+        Self {
+            key,
+            id: summary.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let text = match &self.key {
+            SortKey::Name(name) => format!("name:{}:{}", self.id, name),
+            SortKey::Added(date) => {
+                format!("added:{}:{}", self.id, text(*date))
+            }
+            SortKey::Due(date) => format!("due:{}:{}", self.id, text(*date)),
+            SortKey::NotDue => format!("not_due:{}:", self.id),
+        };
+
+        text.bytes().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+    }
+
+    /// Fails when the cursor is malformed or came from another sort
+    pub fn decode(sort: KombuchaSort, cursor: &str) -> Result<Self> {
+        let invalid =
+            || Error::Validation(format!("Invalid cursor {}", cursor));
+
+        let bytes = (0..cursor.len())
+            .step_by(2)
+            .map(|i| {
+                cursor
+                    .get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        let text = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        let mut parts = text.splitn(3, ':');
+        let (kind, id, value) = match (parts.next(), parts.next(), parts.next())
+        {
+            (Some(kind), Some(id), Some(value)) => (kind, id, value),
+            _ => return Err(invalid()),
+        };
+        let id = id.parse::<KombuchaId>().map_err(|_| invalid())?;
+        let date = || {
+            DateTime::parse_from_rfc3339(value)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|_| invalid())
+        };
+
+        let key = match (sort, kind) {
+            (KombuchaSort::Name, "name") => SortKey::Name(value.to_string()),
+            (KombuchaSort::Added, "added") => SortKey::Added(date()?),
+            (KombuchaSort::NextMilestone, "due") => SortKey::Due(date()?),
+            (KombuchaSort::NextMilestone, "not_due") => SortKey::NotDue,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self { key, id })
+    }
+}
+
+/// Turns the `limit + 1` summaries a store fetched into a page, the extra one
+/// only tells whether there's a next page
+pub fn page(
+    mut summaries: Vec<KombuchaSummary>,
+    sort: KombuchaSort,
+    limit: u32,
+) -> KombuchaPage {
+    let next_cursor = if summaries.len() > limit as usize {
+        summaries.truncate(limit as usize);
+        summaries
+            .last()
+            .map(|summary| Cursor::of(sort, summary).encode())
+    } else {
+        None
+    };
+
+    KombuchaPage {
+        kombuchas: summaries,
+        next_cursor,
+    }
+}
+
+/// A value bound to the query built by `summaries_sql`, in order
+#[derive(Debug)]
+pub enum Param {
+    Status(FermentationStatus),
+    Text(String),
+    Date(DateTime<Utc>),
+    Id(KombuchaId),
+    Limit(i64),
+}
+
+/// Selects `limit + 1` summaries matching the query, after the cursor and in
+/// the requested order. `placeholder` is the prefix of numbered parameters,
+/// `$` for Postgres and `?` for SQLite
+pub fn summaries_sql(
+    query: &KombuchaQuery,
+    cursor: Option<&Cursor>,
+    limit: u32,
+    placeholder: char,
+    like: &str,
+) -> (String, Vec<Param>) {
+    let mut params = Vec::new();
+    let mut param = |value| {
+        params.push(value);
+        format!("{}{}", placeholder, params.len())
+    };
+
+    let mut conditions = vec!["k.deleted_at IS NULL".to_string()];
+
+    if let Some(status) = query.status {
+        conditions.push(format!("s.status = {}", param(Param::Status(status))));
+    }
+    if let Some(q) = &query.q {
+        conditions.push(format!(
+            "k.name {} {} ESCAPE '\\'",
+            like,
+            param(Param::Text(like_pattern(q)))
+        ));
+    }
+    if let Some(added_after) = query.added_after {
+        conditions
+            .push(format!("k.added > {}", param(Param::Date(added_after))));
+    }
+    if let Some(Cursor { key, id }) = cursor {
+        let condition = match key {
+            SortKey::Name(name) => {
+                let name = param(Param::Text(name.clone()));
+                let id = param(Param::Id(*id));
+                format!(
+                    "(k.name > {0} OR (k.name = {0} AND k.id > {1}))",
+                    name, id
+                )
+            }
+            SortKey::Added(added) => {
+                let added = param(Param::Date(*added));
+                let id = param(Param::Id(*id));
+                format!(
+                    "(k.added > {0} OR (k.added = {0} AND k.id > {1}))",
+                    added, id
+                )
+            }
+            SortKey::Due(date) => {
+                let date = param(Param::Date(*date));
+                let id = param(Param::Id(*id));
+                format!(
+                    "(m.est_end_date IS NULL OR m.est_end_date > {0} OR (m.est_end_date = {0} AND k.id > {1}))",
+                    date, id
+                )
+            }
+            SortKey::NotDue => format!(
+                "(m.est_end_date IS NULL AND k.id > {})",
+                param(Param::Id(*id))
+            ),
+        };
+        conditions.push(condition);
+    }
+
+    let order = match query.sort {
+        KombuchaSort::Name => "k.name, k.id",
+        KombuchaSort::Added => "k.added, k.id",
+        KombuchaSort::NextMilestone => {
+            "m.est_end_date IS NULL, m.est_end_date, k.id"
+        }
+    };
+
+    let limit = param(Param::Limit(i64::from(limit) + 1));
+
+    let mut sql = SELECT_SUMMARIES.to_string();
+    let _ = write!(
+        sql,
+        " WHERE {} ORDER BY {} LIMIT {}",
+        conditions.join(" AND "),
+        order,
+        limit
+    );
+
+    (sql, params)
+}
+
+/// Matches names containing `q` anywhere, wildcards in it are taken literally
+fn like_pattern(q: &str) -> String {
+    let mut pattern = String::with_capacity(q.len() + 2);
+    pattern.push('%');
+    for c in q.chars() {
+        if let '%' | '_' | '\\' = c {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+
+    pattern
+}
+
+/// Keeps every digit of the date, a cursor rounded down would repeat the last
+/// kombucha of the page
+fn text(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+/// Builds a summary from the columns of `SELECT_SUMMARIES`
+pub fn summary(
+    id: KombuchaId,
+    name: String,
+    added: DateTime<Utc>,
+    status: Option<FermentationStatus>,
+    milestone: (
+        Option<FermentationId>,
+        Option<FermentationStatus>,
+        Option<DateTime<Utc>>,
+    ),
+    entry_count: i64,
+) -> KombuchaSummary {
+    let next_milestone = match milestone {
+        (Some(fermentation_id), Some(status), Some(date)) => Some(Milestone {
+            fermentation_id,
+            status,
+            date,
+        }),
+        _ => None,
+    };
+
+    KombuchaSummary {
+        id,
+        name,
+        added,
+        status,
+        next_milestone,
+        entry_count: entry_count as u32,
+    }
+}
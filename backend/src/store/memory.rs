@@ -0,0 +1,1369 @@
+use super::{
+    assemble_kombuchas, assemble_recipes,
+    list::{self, Cursor},
+    next_status, search, KombuchaStore,
+};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use data_types::db::{
+    Entry as DbEntry, Fermentation as DbFermentation, Image as DbImage,
+    Kombucha as DbKombucha, Measurement as DbMeasurement, Recipe as DbRecipe,
+    RecipeFlavouring as DbRecipeFlavouring, RecipeStage as DbRecipeStage,
+    RecipeTea as DbRecipeTea,
+};
+use data_types::{
+    AdvanceFermentation, DeletedEntry, DeletedFermentation, DeletedKombucha,
+    Entry, EntryId, EntryPatch, Fermentation, FermentationId, Image, ImageId,
+    Kombucha, KombuchaId, KombuchaPage, KombuchaPatch, KombuchaQuery,
+    Measurement, MeasurementId, NewFermentation, NewMeasurement,
+    NewRecipeTemplate, Recipe, RecipeTemplate, SearchHit, SearchQuery,
+    TemplateId, Trash,
+};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+/// Keeps everything in memory, meant for tests and demos
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    tables: Mutex<Tables>,
+}
+
+/// Rows of every table, ids are never reused just like with a sequence
+#[derive(Debug, Default)]
+struct Tables {
+    last_id: i32,
+    kombuchas: Vec<DbKombucha>,
+    entries: Vec<DbEntry>,
+    fermentations: Vec<DbFermentation>,
+    images: Vec<DbImage>,
+    measurements: Vec<DbMeasurement>,
+    recipes: Vec<DbRecipe>,
+    recipe_teas: Vec<DbRecipeTea>,
+    recipe_stages: Vec<DbRecipeStage>,
+    recipe_flavourings: Vec<DbRecipeFlavouring>,
+    templates: Vec<RecipeTemplate>,
+    /// The `deleted_at` column of kombuchas, entries and fermentations, ids
+    /// are unique across tables
+    deleted_at: HashMap<i32, DateTime<Utc>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Tables {
+    fn next_id<T: From<i32>>(&mut self) -> T {
+        self.last_id += 1;
+        self.last_id.into()
+    }
+
+    fn deleted_at(&self, id: impl Into<i32>) -> Option<DateTime<Utc>> {
+        self.deleted_at.get(&id.into()).copied()
+    }
+
+    fn is_deleted(&self, id: impl Into<i32>) -> bool {
+        self.deleted_at.contains_key(&id.into())
+    }
+
+    /// Ids of the kombucha and of its entries and fermentations, whether
+    /// they're deleted or not
+    fn rows_of(&self, id: KombuchaId) -> Vec<i32> {
+        let entries = self
+            .entries
+            .iter()
+            .filter(|entry| entry.kombucha_id == id)
+            .map(|entry| i32::from(entry.id));
+        let fermentations = self
+            .fermentations
+            .iter()
+            .filter(|fermentation| fermentation.kombucha_id == id)
+            .map(|fermentation| i32::from(fermentation.id));
+
+        std::iter::once(i32::from(id))
+            .chain(entries)
+            .chain(fermentations)
+            .collect()
+    }
+
+    fn kombucha(&self, id: KombuchaId) -> Option<&DbKombucha> {
+        self.kombuchas
+            .iter()
+            .find(|kombucha| kombucha.id == id && !self.is_deleted(id))
+    }
+
+    fn ensure_kombucha(&self, id: KombuchaId) -> Result<()> {
+        self.kombucha(id)
+            .map(|_| ())
+            .ok_or_else(|| Error::not_found(format!("Kombucha {}", id)))
+    }
+
+    fn insert_kombucha(&mut self, name: &str) -> KombuchaId {
+        let id = self.next_id();
+        let now = Utc::now();
+
+        self.kombuchas.push(DbKombucha {
+            id,
+            name: name.to_string(),
+            added: now,
+            version: 1,
+            updated_at: now,
+        });
+
+        id
+    }
+
+    fn insert_fermentation(
+        &mut self,
+        kombucha_id: KombuchaId,
+        fermentation: &NewFermentation,
+    ) -> FermentationId {
+        let id = self.next_id();
+
+        self.fermentations.push(DbFermentation {
+            id,
+            kombucha_id,
+            start_date: fermentation.start_date.unwrap_or_else(Utc::now),
+            end_date: None,
+            est_end_date: fermentation.est_end_date,
+            status: fermentation.status,
+        });
+
+        id
+    }
+
+    fn replace_recipe(&mut self, kombucha_id: KombuchaId, recipe: &Recipe) {
+        self.recipes
+            .retain(|recipe| recipe.kombucha_id != kombucha_id);
+        self.recipe_teas
+            .retain(|tea| tea.kombucha_id != kombucha_id);
+        self.recipe_stages
+            .retain(|stage| stage.kombucha_id != kombucha_id);
+        self.recipe_flavourings
+            .retain(|flavouring| flavouring.kombucha_id != kombucha_id);
+
+        self.recipes.push(DbRecipe {
+            kombucha_id,
+            sugar_type: recipe.sugar_type.clone(),
+            sugar_grams: recipe.sugar_grams,
+            water_ml: recipe.water_ml,
+            starter_ml: recipe.starter_ml,
+            vessel: recipe.vessel.clone(),
+            vessel_ml: recipe.vessel_ml,
+        });
+        for (position, tea) in recipe.teas.iter().enumerate() {
+            self.recipe_teas.push(DbRecipeTea {
+                kombucha_id,
+                position: position as i32,
+                variety: tea.variety.clone(),
+                grams: tea.grams,
+            });
+        }
+        for (position, stage) in recipe.stages.iter().enumerate() {
+            self.recipe_stages.push(DbRecipeStage {
+                kombucha_id,
+                position: position as i32,
+                status: stage.status,
+                days: stage.days as i32,
+            });
+        }
+        for (position, flavouring) in recipe.flavourings.iter().enumerate() {
+            self.recipe_flavourings.push(DbRecipeFlavouring {
+                kombucha_id,
+                position: position as i32,
+                ingredient: flavouring.ingredient.clone(),
+                amount: flavouring.amount.clone(),
+                stage: flavouring.stage,
+            });
+        }
+    }
+
+    fn entries(&self, kombucha_id: KombuchaId) -> Vec<Entry> {
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.kombucha_id == kombucha_id && !self.is_deleted(entry.id)
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.added, entry.id));
+
+        entries.into_iter().cloned().map(Entry::from).collect()
+    }
+
+    fn fermentations(&self, kombucha_id: KombuchaId) -> Vec<Fermentation> {
+        let mut fermentations: Vec<_> = self
+            .fermentations
+            .iter()
+            .filter(|fermentation| {
+                fermentation.kombucha_id == kombucha_id
+                    && !self.is_deleted(fermentation.id)
+            })
+            .collect();
+        fermentations.sort_by_key(|fermentation| {
+            (fermentation.start_date, fermentation.id)
+        });
+
+        fermentations
+            .into_iter()
+            .cloned()
+            .map(Fermentation::from)
+            .collect()
+    }
+
+    fn images(&self, kombucha_id: KombuchaId) -> Vec<Image> {
+        let mut images: Vec<_> = self
+            .images
+            .iter()
+            .filter(|image| {
+                image.kombucha_id == kombucha_id
+                    && !self.is_deleted(kombucha_id)
+            })
+            .collect();
+        images.sort_by_key(|image| (image.added, image.id));
+
+        images.into_iter().cloned().map(Image::from).collect()
+    }
+
+    fn latest_measurements(&self, kombucha_id: KombuchaId) -> Vec<Measurement> {
+        let mut measurements = self.measurements(kombucha_id);
+        let older = measurements
+            .len()
+            .saturating_sub(Kombucha::LATEST_MEASUREMENTS);
+        measurements.drain(..older);
+
+        measurements
+    }
+
+    fn measurements(&self, kombucha_id: KombuchaId) -> Vec<Measurement> {
+        let mut measurements: Vec<_> = self
+            .measurements
+            .iter()
+            .filter(|measurement| {
+                measurement.kombucha_id == kombucha_id
+                    && !self.is_deleted(kombucha_id)
+            })
+            .collect();
+        measurements
+            .sort_by_key(|measurement| (measurement.taken_at, measurement.id));
+
+        measurements
+            .into_iter()
+            .cloned()
+            .map(Measurement::from)
+            .collect()
+    }
+
+    fn recipe(&self, kombucha_id: KombuchaId) -> Option<Recipe> {
+        if self.is_deleted(kombucha_id) {
+            return None;
+        }
+
+        let db_recipe = self
+            .recipes
+            .iter()
+            .find(|recipe| recipe.kombucha_id == kombucha_id)?;
+
+        Some(Recipe::from_db(
+            db_recipe.clone(),
+            self.recipe_teas(kombucha_id),
+            self.recipe_stages(kombucha_id),
+            self.recipe_flavourings(kombucha_id),
+        ))
+    }
+
+    /// In the order they were added
+    fn recipe_teas(&self, kombucha_id: KombuchaId) -> Vec<DbRecipeTea> {
+        let mut teas: Vec<_> = self
+            .recipe_teas
+            .iter()
+            .filter(|tea| tea.kombucha_id == kombucha_id)
+            .cloned()
+            .collect();
+        teas.sort_by_key(|tea| tea.position);
+
+        teas
+    }
+
+    /// In the order they were added
+    fn recipe_stages(&self, kombucha_id: KombuchaId) -> Vec<DbRecipeStage> {
+        let mut stages: Vec<_> = self
+            .recipe_stages
+            .iter()
+            .filter(|stage| stage.kombucha_id == kombucha_id)
+            .cloned()
+            .collect();
+        stages.sort_by_key(|stage| stage.position);
+
+        stages
+    }
+
+    /// In the order they were added
+    fn recipe_flavourings(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Vec<DbRecipeFlavouring> {
+        let mut flavourings: Vec<_> = self
+            .recipe_flavourings
+            .iter()
+            .filter(|flavouring| flavouring.kombucha_id == kombucha_id)
+            .cloned()
+            .collect();
+        flavourings.sort_by_key(|flavouring| flavouring.position);
+
+        flavourings
+    }
+
+    fn assemble_kombucha(&self, db_kombucha: &DbKombucha) -> Kombucha {
+        Kombucha {
+            id: db_kombucha.id,
+            name: db_kombucha.name.clone(),
+            added: db_kombucha.added,
+            version: db_kombucha.version,
+            updated_at: db_kombucha.updated_at,
+            entries: self.entries(db_kombucha.id),
+            fermentations: self.fermentations(db_kombucha.id),
+            images: self.images(db_kombucha.id),
+            measurements: self.latest_measurements(db_kombucha.id),
+            recipe: self.recipe(db_kombucha.id),
+        }
+    }
+}
+
+#[async_trait]
+impl KombuchaStore for MemoryStore {
+    // CREATE
+
+    async fn create_kombucha(&self, name: &str) -> Result<KombuchaId> {
+        Ok(self.tables().insert_kombucha(name))
+    }
+
+    async fn create_kombucha_with_recipe(
+        &self,
+        name: &str,
+        recipe: &Recipe,
+        fermentation: Option<&NewFermentation>,
+    ) -> Result<KombuchaId> {
+        // Nothing below can fail, so holding the lock throughout is enough
+        // for none of it to be seen half done
+        let mut tables = self.tables();
+        let id = tables.insert_kombucha(name);
+        tables.replace_recipe(id, recipe);
+        if let Some(fermentation) = fermentation {
+            tables.insert_fermentation(id, fermentation);
+        }
+
+        Ok(id)
+    }
+
+    async fn create_entry(&self, kombucha_id: KombuchaId) -> Result<EntryId> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+        let id = tables.next_id();
+        let now = Utc::now();
+
+        tables.entries.push(DbEntry {
+            id,
+            kombucha_id,
+            content: String::new(),
+            added: now,
+            version: 1,
+            updated_at: now,
+        });
+
+        Ok(id)
+    }
+
+    async fn create_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &NewFermentation,
+    ) -> Result<FermentationId> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+
+        Ok(tables.insert_fermentation(kombucha_id, fermentation))
+    }
+
+    async fn create_image(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: Option<EntryId>,
+        content_type: &str,
+        file_name: &(dyn Fn(ImageId) -> String + Sync),
+    ) -> Result<DbImage> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+        let id = tables.next_id();
+
+        let image = DbImage {
+            id,
+            kombucha_id,
+            entry_id,
+            file_name: file_name(id),
+            content_type: content_type.to_string(),
+            added: Utc::now(),
+        };
+        tables.images.push(image.clone());
+
+        Ok(image)
+    }
+
+    async fn create_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &NewMeasurement,
+    ) -> Result<MeasurementId> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+        let id = tables.next_id();
+
+        tables.measurements.push(DbMeasurement {
+            id,
+            kombucha_id,
+            kind: measurement.kind,
+            value: measurement.value,
+            unit: measurement.unit().to_string(),
+            taken_at: measurement.taken_at.unwrap_or_else(Utc::now),
+            fermentation_id: measurement.fermentation_id,
+        });
+
+        Ok(id)
+    }
+
+    async fn create_template(
+        &self,
+        template: &NewRecipeTemplate,
+    ) -> Result<TemplateId> {
+        let mut tables = self.tables();
+        let id = tables.next_id();
+
+        tables.templates.push(RecipeTemplate {
+            id,
+            name: template.name.clone(),
+            recipe: template.recipe.clone(),
+        });
+
+        Ok(id)
+    }
+
+    // GET
+
+    async fn get_all_kombuchas(&self) -> Result<Vec<Kombucha>> {
+        let tables = self.tables();
+
+        let mut kombuchas: Vec<_> = tables
+            .kombuchas
+            .iter()
+            .filter(|kombucha| !tables.is_deleted(kombucha.id))
+            .cloned()
+            .collect();
+        kombuchas.sort_by_key(|kombucha| kombucha.id);
+        let mut entries: Vec<_> = tables
+            .entries
+            .iter()
+            .filter(|entry| !tables.is_deleted(entry.id))
+            .cloned()
+            .collect();
+        entries.sort_by_key(|entry| (entry.added, entry.id));
+        let mut fermentations: Vec<_> = tables
+            .fermentations
+            .iter()
+            .filter(|fermentation| !tables.is_deleted(fermentation.id))
+            .cloned()
+            .collect();
+        fermentations.sort_by_key(|fermentation| {
+            (fermentation.start_date, fermentation.id)
+        });
+        let mut images = tables.images.clone();
+        images.sort_by_key(|image| (image.added, image.id));
+        let mut measurements = tables.measurements.clone();
+        measurements
+            .sort_by_key(|measurement| (measurement.taken_at, measurement.id));
+        // Only the latest of each kombucha, counted from the newest
+        let mut counts = HashMap::new();
+        let mut measurements: Vec<_> = measurements
+            .into_iter()
+            .rev()
+            .filter(|measurement| {
+                let count = counts.entry(measurement.kombucha_id).or_insert(0);
+                *count += 1;
+                *count <= Kombucha::LATEST_MEASUREMENTS
+            })
+            .collect();
+        measurements.reverse();
+        let mut recipe_teas = tables.recipe_teas.clone();
+        recipe_teas.sort_by_key(|tea| (tea.kombucha_id, tea.position));
+        let mut recipe_stages = tables.recipe_stages.clone();
+        recipe_stages.sort_by_key(|stage| (stage.kombucha_id, stage.position));
+        let mut recipe_flavourings = tables.recipe_flavourings.clone();
+        recipe_flavourings.sort_by_key(|flavouring| {
+            (flavouring.kombucha_id, flavouring.position)
+        });
+
+        Ok(assemble_kombuchas(
+            kombuchas,
+            entries,
+            fermentations,
+            images,
+            measurements,
+            assemble_recipes(
+                tables.recipes.clone(),
+                recipe_teas,
+                recipe_stages,
+                recipe_flavourings,
+            ),
+        ))
+    }
+
+    async fn list_kombuchas(
+        &self,
+        query: &KombuchaQuery,
+    ) -> Result<KombuchaPage> {
+        let limit = list::limit(query)?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(query.sort, cursor))
+            .transpose()?;
+        let q = query.q.as_ref().map(|q| q.to_lowercase());
+
+        let mut summaries: Vec<_> = self
+            .get_all_kombuchas()
+            .await?
+            .iter()
+            .map(Kombucha::summary)
+            .filter(|summary| {
+                query
+                    .status
+                    .is_none_or(|status| summary.status == Some(status))
+            })
+            .filter(|summary| {
+                q.as_ref()
+                    .is_none_or(|q| summary.name.to_lowercase().contains(q))
+            })
+            .filter(|summary| {
+                query
+                    .added_after
+                    .is_none_or(|added_after| summary.added > added_after)
+            })
+            .filter(|summary| {
+                cursor.as_ref().is_none_or(|cursor| {
+                    Cursor::of(query.sort, summary) > *cursor
+                })
+            })
+            .collect();
+        summaries.sort_by_key(|summary| Cursor::of(query.sort, summary));
+        summaries.truncate(limit as usize + 1);
+
+        Ok(list::page(summaries, query.sort, limit))
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let limit = search::limit(query)?;
+        let terms = search::terms(&query.q);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tables = self.tables();
+
+        let names = tables
+            .kombuchas
+            .iter()
+            .filter(|kombucha| !tables.is_deleted(kombucha.id))
+            .map(|kombucha| {
+                (
+                    kombucha.id,
+                    kombucha.name.clone(),
+                    None,
+                    kombucha.name.as_str(),
+                )
+            });
+        let entries = tables
+            .entries
+            .iter()
+            .filter(|entry| !tables.is_deleted(entry.id))
+            .filter_map(|entry| {
+                tables.kombucha(entry.kombucha_id).map(|kombucha| {
+                    (
+                        kombucha.id,
+                        kombucha.name.clone(),
+                        Some(entry.id),
+                        entry.content.as_str(),
+                    )
+                })
+            });
+
+        Ok(search::rank(names.chain(entries), &terms, limit))
+    }
+
+    async fn get_kombucha(&self, id: KombuchaId) -> Result<Option<Kombucha>> {
+        let tables = self.tables();
+
+        Ok(tables
+            .kombucha(id)
+            .map(|kombucha| tables.assemble_kombucha(kombucha)))
+    }
+
+    async fn get_kombucha_row(
+        &self,
+        id: KombuchaId,
+    ) -> Result<Option<DbKombucha>> {
+        Ok(self.tables().kombucha(id).cloned())
+    }
+
+    async fn get_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<Option<Entry>> {
+        let tables = self.tables();
+
+        Ok(tables
+            .entries
+            .iter()
+            .find(|entry| {
+                entry.kombucha_id == kombucha_id
+                    && entry.id == entry_id
+                    && !tables.is_deleted(entry_id)
+            })
+            .cloned()
+            .map(Entry::from))
+    }
+
+    async fn get_entries(&self, kombucha_id: KombuchaId) -> Result<Vec<Entry>> {
+        Ok(self.tables().entries(kombucha_id))
+    }
+
+    async fn get_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<Option<Fermentation>> {
+        let tables = self.tables();
+
+        Ok(tables
+            .fermentations
+            .iter()
+            .find(|fermentation| {
+                fermentation.kombucha_id == kombucha_id
+                    && fermentation.id == fermentation_id
+                    && !tables.is_deleted(fermentation_id)
+            })
+            .cloned()
+            .map(Fermentation::from))
+    }
+
+    async fn get_fermentations(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Fermentation>> {
+        Ok(self.tables().fermentations(kombucha_id))
+    }
+
+    async fn get_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<Option<DbImage>> {
+        let tables = self.tables();
+
+        Ok(tables
+            .images
+            .iter()
+            .find(|image| {
+                image.kombucha_id == kombucha_id
+                    && image.id == image_id
+                    && !tables.is_deleted(kombucha_id)
+            })
+            .cloned())
+    }
+
+    async fn get_images(&self, kombucha_id: KombuchaId) -> Result<Vec<Image>> {
+        Ok(self.tables().images(kombucha_id))
+    }
+
+    async fn get_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<Option<Measurement>> {
+        Ok(self
+            .tables()
+            .measurements(kombucha_id)
+            .into_iter()
+            .find(|measurement| measurement.id == measurement_id))
+    }
+
+    async fn get_measurements(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Measurement>> {
+        Ok(self.tables().measurements(kombucha_id))
+    }
+
+    async fn get_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Recipe>> {
+        Ok(self.tables().recipe(kombucha_id))
+    }
+
+    async fn get_templates(&self) -> Result<Vec<RecipeTemplate>> {
+        let mut templates = self.tables().templates.clone();
+        templates.sort_by(|a, b| (&a.name, a.id).cmp(&(&b.name, b.id)));
+
+        Ok(templates)
+    }
+
+    async fn get_template(
+        &self,
+        id: TemplateId,
+    ) -> Result<Option<RecipeTemplate>> {
+        Ok(self
+            .tables()
+            .templates
+            .iter()
+            .find(|template| template.id == id)
+            .cloned())
+    }
+
+    // UPDATE
+
+    async fn update_kombucha(&self, kombucha: &Kombucha) -> Result<i32> {
+        let mut tables = self.tables();
+        let Tables {
+            kombuchas,
+            entries,
+            deleted_at,
+            ..
+        } = &mut *tables;
+
+        let what = format!("Kombucha {}", kombucha.id);
+        let db_kombucha = kombuchas
+            .iter_mut()
+            .find(|db_kombucha| {
+                db_kombucha.id == kombucha.id
+                    && !deleted_at.contains_key(&kombucha.id.into())
+            })
+            .ok_or_else(|| Error::not_found(&what))?;
+        if db_kombucha.version != kombucha.version {
+            return Err(Error::stale(
+                what,
+                kombucha.version,
+                Some(db_kombucha.version),
+            ));
+        }
+
+        let is_live = |db_entry: &DbEntry, entry: &Entry| {
+            db_entry.kombucha_id == kombucha.id
+                && db_entry.id == entry.id
+                && !deleted_at.contains_key(&entry.id.into())
+        };
+        if let Some(entry) = kombucha.entries.iter().find(|entry| {
+            !entries.iter().any(|db_entry| is_live(db_entry, entry))
+        }) {
+            return Err(Error::not_found(format!(
+                "Entry {} of kombucha {}",
+                entry.id, kombucha.id
+            )));
+        }
+
+        let now = Utc::now();
+        db_kombucha.name = kombucha.name.clone();
+        db_kombucha.added = kombucha.added;
+        db_kombucha.version += 1;
+        db_kombucha.updated_at = now;
+
+        for entry in &kombucha.entries {
+            if let Some(db_entry) =
+                entries.iter_mut().find(|db_entry| is_live(db_entry, entry))
+            {
+                db_entry.added = entry.added;
+                db_entry.content = entry.content.clone();
+                db_entry.version += 1;
+                db_entry.updated_at = now;
+            }
+        }
+
+        Ok(db_kombucha.version)
+    }
+
+    async fn patch_kombucha(
+        &self,
+        id: KombuchaId,
+        version: i32,
+        patch: &KombuchaPatch,
+    ) -> Result<i32> {
+        let mut tables = self.tables();
+        let Tables {
+            kombuchas,
+            deleted_at,
+            ..
+        } = &mut *tables;
+
+        let what = format!("Kombucha {}", id);
+        let db_kombucha = kombuchas
+            .iter_mut()
+            .find(|db_kombucha| {
+                db_kombucha.id == id && !deleted_at.contains_key(&id.into())
+            })
+            .ok_or_else(|| Error::not_found(&what))?;
+        if db_kombucha.version != version {
+            return Err(Error::stale(what, version, Some(db_kombucha.version)));
+        }
+
+        if let Some(name) = &patch.name {
+            db_kombucha.name = name.clone();
+        }
+        if let Some(added) = patch.added {
+            db_kombucha.added = added;
+        }
+        db_kombucha.version += 1;
+        db_kombucha.updated_at = Utc::now();
+
+        Ok(db_kombucha.version)
+    }
+
+    async fn patch_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+        version: i32,
+        patch: &EntryPatch,
+    ) -> Result<i32> {
+        let mut tables = self.tables();
+        let Tables {
+            kombuchas,
+            entries,
+            deleted_at,
+            ..
+        } = &mut *tables;
+
+        let what = format!("Entry {} of kombucha {}", entry_id, kombucha_id);
+        let db_entry = entries
+            .iter_mut()
+            .find(|db_entry| {
+                db_entry.kombucha_id == kombucha_id
+                    && db_entry.id == entry_id
+                    && !deleted_at.contains_key(&entry_id.into())
+            })
+            .ok_or_else(|| Error::not_found(&what))?;
+        if db_entry.version != version {
+            return Err(Error::stale(what, version, Some(db_entry.version)));
+        }
+
+        if let Some(added) = patch.added {
+            db_entry.added = added;
+        }
+        if let Some(content) = &patch.content {
+            db_entry.content = content.clone();
+        }
+        let now = Utc::now();
+        db_entry.version += 1;
+        db_entry.updated_at = now;
+
+        if let Some(db_kombucha) = kombuchas
+            .iter_mut()
+            .find(|db_kombucha| db_kombucha.id == kombucha_id)
+        {
+            db_kombucha.version += 1;
+            db_kombucha.updated_at = now;
+        }
+
+        Ok(db_entry.version)
+    }
+
+    async fn update_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &Fermentation,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+        let Tables {
+            fermentations,
+            deleted_at,
+            ..
+        } = &mut *tables;
+
+        let db_fermentation = fermentations
+            .iter_mut()
+            .find(|db_fermentation| {
+                db_fermentation.kombucha_id == kombucha_id
+                    && db_fermentation.id == fermentation.id
+                    && !deleted_at.contains_key(&fermentation.id.into())
+            })
+            .ok_or_else(|| {
+                Error::not_found(format!(
+                    "Fermentation {} of kombucha {}",
+                    fermentation.id, kombucha_id
+                ))
+            })?;
+        db_fermentation.start_date = fermentation.start_date;
+        db_fermentation.end_date = fermentation.end_date;
+        db_fermentation.est_end_date = fermentation.est_end_date;
+        db_fermentation.status = fermentation.status;
+
+        Ok(())
+    }
+
+    async fn advance_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        advance: &AdvanceFermentation,
+    ) -> Result<Fermentation> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+
+        let now = advance.start_date.unwrap_or_else(Utc::now);
+        let Tables {
+            fermentations,
+            deleted_at,
+            ..
+        } = &mut *tables;
+
+        let current = fermentations
+            .iter_mut()
+            .filter(|fermentation| {
+                fermentation.kombucha_id == kombucha_id
+                    && !deleted_at.contains_key(&fermentation.id.into())
+            })
+            .max_by_key(|fermentation| {
+                (fermentation.start_date, fermentation.id)
+            });
+
+        let status = next_status(
+            current.as_ref().map(|current| current.status),
+            advance.status,
+        )?;
+
+        if let Some(current) = current {
+            current.end_date.get_or_insert(now);
+        }
+
+        let new_fermentation = DbFermentation {
+            id: tables.next_id(),
+            kombucha_id,
+            start_date: now,
+            end_date: if status.is_terminal() {
+                Some(now)
+            } else {
+                None
+            },
+            est_end_date: advance.est_end_date,
+            status,
+        };
+        tables.fermentations.push(new_fermentation.clone());
+
+        Ok(new_fermentation.into())
+    }
+
+    async fn update_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+
+        let db_measurement = tables
+            .measurements
+            .iter_mut()
+            .find(|db_measurement| {
+                db_measurement.kombucha_id == kombucha_id
+                    && db_measurement.id == measurement.id
+            })
+            .ok_or_else(|| {
+                Error::not_found(format!(
+                    "Measurement {} of kombucha {}",
+                    measurement.id, kombucha_id
+                ))
+            })?;
+        db_measurement.kind = measurement.kind;
+        db_measurement.value = measurement.value;
+        db_measurement.unit = measurement.unit.clone();
+        db_measurement.taken_at = measurement.taken_at;
+        db_measurement.fermentation_id = measurement.fermentation_id;
+
+        Ok(())
+    }
+
+    async fn set_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+        recipe: &Recipe,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+        tables.replace_recipe(kombucha_id, recipe);
+
+        Ok(())
+    }
+
+    // DELETE
+
+    async fn delete_kombucha(&self, id: KombuchaId) -> Result<()> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(id)?;
+
+        let now = Utc::now();
+        let ids = tables.rows_of(id);
+
+        for id in ids {
+            tables.deleted_at.entry(id).or_insert(now);
+        }
+
+        Ok(())
+    }
+
+    async fn delete_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+
+        if !tables
+            .entries(kombucha_id)
+            .iter()
+            .any(|entry| entry.id == entry_id)
+        {
+            return Err(Error::not_found("entry"));
+        }
+        tables.deleted_at.insert(entry_id.into(), Utc::now());
+
+        Ok(())
+    }
+
+    async fn delete_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+
+        if !tables
+            .fermentations(kombucha_id)
+            .iter()
+            .any(|fermentation| fermentation.id == fermentation_id)
+        {
+            return Err(Error::not_found("fermentation"));
+        }
+        tables.deleted_at.insert(fermentation_id.into(), Utc::now());
+
+        Ok(())
+    }
+
+    async fn delete_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+
+        let len = tables.images.len();
+        tables.images.retain(|image| {
+            !(image.kombucha_id == kombucha_id && image.id == image_id)
+        });
+        if tables.images.len() == len {
+            return Err(Error::not_found("image"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+
+        let len = tables.measurements.len();
+        tables.measurements.retain(|measurement| {
+            !(measurement.kombucha_id == kombucha_id
+                && measurement.id == measurement_id)
+        });
+        if tables.measurements.len() == len {
+            return Err(Error::not_found("measurement"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_recipe(&self, kombucha_id: KombuchaId) -> Result<()> {
+        let mut tables = self.tables();
+        tables.ensure_kombucha(kombucha_id)?;
+
+        let len = tables.recipes.len();
+        tables
+            .recipes
+            .retain(|recipe| recipe.kombucha_id != kombucha_id);
+        if tables.recipes.len() == len {
+            return Err(Error::not_found("recipe"));
+        }
+        tables
+            .recipe_teas
+            .retain(|tea| tea.kombucha_id != kombucha_id);
+        tables
+            .recipe_stages
+            .retain(|stage| stage.kombucha_id != kombucha_id);
+        tables
+            .recipe_flavourings
+            .retain(|flavouring| flavouring.kombucha_id != kombucha_id);
+
+        Ok(())
+    }
+
+    async fn delete_template(&self, id: TemplateId) -> Result<()> {
+        let mut tables = self.tables();
+
+        let len = tables.templates.len();
+        tables.templates.retain(|template| template.id != id);
+        if tables.templates.len() == len {
+            return Err(Error::not_found("template"));
+        }
+
+        Ok(())
+    }
+
+    // TRASH
+
+    async fn get_trash(&self) -> Result<Trash> {
+        let tables = self.tables();
+
+        let mut kombuchas: Vec<_> = tables
+            .kombuchas
+            .iter()
+            .filter_map(|kombucha| {
+                Some(DeletedKombucha {
+                    id: kombucha.id,
+                    name: kombucha.name.clone(),
+                    deleted_at: tables.deleted_at(kombucha.id)?,
+                })
+            })
+            .collect();
+        kombuchas.sort_by_key(|kombucha| {
+            (Reverse(kombucha.deleted_at), Reverse(kombucha.id))
+        });
+
+        let mut entries: Vec<_> = tables
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let kombucha = tables.kombucha(entry.kombucha_id)?;
+
+                Some(DeletedEntry {
+                    kombucha_id: kombucha.id,
+                    kombucha_name: kombucha.name.clone(),
+                    entry: Entry::from(entry.clone()),
+                    deleted_at: tables.deleted_at(entry.id)?,
+                })
+            })
+            .collect();
+        entries.sort_by_key(|deleted| {
+            (Reverse(deleted.deleted_at), Reverse(deleted.entry.id))
+        });
+
+        let mut fermentations: Vec<_> = tables
+            .fermentations
+            .iter()
+            .filter_map(|fermentation| {
+                let kombucha = tables.kombucha(fermentation.kombucha_id)?;
+
+                Some(DeletedFermentation {
+                    kombucha_id: kombucha.id,
+                    kombucha_name: kombucha.name.clone(),
+                    fermentation: Fermentation::from(fermentation.clone()),
+                    deleted_at: tables.deleted_at(fermentation.id)?,
+                })
+            })
+            .collect();
+        fermentations.sort_by_key(|deleted| {
+            (
+                Reverse(deleted.deleted_at),
+                Reverse(deleted.fermentation.id),
+            )
+        });
+
+        Ok(Trash {
+            kombuchas,
+            entries,
+            fermentations,
+        })
+    }
+
+    async fn restore_kombucha(&self, id: KombuchaId) -> Result<()> {
+        let mut tables = self.tables();
+
+        let deleted_at = tables
+            .kombuchas
+            .iter()
+            .find(|kombucha| kombucha.id == id)
+            .and_then(|kombucha| tables.deleted_at(kombucha.id))
+            .ok_or_else(|| {
+                Error::not_found(format!("Deleted kombucha {}", id))
+            })?;
+
+        let ids = tables.rows_of(id);
+
+        // What was deleted before the kombucha stays in the trash
+        for id in ids {
+            if tables.deleted_at(id) == Some(deleted_at) {
+                tables.deleted_at.remove(&id);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn restore_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+
+        let deleted = tables.kombucha(kombucha_id).is_some()
+            && tables.entries.iter().any(|entry| {
+                entry.kombucha_id == kombucha_id && entry.id == entry_id
+            })
+            && tables.is_deleted(entry_id);
+        if !deleted {
+            return Err(Error::not_found(format!(
+                "Deleted entry {} of kombucha {}",
+                entry_id, kombucha_id
+            )));
+        }
+        tables.deleted_at.remove(&entry_id.into());
+
+        Ok(())
+    }
+
+    async fn restore_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        let mut tables = self.tables();
+
+        let deleted = tables.kombucha(kombucha_id).is_some()
+            && tables.fermentations.iter().any(|fermentation| {
+                fermentation.kombucha_id == kombucha_id
+                    && fermentation.id == fermentation_id
+            })
+            && tables.is_deleted(fermentation_id);
+        if !deleted {
+            return Err(Error::not_found(format!(
+                "Deleted fermentation {} of kombucha {}",
+                fermentation_id, kombucha_id
+            )));
+        }
+        tables.deleted_at.remove(&fermentation_id.into());
+
+        Ok(())
+    }
+
+    async fn purge_deleted(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<KombuchaId>> {
+        let mut tables = self.tables();
+        let expired = |tables: &Tables, id: i32| {
+            tables
+                .deleted_at(id)
+                .is_some_and(|deleted_at| deleted_at < before)
+        };
+
+        let kombucha_ids: Vec<KombuchaId> = tables
+            .kombuchas
+            .iter()
+            .map(|kombucha| kombucha.id)
+            .filter(|id| expired(&tables, (*id).into()))
+            .collect();
+        let entry_ids: Vec<EntryId> = tables
+            .entries
+            .iter()
+            .filter(|entry| {
+                kombucha_ids.contains(&entry.kombucha_id)
+                    || expired(&tables, entry.id.into())
+            })
+            .map(|entry| entry.id)
+            .collect();
+        let fermentation_ids: Vec<FermentationId> = tables
+            .fermentations
+            .iter()
+            .filter(|fermentation| {
+                kombucha_ids.contains(&fermentation.kombucha_id)
+                    || expired(&tables, fermentation.id.into())
+            })
+            .map(|fermentation| fermentation.id)
+            .collect();
+
+        // Mirrors `ON DELETE CASCADE` for the kombucha's images,
+        // measurements and recipe and `ON DELETE SET NULL` for those of
+        // entries and fermentations
+        tables
+            .recipes
+            .retain(|recipe| !kombucha_ids.contains(&recipe.kombucha_id));
+        tables
+            .recipe_teas
+            .retain(|tea| !kombucha_ids.contains(&tea.kombucha_id));
+        tables
+            .recipe_stages
+            .retain(|stage| !kombucha_ids.contains(&stage.kombucha_id));
+        tables.recipe_flavourings.retain(|flavouring| {
+            !kombucha_ids.contains(&flavouring.kombucha_id)
+        });
+        tables
+            .images
+            .retain(|image| !kombucha_ids.contains(&image.kombucha_id));
+        tables.measurements.retain(|measurement| {
+            !kombucha_ids.contains(&measurement.kombucha_id)
+        });
+        for measurement in &mut tables.measurements {
+            if measurement.fermentation_id.is_some_and(|fermentation_id| {
+                fermentation_ids.contains(&fermentation_id)
+            }) {
+                measurement.fermentation_id = None;
+            }
+        }
+        for image in &mut tables.images {
+            if image
+                .entry_id
+                .is_some_and(|entry_id| entry_ids.contains(&entry_id))
+            {
+                image.entry_id = None;
+            }
+        }
+        tables
+            .entries
+            .retain(|entry| !entry_ids.contains(&entry.id));
+        tables.fermentations.retain(|fermentation| {
+            !fermentation_ids.contains(&fermentation.id)
+        });
+        tables
+            .kombuchas
+            .retain(|kombucha| !kombucha_ids.contains(&kombucha.id));
+
+        let purged = kombucha_ids
+            .iter()
+            .map(|&id| i32::from(id))
+            .chain(entry_ids.iter().map(|&id| i32::from(id)))
+            .chain(fermentation_ids.iter().map(|&id| i32::from(id)));
+        for id in purged.collect::<Vec<_>>() {
+            tables.deleted_at.remove(&id);
+        }
+
+        Ok(kombucha_ids)
+    }
+}
@@ -0,0 +1,481 @@
+//! Persistence of kombuchas, `App` only ever talks to a `KombuchaStore` so
+//! that the database behind it can be swapped
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use data_types::db::{
+    Entry as DbEntry, Fermentation as DbFermentation, Image as DbImage,
+    Kombucha as DbKombucha, Measurement as DbMeasurement, Recipe as DbRecipe,
+    RecipeFlavouring as DbRecipeFlavouring, RecipeStage as DbRecipeStage,
+    RecipeTea as DbRecipeTea,
+};
+use data_types::{
+    AdvanceFermentation, Entry, EntryId, EntryPatch, Fermentation,
+    FermentationId, FermentationStatus, Image, ImageId, Kombucha, KombuchaId,
+    KombuchaPage, KombuchaPatch, KombuchaQuery, Measurement, MeasurementId,
+    NewFermentation, NewMeasurement, NewRecipeTemplate, Recipe, RecipeTemplate,
+    SearchHit, SearchQuery, TemplateId, Trash,
+};
+
+use std::collections::HashMap;
+
+mod list;
+mod memory;
+mod postgres;
+mod search;
+mod sqlite;
+
+pub use self::memory::MemoryStore;
+pub use self::postgres::PostgresStore;
+pub use self::sqlite::SqliteStore;
+
+#[async_trait]
+pub trait KombuchaStore: Send + Sync + std::fmt::Debug {
+    // CREATE
+
+    async fn create_kombucha(&self, name: &str) -> Result<KombuchaId>;
+
+    /// Creates the kombucha with its recipe and, when given, its first
+    /// fermentation, leaving nothing behind when any of them fails
+    async fn create_kombucha_with_recipe(
+        &self,
+        name: &str,
+        recipe: &Recipe,
+        fermentation: Option<&NewFermentation>,
+    ) -> Result<KombuchaId>;
+
+    async fn create_entry(&self, kombucha_id: KombuchaId) -> Result<EntryId>;
+
+    async fn create_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &NewFermentation,
+    ) -> Result<FermentationId>;
+
+    /// Inserts the row of an image, its file is named after the new id
+    /// with `file_name`
+    async fn create_image(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: Option<EntryId>,
+        content_type: &str,
+        file_name: &(dyn Fn(ImageId) -> String + Sync),
+    ) -> Result<DbImage>;
+
+    /// Fills in the unit and the time the measurement was taken when they
+    /// were left out
+    async fn create_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &NewMeasurement,
+    ) -> Result<MeasurementId>;
+
+    async fn create_template(
+        &self,
+        template: &NewRecipeTemplate,
+    ) -> Result<TemplateId>;
+
+    // GET
+
+    async fn get_all_kombuchas(&self) -> Result<Vec<Kombucha>>;
+
+    /// A page of summaries matching `query`, see `list` for the cursor
+    async fn list_kombuchas(
+        &self,
+        query: &KombuchaQuery,
+    ) -> Result<KombuchaPage>;
+
+    /// Kombuchas and entries matching the query, best matches first.
+    ///
+    /// Postgres matches stemmed English words with its full-text search and
+    /// ranks with `ts_rank`. The other stores only match words starting with
+    /// every term, without stemming or stop words, and rank by the share of
+    /// words that matched, so the same query can find and order hits
+    /// differently there. Their snippets are cut around the first match like
+    /// the ones of `ts_headline`, but not split into fragments
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>>;
+
+    async fn get_kombucha(&self, id: KombuchaId) -> Result<Option<Kombucha>>;
+
+    /// The kombucha without its entries, fermentations and images
+    async fn get_kombucha_row(
+        &self,
+        id: KombuchaId,
+    ) -> Result<Option<DbKombucha>>;
+
+    async fn get_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<Option<Entry>>;
+
+    async fn get_entries(&self, kombucha_id: KombuchaId) -> Result<Vec<Entry>>;
+
+    async fn get_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<Option<Fermentation>>;
+
+    async fn get_fermentations(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Fermentation>>;
+
+    async fn get_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<Option<DbImage>>;
+
+    async fn get_images(&self, kombucha_id: KombuchaId) -> Result<Vec<Image>>;
+
+    async fn get_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<Option<Measurement>>;
+
+    /// Oldest first
+    async fn get_measurements(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Measurement>>;
+
+    async fn get_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Recipe>>;
+
+    /// Ordered by name
+    async fn get_templates(&self) -> Result<Vec<RecipeTemplate>>;
+
+    async fn get_template(
+        &self,
+        id: TemplateId,
+    ) -> Result<Option<RecipeTemplate>>;
+
+    // UPDATE
+
+    /// Updates the kombucha and its entries unless it's no longer at
+    /// `kombucha.version`, returns the version it's at afterwards. Nothing is
+    /// updated when one of the entries isn't the kombucha's
+    async fn update_kombucha(&self, kombucha: &Kombucha) -> Result<i32>;
+
+    /// Updates the fields of the patch unless the kombucha is no longer at
+    /// `version`, returns the version it's at afterwards
+    async fn patch_kombucha(
+        &self,
+        id: KombuchaId,
+        version: i32,
+        patch: &KombuchaPatch,
+    ) -> Result<i32>;
+
+    /// Updates the fields of the patch unless the entry is no longer at
+    /// `version`, returns the version it's at afterwards. Its kombucha is
+    /// bumped as well, since updating the kombucha overwrites its entries
+    async fn patch_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+        version: i32,
+        patch: &EntryPatch,
+    ) -> Result<i32>;
+
+    async fn update_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &Fermentation,
+    ) -> Result<()>;
+
+    /// Closes the current fermentation of a kombucha and opens one in the
+    /// next stage, terminal stages are opened already closed
+    async fn advance_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        advance: &AdvanceFermentation,
+    ) -> Result<Fermentation>;
+
+    async fn update_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &Measurement,
+    ) -> Result<()>;
+
+    /// Creates the recipe of the kombucha or replaces the one it has
+    async fn set_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+        recipe: &Recipe,
+    ) -> Result<()>;
+
+    // DELETE
+
+    /// Moves the kombucha to the trash together with its entries and
+    /// fermentations that weren't deleted already
+    async fn delete_kombucha(&self, id: KombuchaId) -> Result<()>;
+
+    /// Moves the entry to the trash
+    async fn delete_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()>;
+
+    /// Moves the fermentation to the trash
+    async fn delete_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()>;
+
+    async fn delete_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<()>;
+
+    /// Measurements are deleted for good, they're quickly logged again
+    async fn delete_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<()>;
+
+    async fn delete_recipe(&self, kombucha_id: KombuchaId) -> Result<()>;
+
+    async fn delete_template(&self, id: TemplateId) -> Result<()>;
+
+    // TRASH
+
+    async fn get_trash(&self) -> Result<Trash>;
+
+    /// Takes the kombucha out of the trash along with whatever was deleted
+    /// together with it
+    async fn restore_kombucha(&self, id: KombuchaId) -> Result<()>;
+
+    async fn restore_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()>;
+
+    async fn restore_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()>;
+
+    /// Deletes for good everything deleted before `before`, returns the
+    /// kombuchas that are gone so that their images can be removed
+    async fn purge_deleted(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<KombuchaId>>;
+}
+
+/// Groups the rows of a few batched queries under their kombuchas, the child
+/// rows keep the order they were fetched in
+fn assemble_kombuchas(
+    db_kombuchas: Vec<DbKombucha>,
+    db_entries: Vec<DbEntry>,
+    db_fermentations: Vec<DbFermentation>,
+    db_images: Vec<DbImage>,
+    db_measurements: Vec<DbMeasurement>,
+    mut recipes: HashMap<KombuchaId, Recipe>,
+) -> Vec<Kombucha> {
+    let mut kombuchas: Vec<_> = db_kombuchas
+        .into_iter()
+        .map(|db_kombucha| Kombucha {
+            id: db_kombucha.id,
+            name: db_kombucha.name,
+            added: db_kombucha.added,
+            version: db_kombucha.version,
+            updated_at: db_kombucha.updated_at,
+            entries: Vec::new(),
+            fermentations: Vec::new(),
+            images: Vec::new(),
+            measurements: Vec::new(),
+            recipe: recipes.remove(&db_kombucha.id),
+        })
+        .collect();
+
+    let positions: HashMap<KombuchaId, usize> = kombuchas
+        .iter()
+        .enumerate()
+        .map(|(position, kombucha)| (kombucha.id, position))
+        .collect();
+
+    for db_entry in db_entries {
+        if let Some(&position) = positions.get(&db_entry.kombucha_id) {
+            kombuchas[position].entries.push(Entry::from(db_entry));
+        }
+    }
+
+    for db_fermentation in db_fermentations {
+        if let Some(&position) = positions.get(&db_fermentation.kombucha_id) {
+            kombuchas[position]
+                .fermentations
+                .push(Fermentation::from(db_fermentation));
+        }
+    }
+
+    for db_image in db_images {
+        if let Some(&position) = positions.get(&db_image.kombucha_id) {
+            kombuchas[position].images.push(Image::from(db_image));
+        }
+    }
+
+    for db_measurement in db_measurements {
+        if let Some(&position) = positions.get(&db_measurement.kombucha_id) {
+            kombuchas[position]
+                .measurements
+                .push(Measurement::from(db_measurement));
+        }
+    }
+
+    kombuchas
+}
+
+/// Puts the teas, stages and flavourings of each recipe into it, they keep the
+/// order they were fetched in
+fn assemble_recipes(
+    db_recipes: Vec<DbRecipe>,
+    db_recipe_teas: Vec<DbRecipeTea>,
+    db_recipe_stages: Vec<DbRecipeStage>,
+    db_recipe_flavourings: Vec<DbRecipeFlavouring>,
+) -> HashMap<KombuchaId, Recipe> {
+    let mut teas: HashMap<KombuchaId, Vec<DbRecipeTea>> = HashMap::new();
+    for db_recipe_tea in db_recipe_teas {
+        teas.entry(db_recipe_tea.kombucha_id)
+            .or_default()
+            .push(db_recipe_tea);
+    }
+    let mut stages: HashMap<KombuchaId, Vec<DbRecipeStage>> = HashMap::new();
+    for db_recipe_stage in db_recipe_stages {
+        stages
+            .entry(db_recipe_stage.kombucha_id)
+            .or_default()
+            .push(db_recipe_stage);
+    }
+    let mut flavourings: HashMap<KombuchaId, Vec<DbRecipeFlavouring>> =
+        HashMap::new();
+    for db_recipe_flavouring in db_recipe_flavourings {
+        flavourings
+            .entry(db_recipe_flavouring.kombucha_id)
+            .or_default()
+            .push(db_recipe_flavouring);
+    }
+
+    db_recipes
+        .into_iter()
+        .map(|db_recipe| {
+            let kombucha_id = db_recipe.kombucha_id;
+            let db_teas = teas.remove(&kombucha_id).unwrap_or_default();
+            let db_stages = stages.remove(&kombucha_id).unwrap_or_default();
+            let db_flavourings =
+                flavourings.remove(&kombucha_id).unwrap_or_default();
+
+            (
+                kombucha_id,
+                Recipe::from_db(db_recipe, db_teas, db_stages, db_flavourings),
+            )
+        })
+        .collect()
+}
+
+/// Templates keep their recipe as JSON, it's only ever copied whole
+fn template(
+    (id, name, recipe): (TemplateId, String, String),
+) -> Result<RecipeTemplate> {
+    let recipe = serde_json::from_str(&recipe).map_err(anyhow::Error::from)?;
+
+    Ok(RecipeTemplate { id, name, recipe })
+}
+
+fn template_recipe(recipe: &Recipe) -> Result<String> {
+    Ok(serde_json::to_string(recipe).map_err(anyhow::Error::from)?)
+}
+
+/// The stage a kombucha advances to from its `current` one, when no stage is
+/// `requested` it's the one following `current`
+pub(crate) fn next_status(
+    current: Option<FermentationStatus>,
+    requested: Option<FermentationStatus>,
+) -> Result<FermentationStatus> {
+    match (current, requested) {
+        (None, None) | (None, Some(FermentationStatus::Primary)) => {
+            Ok(FermentationStatus::Primary)
+        }
+        (None, Some(status)) => Err(Error::Validation(format!(
+            "Can't start a kombucha in {}",
+            status
+        ))),
+        (Some(current), None) => current.next().ok_or_else(|| {
+            Error::Validation(format!(
+                "Can't advance a kombucha past {}",
+                current
+            ))
+        }),
+        (Some(current), Some(status)) => current
+            .transition_to(status)
+            .map_err(|err| Error::Validation(err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_status;
+    use data_types::FermentationStatus::{self, *};
+
+    #[test]
+    fn picks_the_next_status() {
+        let cases: &[(
+            Option<FermentationStatus>,
+            Option<FermentationStatus>,
+            FermentationStatus,
+        )] = &[
+            (None, None, Primary),
+            (None, Some(Primary), Primary),
+            (Some(Primary), None, Secondary),
+            (Some(Refrigerated), None, Finished),
+            (Some(Secondary), Some(Conditioning), Conditioning),
+            (Some(Bottled), Some(Failed), Failed),
+        ];
+
+        for &(current, requested, expected) in cases {
+            assert_eq!(
+                next_status(current, requested).unwrap(),
+                expected,
+                "{:?} to {:?}",
+                current,
+                requested
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_next_statuses() {
+        let cases: &[(
+            Option<FermentationStatus>,
+            Option<FermentationStatus>,
+        )] = &[
+            (None, Some(Secondary)),
+            (None, Some(Finished)),
+            (Some(Finished), None),
+            (Some(Discarded), None),
+            (Some(Secondary), Some(Primary)),
+            (Some(Failed), Some(Discarded)),
+        ];
+
+        for &(current, requested) in cases {
+            assert!(
+                next_status(current, requested).is_err(),
+                "{:?} to {:?}",
+                current,
+                requested
+            );
+        }
+    }
+}
@@ -0,0 +1,1633 @@
+use super::{
+    assemble_kombuchas, assemble_recipes,
+    list::{self, Cursor, Param},
+    next_status, search, template, template_recipe, KombuchaStore,
+};
+use crate::{
+    error::{Error, Result},
+    migrate::{self, Migrate, Migration},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use data_types::db::{
+    Entry as DbEntry, Fermentation as DbFermentation, Image as DbImage,
+    Kombucha as DbKombucha, Measurement as DbMeasurement, Recipe as DbRecipe,
+    RecipeFlavouring as DbRecipeFlavouring, RecipeStage as DbRecipeStage,
+    RecipeTea as DbRecipeTea,
+};
+use data_types::{
+    AdvanceFermentation, DeletedEntry, DeletedFermentation, DeletedKombucha,
+    Entry, EntryId, EntryPatch, Fermentation, FermentationId,
+    FermentationStatus, Image, ImageId, Kombucha, KombuchaId, KombuchaPage,
+    KombuchaPatch, KombuchaQuery, Measurement, MeasurementId, NewFermentation,
+    NewMeasurement, NewRecipeTemplate, Recipe, RecipeTemplate, SearchHit,
+    SearchQuery, TemplateId, Trash,
+};
+use sqlx::{
+    pool::PoolConnection, postgres::PgQueryAs, Executor, PgConnection, PgPool,
+    Transaction,
+};
+
+type SummaryRow = (
+    KombuchaId,
+    String,
+    DateTime<Utc>,
+    Option<FermentationStatus>,
+    Option<FermentationId>,
+    Option<FermentationStatus>,
+    Option<DateTime<Utc>>,
+    i64,
+);
+type SearchRow = (KombuchaId, String, Option<EntryId>, String, f32);
+type DeletedEntryRow = (
+    EntryId,
+    KombuchaId,
+    String,
+    DateTime<Utc>,
+    i32,
+    DateTime<Utc>,
+    String,
+    DateTime<Utc>,
+);
+type DeletedFermentationRow = (
+    FermentationId,
+    DateTime<Utc>,
+    Option<DateTime<Utc>>,
+    Option<DateTime<Utc>>,
+    FermentationStatus,
+    KombuchaId,
+    String,
+    DateTime<Utc>,
+);
+
+#[derive(Clone, Debug)]
+pub struct PostgresStore {
+    db: PgPool,
+}
+
+impl PostgresStore {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    async fn get_all_db_kombuchas(&self) -> Result<Vec<DbKombucha>> {
+        let row = sqlx::query_as::<_, DbKombucha>(
+            "SELECT id, name, added, version, updated_at FROM kombucha WHERE deleted_at IS NULL ORDER BY id",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(row)
+    }
+
+    async fn get_db_entries_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbEntry>> {
+        let rows = sqlx::query_as::<_, DbEntry>(
+            "SELECT id, kombucha_id, content, added, version, updated_at FROM kombucha_entry WHERE kombucha_id = ANY($1) AND deleted_at IS NULL ORDER BY added, id",
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_fermentations_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbFermentation>> {
+        let rows = sqlx::query_as::<_, DbFermentation>(
+            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE kombucha_id = ANY($1) AND deleted_at IS NULL ORDER BY start_date, id"
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_images_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbImage>> {
+        let rows = sqlx::query_as::<_, DbImage>(
+            "SELECT id, kombucha_id, entry_id, file_name, content_type, added FROM kombucha_image WHERE kombucha_id = ANY($1) ORDER BY added, id"
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    /// The latest measurements of each kombucha
+    async fn get_db_measurements_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbMeasurement>> {
+        let rows = sqlx::query_as::<_, DbMeasurement>(
+            "SELECT id, kombucha_id, kind, value, unit, taken_at, fermentation_id FROM (SELECT *, ROW_NUMBER() OVER (PARTITION BY kombucha_id ORDER BY taken_at DESC, id DESC) AS position FROM kombucha_measurement WHERE kombucha_id = ANY($1)) m WHERE position <= $2 ORDER BY taken_at, id"
+        )
+        .bind(kombucha_ids.to_vec())
+        .bind(Kombucha::LATEST_MEASUREMENTS as i64)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_recipes_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbRecipe>> {
+        let rows = sqlx::query_as::<_, DbRecipe>(
+            "SELECT kombucha_id, sugar_type, sugar_grams, water_ml, starter_ml, vessel, vessel_ml FROM kombucha_recipe WHERE kombucha_id = ANY($1)"
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_recipe_teas_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbRecipeTea>> {
+        let rows = sqlx::query_as::<_, DbRecipeTea>(
+            "SELECT kombucha_id, position, variety, grams FROM kombucha_recipe_tea WHERE kombucha_id = ANY($1) ORDER BY kombucha_id, position"
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_recipe_stages_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbRecipeStage>> {
+        let rows = sqlx::query_as::<_, DbRecipeStage>(
+            "SELECT kombucha_id, position, status, days FROM kombucha_recipe_stage WHERE kombucha_id = ANY($1) ORDER BY kombucha_id, position"
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_recipe_flavourings_for_kombuchas(
+        &self,
+        kombucha_ids: &[i32],
+    ) -> Result<Vec<DbRecipeFlavouring>> {
+        let rows = sqlx::query_as::<_, DbRecipeFlavouring>(
+            "SELECT kombucha_id, position, ingredient, amount, stage FROM kombucha_recipe_flavouring WHERE kombucha_id = ANY($1) ORDER BY kombucha_id, position"
+        )
+        .bind(kombucha_ids.to_vec())
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_entries_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbEntry>> {
+        let row = sqlx::query_as::<_, DbEntry>(
+            "SELECT id, kombucha_id, content, added, version, updated_at FROM kombucha_entry WHERE kombucha_id = $1 AND deleted_at IS NULL ORDER BY added, id",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(row)
+    }
+
+    async fn get_db_fermentations_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbFermentation>> {
+        let rows = sqlx::query_as::<_, DbFermentation>(
+            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE kombucha_id = $1 AND deleted_at IS NULL ORDER BY start_date, id"
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_images_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbImage>> {
+        let rows = sqlx::query_as::<_, DbImage>(
+            "SELECT i.id, i.kombucha_id, i.entry_id, i.file_name, i.content_type, i.added FROM kombucha_image i JOIN kombucha k ON k.id = i.kombucha_id WHERE i.kombucha_id = $1 AND k.deleted_at IS NULL ORDER BY i.added, i.id"
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_db_measurements_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbMeasurement>> {
+        let rows = sqlx::query_as::<_, DbMeasurement>(
+            "SELECT m.id, m.kombucha_id, m.kind, m.value, m.unit, m.taken_at, m.fermentation_id FROM kombucha_measurement m JOIN kombucha k ON k.id = m.kombucha_id WHERE m.kombucha_id = $1 AND k.deleted_at IS NULL ORDER BY m.taken_at, m.id"
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_latest_db_measurements_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbMeasurement>> {
+        let rows = sqlx::query_as::<_, DbMeasurement>(
+            "SELECT id, kombucha_id, kind, value, unit, taken_at, fermentation_id FROM (SELECT m.* FROM kombucha_measurement m JOIN kombucha k ON k.id = m.kombucha_id WHERE m.kombucha_id = $1 AND k.deleted_at IS NULL ORDER BY m.taken_at DESC, m.id DESC LIMIT $2) m ORDER BY taken_at, id"
+        )
+        .bind(kombucha_id)
+        .bind(Kombucha::LATEST_MEASUREMENTS as i64)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows)
+    }
+
+    async fn get_recipe_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Recipe>> {
+        let db_recipe = match sqlx::query_as::<_, DbRecipe>(
+            "SELECT r.kombucha_id, r.sugar_type, r.sugar_grams, r.water_ml, r.starter_ml, r.vessel, r.vessel_ml FROM kombucha_recipe r JOIN kombucha k ON k.id = r.kombucha_id WHERE r.kombucha_id = $1 AND k.deleted_at IS NULL"
+        )
+        .bind(kombucha_id)
+        .fetch_optional(&self.db)
+        .await?
+        {
+            Some(db_recipe) => db_recipe,
+            None => return Ok(None),
+        };
+
+        let db_teas = sqlx::query_as::<_, DbRecipeTea>(
+            "SELECT kombucha_id, position, variety, grams FROM kombucha_recipe_tea WHERE kombucha_id = $1 ORDER BY position",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        let db_stages = sqlx::query_as::<_, DbRecipeStage>(
+            "SELECT kombucha_id, position, status, days FROM kombucha_recipe_stage WHERE kombucha_id = $1 ORDER BY position",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        let db_flavourings = sqlx::query_as::<_, DbRecipeFlavouring>(
+            "SELECT kombucha_id, position, ingredient, amount, stage FROM kombucha_recipe_flavouring WHERE kombucha_id = $1 ORDER BY position",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(Some(Recipe::from_db(
+            db_recipe,
+            db_teas,
+            db_stages,
+            db_flavourings,
+        )))
+    }
+
+    async fn assemble_kombucha(
+        &self,
+        db_kombucha: DbKombucha,
+    ) -> Result<Kombucha> {
+        let db_entries =
+            self.get_db_entries_for_kombucha(db_kombucha.id).await?;
+        let db_fermentations = self
+            .get_db_fermentations_for_kombucha(db_kombucha.id)
+            .await?;
+        let db_images = self.get_db_images_for_kombucha(db_kombucha.id).await?;
+        let db_measurements = self
+            .get_latest_db_measurements_for_kombucha(db_kombucha.id)
+            .await?;
+        let recipe = self.get_recipe_for_kombucha(db_kombucha.id).await?;
+
+        let DbKombucha {
+            id,
+            name,
+            added,
+            version,
+            updated_at,
+        } = db_kombucha;
+
+        let entries = db_entries.into_iter().map(Entry::from).collect();
+        let fermentations = db_fermentations
+            .into_iter()
+            .map(Fermentation::from)
+            .collect();
+        let images = db_images.into_iter().map(Image::from).collect();
+        let measurements =
+            db_measurements.into_iter().map(Measurement::from).collect();
+
+        Ok(Kombucha {
+            id,
+            name,
+            added,
+            version,
+            updated_at,
+            entries,
+            fermentations,
+            images,
+            measurements,
+            recipe,
+        })
+    }
+}
+
+#[async_trait]
+impl KombuchaStore for PostgresStore {
+    // CREATE
+
+    async fn create_kombucha(&self, name: &str) -> Result<KombuchaId> {
+        let mut transaction = self.db.begin().await?;
+        let result = insert_kombucha(&mut transaction, name).await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_kombucha_with_recipe(
+        &self,
+        name: &str,
+        recipe: &Recipe,
+        fermentation: Option<&NewFermentation>,
+    ) -> Result<KombuchaId> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            let id = insert_kombucha(&mut transaction, name).await?;
+            replace_recipe(&mut transaction, id, recipe).await?;
+            if let Some(fermentation) = fermentation {
+                insert_fermentation(&mut transaction, id, fermentation).await?;
+            }
+
+            Ok(id)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_entry(&self, kombucha_id: KombuchaId) -> Result<EntryId> {
+        let (id,) = sqlx::query_as::<_, (EntryId,)>(
+            "INSERT INTO kombucha_entry (kombucha_id, content, added) SELECT $1, '', NOW() WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL) RETURNING id",
+        )
+        .bind(kombucha_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| {
+            Error::not_found(format!("Kombucha {}", kombucha_id))
+        })?;
+
+        Ok(id)
+    }
+
+    async fn create_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &NewFermentation,
+    ) -> Result<FermentationId> {
+        let mut transaction = self.db.begin().await?;
+        let result =
+            insert_fermentation(&mut transaction, kombucha_id, fermentation)
+                .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_image(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: Option<EntryId>,
+        content_type: &str,
+        file_name: &(dyn Fn(ImageId) -> String + Sync),
+    ) -> Result<DbImage> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            let (id,) = sqlx::query_as::<_, (ImageId,)>(
+                "INSERT INTO kombucha_image (kombucha_id, entry_id, file_name, content_type, added) SELECT $1, $2, '', $3, NOW() WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL) RETURNING id",
+            )
+            .bind(kombucha_id)
+            .bind(entry_id)
+            .bind(content_type)
+            .fetch_optional(&mut transaction)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found(format!("Kombucha {}", kombucha_id))
+            })?;
+
+            let image = sqlx::query_as::<_, DbImage>(
+                "UPDATE kombucha_image SET file_name = $1 WHERE id = $2 RETURNING id, kombucha_id, entry_id, file_name, content_type, added",
+            )
+            .bind(file_name(id))
+            .bind(id)
+            .fetch_one(&mut transaction)
+            .await?;
+
+            Ok(image)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &NewMeasurement,
+    ) -> Result<MeasurementId> {
+        let (id,) = sqlx::query_as::<_, (MeasurementId,)>(
+            "INSERT INTO kombucha_measurement (kombucha_id, fermentation_id, kind, value, unit, taken_at) SELECT $1, $2, $3, $4, $5, COALESCE($6, NOW()) WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL) RETURNING id",
+        )
+        .bind(kombucha_id)
+        .bind(measurement.fermentation_id)
+        .bind(measurement.kind)
+        .bind(measurement.value)
+        .bind(measurement.unit())
+        .bind(measurement.taken_at)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| {
+            Error::not_found(format!("Kombucha {}", kombucha_id))
+        })?;
+
+        Ok(id)
+    }
+
+    async fn create_template(
+        &self,
+        template: &NewRecipeTemplate,
+    ) -> Result<TemplateId> {
+        let (id,) = sqlx::query_as::<_, (TemplateId,)>(
+            "INSERT INTO recipe_template (name, recipe) VALUES ($1, $2) RETURNING id",
+        )
+        .bind(&template.name)
+        .bind(template_recipe(&template.recipe)?)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(id)
+    }
+
+    // GET
+
+    async fn get_all_kombuchas(&self) -> Result<Vec<Kombucha>> {
+        let db_kombuchas = self.get_all_db_kombuchas().await?;
+        let ids: Vec<i32> = db_kombuchas
+            .iter()
+            .map(|kombucha| kombucha.id.into())
+            .collect();
+
+        let db_entries = self.get_db_entries_for_kombuchas(&ids).await?;
+        let db_fermentations =
+            self.get_db_fermentations_for_kombuchas(&ids).await?;
+        let db_images = self.get_db_images_for_kombuchas(&ids).await?;
+        let db_measurements =
+            self.get_db_measurements_for_kombuchas(&ids).await?;
+        let db_recipes = self.get_db_recipes_for_kombuchas(&ids).await?;
+        let db_recipe_teas =
+            self.get_db_recipe_teas_for_kombuchas(&ids).await?;
+        let db_recipe_stages =
+            self.get_db_recipe_stages_for_kombuchas(&ids).await?;
+        let db_recipe_flavourings =
+            self.get_db_recipe_flavourings_for_kombuchas(&ids).await?;
+
+        Ok(assemble_kombuchas(
+            db_kombuchas,
+            db_entries,
+            db_fermentations,
+            db_images,
+            db_measurements,
+            assemble_recipes(
+                db_recipes,
+                db_recipe_teas,
+                db_recipe_stages,
+                db_recipe_flavourings,
+            ),
+        ))
+    }
+
+    async fn list_kombuchas(
+        &self,
+        query: &KombuchaQuery,
+    ) -> Result<KombuchaPage> {
+        let limit = list::limit(query)?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(query.sort, cursor))
+            .transpose()?;
+
+        let (sql, params) =
+            list::summaries_sql(query, cursor.as_ref(), limit, '$', "ILIKE");
+
+        let mut rows = sqlx::query_as::<_, SummaryRow>(&sql);
+        for param in params {
+            rows = match param {
+                Param::Status(status) => rows.bind(status),
+                Param::Text(text) => rows.bind(text),
+                Param::Date(date) => rows.bind(date),
+                Param::Id(id) => rows.bind(id),
+                Param::Limit(limit) => rows.bind(limit),
+            };
+        }
+
+        let summaries = rows
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(
+                |(
+                    id,
+                    name,
+                    added,
+                    status,
+                    fermentation_id,
+                    milestone_status,
+                    est_end_date,
+                    entry_count,
+                )| {
+                    list::summary(
+                        id,
+                        name,
+                        added,
+                        status,
+                        (fermentation_id, milestone_status, est_end_date),
+                        entry_count,
+                    )
+                },
+            )
+            .collect();
+
+        Ok(list::page(summaries, query.sort, limit))
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let limit = search::limit(query)?;
+        let terms = search::terms(&query.q);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let options =
+            format!("StartSel={}, StopSel={}", search::START, search::STOP);
+
+        let rows = sqlx::query_as::<_, SearchRow>(
+            "SELECT kombucha_id, kombucha_name, entry_id, snippet, rank FROM (
+                SELECT k.id AS kombucha_id, k.name AS kombucha_name, NULL::INTEGER AS entry_id,
+                    ts_headline('english', k.name, q, $2 || ', HighlightAll=TRUE') AS snippet,
+                    ts_rank(to_tsvector('english', k.name), q) AS rank
+                FROM kombucha k, to_tsquery('english', $1) q
+                WHERE k.deleted_at IS NULL AND to_tsvector('english', k.name) @@ q
+                UNION ALL
+                SELECT e.kombucha_id, k.name, e.id,
+                    ts_headline('english', e.content, q, $2 || ', MaxFragments=2'),
+                    ts_rank(to_tsvector('english', e.content), q)
+                FROM kombucha_entry e
+                JOIN kombucha k ON k.id = e.kombucha_id, to_tsquery('english', $1) q
+                WHERE e.deleted_at IS NULL AND k.deleted_at IS NULL
+                    AND to_tsvector('english', e.content) @@ q
+            ) hits
+            ORDER BY rank DESC, kombucha_id, entry_id NULLS FIRST
+            LIMIT $3",
+        )
+        .bind(search::tsquery(&terms))
+        .bind(options)
+        .bind(i64::from(limit))
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(kombucha_id, kombucha_name, entry_id, snippet, rank)| {
+                SearchHit {
+                    kombucha_id,
+                    kombucha_name,
+                    entry_id,
+                    snippet: search::snippet(&snippet),
+                    rank,
+                }
+            })
+            .collect())
+    }
+
+    async fn get_kombucha(&self, id: KombuchaId) -> Result<Option<Kombucha>> {
+        match self.get_kombucha_row(id).await? {
+            Some(db_kombucha) => {
+                Ok(Some(self.assemble_kombucha(db_kombucha).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_kombucha_row(
+        &self,
+        id: KombuchaId,
+    ) -> Result<Option<DbKombucha>> {
+        let row = sqlx::query_as::<_, DbKombucha>(
+            "SELECT id, name, added, version, updated_at FROM kombucha WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(row)
+    }
+
+    async fn get_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<Option<Entry>> {
+        let maybe_entry = sqlx::query_as::<_, DbEntry>(
+            "SELECT id, kombucha_id, content, added, version, updated_at FROM kombucha_entry WHERE id = $1 AND kombucha_id = $2 AND deleted_at IS NULL"
+        )
+        .bind(entry_id)
+        .bind(kombucha_id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(maybe_entry.map(Entry::from))
+    }
+
+    async fn get_entries(&self, kombucha_id: KombuchaId) -> Result<Vec<Entry>> {
+        let entries = self
+            .get_db_entries_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Entry::from)
+            .collect();
+
+        Ok(entries)
+    }
+
+    async fn get_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<Option<Fermentation>> {
+        let maybe_fermentation = sqlx::query_as::<_, DbFermentation>(
+            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE id = $1 AND kombucha_id = $2 AND deleted_at IS NULL"
+        )
+        .bind(fermentation_id)
+        .bind(kombucha_id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(maybe_fermentation.map(Fermentation::from))
+    }
+
+    async fn get_fermentations(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Fermentation>> {
+        let fermentations = self
+            .get_db_fermentations_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Fermentation::from)
+            .collect();
+
+        Ok(fermentations)
+    }
+
+    async fn get_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<Option<DbImage>> {
+        let row = sqlx::query_as::<_, DbImage>(
+            "SELECT i.id, i.kombucha_id, i.entry_id, i.file_name, i.content_type, i.added FROM kombucha_image i JOIN kombucha k ON k.id = i.kombucha_id WHERE i.id = $1 AND i.kombucha_id = $2 AND k.deleted_at IS NULL"
+        )
+        .bind(image_id)
+        .bind(kombucha_id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(row)
+    }
+
+    async fn get_images(&self, kombucha_id: KombuchaId) -> Result<Vec<Image>> {
+        let images = self
+            .get_db_images_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Image::from)
+            .collect();
+
+        Ok(images)
+    }
+
+    async fn get_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<Option<Measurement>> {
+        let row = sqlx::query_as::<_, DbMeasurement>(
+            "SELECT m.id, m.kombucha_id, m.kind, m.value, m.unit, m.taken_at, m.fermentation_id FROM kombucha_measurement m JOIN kombucha k ON k.id = m.kombucha_id WHERE m.id = $1 AND m.kombucha_id = $2 AND k.deleted_at IS NULL"
+        )
+        .bind(measurement_id)
+        .bind(kombucha_id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(row.map(Measurement::from))
+    }
+
+    async fn get_measurements(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Measurement>> {
+        let measurements = self
+            .get_db_measurements_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Measurement::from)
+            .collect();
+
+        Ok(measurements)
+    }
+
+    async fn get_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Recipe>> {
+        self.get_recipe_for_kombucha(kombucha_id).await
+    }
+
+    async fn get_templates(&self) -> Result<Vec<RecipeTemplate>> {
+        sqlx::query_as::<_, (TemplateId, String, String)>(
+            "SELECT id, name, recipe FROM recipe_template ORDER BY name, id",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(template)
+        .collect()
+    }
+
+    async fn get_template(
+        &self,
+        id: TemplateId,
+    ) -> Result<Option<RecipeTemplate>> {
+        sqlx::query_as::<_, (TemplateId, String, String)>(
+            "SELECT id, name, recipe FROM recipe_template WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?
+        .map(template)
+        .transpose()
+    }
+
+    // UPDATE
+
+    #[allow(clippy::needless_borrows_for_generic_args)]
+    async fn update_kombucha(&self, kombucha: &Kombucha) -> Result<i32> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            let updated = sqlx::query_as::<_, (i32,)>(
+                "UPDATE kombucha SET (name, added, version, updated_at) = ($1, $2, version + 1, NOW()) WHERE id = $3 AND version = $4 AND deleted_at IS NULL RETURNING version",
+            )
+            .bind(&kombucha.name)
+            .bind(&kombucha.added)
+            .bind(kombucha.id)
+            .bind(kombucha.version)
+            .fetch_optional(&mut transaction)
+            .await?;
+
+            let (version,) = match updated {
+                Some(updated) => updated,
+                None => {
+                    let current = sqlx::query_as::<_, (i32,)>(
+                        "SELECT version FROM kombucha WHERE id = $1 AND deleted_at IS NULL",
+                    )
+                    .bind(kombucha.id)
+                    .fetch_optional(&mut transaction)
+                    .await?;
+
+                    return Err(Error::stale(
+                        format!("Kombucha {}", kombucha.id),
+                        kombucha.version,
+                        current.map(|(version,)| version),
+                    ));
+                }
+            };
+
+            for entry in &kombucha.entries {
+                let query = sqlx::query("UPDATE kombucha_entry SET (added, content, version, updated_at) = ($1, $2, version + 1, NOW()) WHERE id = $3 AND kombucha_id = $4 AND deleted_at IS NULL")
+                    .bind(&entry.added)
+                    .bind(&entry.content)
+                    .bind(entry.id)
+                    .bind(kombucha.id);
+
+                if transaction.execute(query).await? == 0 {
+                    return Err(Error::not_found(format!(
+                        "Entry {} of kombucha {}",
+                        entry.id, kombucha.id
+                    )));
+                }
+            }
+
+            Ok(version)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn patch_kombucha(
+        &self,
+        id: KombuchaId,
+        version: i32,
+        patch: &KombuchaPatch,
+    ) -> Result<i32> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            let updated = sqlx::query_as::<_, (i32,)>(
+                "UPDATE kombucha SET (name, added, version, updated_at) = (COALESCE($1, name), COALESCE($2, added), version + 1, NOW()) WHERE id = $3 AND version = $4 AND deleted_at IS NULL RETURNING version",
+            )
+            .bind(&patch.name)
+            .bind(patch.added)
+            .bind(id)
+            .bind(version)
+            .fetch_optional(&mut transaction)
+            .await?;
+
+            let (updated,) = match updated {
+                Some(updated) => updated,
+                None => {
+                    let current = sqlx::query_as::<_, (i32,)>(
+                        "SELECT version FROM kombucha WHERE id = $1 AND deleted_at IS NULL",
+                    )
+                    .bind(id)
+                    .fetch_optional(&mut transaction)
+                    .await?;
+
+                    return Err(Error::stale(
+                        format!("Kombucha {}", id),
+                        version,
+                        current.map(|(version,)| version),
+                    ));
+                }
+            };
+
+            Ok(updated)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn patch_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+        version: i32,
+        patch: &EntryPatch,
+    ) -> Result<i32> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            let updated = sqlx::query_as::<_, (i32,)>(
+                "UPDATE kombucha_entry SET (added, content, version, updated_at) = (COALESCE($1, added), COALESCE($2, content), version + 1, NOW()) WHERE id = $3 AND kombucha_id = $4 AND version = $5 AND deleted_at IS NULL RETURNING version",
+            )
+            .bind(patch.added)
+            .bind(&patch.content)
+            .bind(entry_id)
+            .bind(kombucha_id)
+            .bind(version)
+            .fetch_optional(&mut transaction)
+            .await?;
+
+            let (updated,) = match updated {
+                Some(updated) => updated,
+                None => {
+                    let current = sqlx::query_as::<_, (i32,)>(
+                        "SELECT version FROM kombucha_entry WHERE id = $1 AND kombucha_id = $2 AND deleted_at IS NULL",
+                    )
+                    .bind(entry_id)
+                    .bind(kombucha_id)
+                    .fetch_optional(&mut transaction)
+                    .await?;
+
+                    return Err(Error::stale(
+                        format!("Entry {} of kombucha {}", entry_id, kombucha_id),
+                        version,
+                        current.map(|(version,)| version),
+                    ));
+                }
+            };
+
+            transaction
+                .execute(
+                    sqlx::query(
+                        "UPDATE kombucha SET (version, updated_at) = (version + 1, NOW()) WHERE id = $1",
+                    )
+                    .bind(kombucha_id),
+                )
+                .await?;
+
+            Ok(updated)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn update_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &Fermentation,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_fermentation SET (start_date, end_date, est_end_date, status) = ($1, $2, $3, $4) WHERE id = $5 AND kombucha_id = $6 AND deleted_at IS NULL",
+        )
+        .bind(fermentation.start_date)
+        .bind(fermentation.end_date)
+        .bind(fermentation.est_end_date)
+        .bind(fermentation.status)
+        .bind(fermentation.id)
+        .bind(kombucha_id);
+
+        let updated = self.db.acquire().await?.execute(query).await?;
+
+        if updated == 0 {
+            return Err(Error::not_found(format!(
+                "Fermentation {} of kombucha {}",
+                fermentation.id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn advance_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        advance: &AdvanceFermentation,
+    ) -> Result<Fermentation> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            let current = sqlx::query_as::<_, DbFermentation>(
+                "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE kombucha_id = $1 AND deleted_at IS NULL ORDER BY start_date DESC, id DESC LIMIT 1 FOR UPDATE",
+            )
+            .bind(kombucha_id)
+            .fetch_optional(&mut transaction)
+            .await?;
+
+            let status = next_status(
+                current.as_ref().map(|current| current.status),
+                advance.status,
+            )?;
+
+            if let Some(current) = &current {
+                let query = sqlx::query(
+                    "UPDATE kombucha_fermentation SET end_date = COALESCE($2, NOW()) WHERE id = $1 AND end_date IS NULL",
+                )
+                .bind(current.id)
+                .bind(advance.start_date);
+
+                transaction.execute(query).await?;
+            }
+
+            let new_fermentation = sqlx::query_as::<_, DbFermentation>(
+                "INSERT INTO kombucha_fermentation (kombucha_id, start_date, end_date, est_end_date, status) SELECT $1, COALESCE($5, NOW()), CASE WHEN $2 THEN COALESCE($5, NOW()) END, $3, $4 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL) RETURNING id, kombucha_id, start_date, end_date, est_end_date, status",
+            )
+            .bind(kombucha_id)
+            .bind(status.is_terminal())
+            .bind(advance.est_end_date)
+            .bind(status)
+            .bind(advance.start_date)
+            .fetch_optional(&mut transaction)
+            .await?
+            .ok_or_else(|| {
+                Error::not_found(format!("Kombucha {}", kombucha_id))
+            })?;
+
+            Ok(new_fermentation.into())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn update_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_measurement SET (fermentation_id, kind, value, unit, taken_at) = ($1, $2, $3, $4, $5) WHERE id = $6 AND kombucha_id = $7 AND EXISTS (SELECT 1 FROM kombucha WHERE id = $7 AND deleted_at IS NULL)",
+        )
+        .bind(measurement.fermentation_id)
+        .bind(measurement.kind)
+        .bind(measurement.value)
+        .bind(&measurement.unit)
+        .bind(measurement.taken_at)
+        .bind(measurement.id)
+        .bind(kombucha_id);
+
+        let updated = self.db.acquire().await?.execute(query).await?;
+
+        if updated == 0 {
+            return Err(Error::not_found(format!(
+                "Measurement {} of kombucha {}",
+                measurement.id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn set_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+        recipe: &Recipe,
+    ) -> Result<()> {
+        let mut transaction = self.db.begin().await?;
+        let result =
+            replace_recipe(&mut transaction, kombucha_id, recipe).await;
+
+        finish(transaction, result).await
+    }
+
+    // DELETE
+
+    async fn delete_kombucha(&self, id: KombuchaId) -> Result<()> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            // `NOW()` is the same throughout the transaction, that's how a
+            // restore tells the children from those deleted before
+            let query = sqlx::query(
+                "UPDATE kombucha SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+            )
+            .bind(id);
+
+            if transaction.execute(query).await? == 0 {
+                return Err(Error::not_found("kombucha"));
+            }
+
+            for delete in &[
+                "UPDATE kombucha_entry SET deleted_at = NOW() WHERE kombucha_id = $1 AND deleted_at IS NULL",
+                "UPDATE kombucha_fermentation SET deleted_at = NOW() WHERE kombucha_id = $1 AND deleted_at IS NULL",
+            ] {
+                transaction.execute(sqlx::query(delete).bind(id)).await?;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn delete_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_entry SET deleted_at = NOW() WHERE kombucha_id = $1 AND id = $2 AND deleted_at IS NULL",
+        )
+        .bind(kombucha_id)
+        .bind(entry_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("entry"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_fermentation SET deleted_at = NOW() WHERE kombucha_id = $1 AND id = $2 AND deleted_at IS NULL",
+        )
+        .bind(kombucha_id)
+        .bind(fermentation_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("fermentation"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "DELETE FROM kombucha_image WHERE kombucha_id = $1 AND id = $2",
+        )
+        .bind(kombucha_id)
+        .bind(image_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("image"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "DELETE FROM kombucha_measurement WHERE kombucha_id = $1 AND id = $2 AND EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(kombucha_id)
+        .bind(measurement_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("measurement"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_recipe(&self, kombucha_id: KombuchaId) -> Result<()> {
+        // The teas and stages go with it through `ON DELETE CASCADE`
+        let query = sqlx::query(
+            "DELETE FROM kombucha_recipe WHERE kombucha_id = $1 AND EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL)",
+        )
+        .bind(kombucha_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("recipe"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_template(&self, id: TemplateId) -> Result<()> {
+        let query =
+            sqlx::query("DELETE FROM recipe_template WHERE id = $1").bind(id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("template"));
+        }
+
+        Ok(())
+    }
+
+    // TRASH
+
+    async fn get_trash(&self) -> Result<Trash> {
+        let kombuchas = sqlx::query_as::<_, (KombuchaId, String, DateTime<Utc>)>(
+            "SELECT id, name, deleted_at FROM kombucha WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|(id, name, deleted_at)| DeletedKombucha {
+            id,
+            name,
+            deleted_at,
+        })
+        .collect();
+
+        let entries = sqlx::query_as::<_, DeletedEntryRow>(
+            "SELECT e.id, e.kombucha_id, e.content, e.added, e.version, e.updated_at, k.name, e.deleted_at FROM kombucha_entry e JOIN kombucha k ON k.id = e.kombucha_id WHERE e.deleted_at IS NOT NULL AND k.deleted_at IS NULL ORDER BY e.deleted_at DESC, e.id DESC",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(
+            |(
+                id,
+                kombucha_id,
+                content,
+                added,
+                version,
+                updated_at,
+                kombucha_name,
+                deleted_at,
+            )| DeletedEntry {
+                kombucha_id,
+                kombucha_name,
+                entry: Entry {
+                    id,
+                    added,
+                    content,
+                    version,
+                    updated_at,
+                },
+                deleted_at,
+            },
+        )
+        .collect();
+
+        let fermentations = sqlx::query_as::<_, DeletedFermentationRow>(
+            "SELECT f.id, f.start_date, f.end_date, f.est_end_date, f.status, f.kombucha_id, k.name, f.deleted_at FROM kombucha_fermentation f JOIN kombucha k ON k.id = f.kombucha_id WHERE f.deleted_at IS NOT NULL AND k.deleted_at IS NULL ORDER BY f.deleted_at DESC, f.id DESC",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(
+            |(
+                id,
+                start_date,
+                end_date,
+                est_end_date,
+                status,
+                kombucha_id,
+                kombucha_name,
+                deleted_at,
+            )| DeletedFermentation {
+                kombucha_id,
+                kombucha_name,
+                fermentation: Fermentation {
+                    id,
+                    start_date,
+                    end_date,
+                    est_end_date,
+                    status,
+                },
+                deleted_at,
+            },
+        )
+        .collect();
+
+        Ok(Trash {
+            kombuchas,
+            entries,
+            fermentations,
+        })
+    }
+
+    async fn restore_kombucha(&self, id: KombuchaId) -> Result<()> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            // What was deleted before the kombucha stays in the trash
+            for restore in &[
+                "UPDATE kombucha_entry e SET deleted_at = NULL FROM kombucha k WHERE k.id = $1 AND e.kombucha_id = k.id AND e.deleted_at = k.deleted_at",
+                "UPDATE kombucha_fermentation f SET deleted_at = NULL FROM kombucha k WHERE k.id = $1 AND f.kombucha_id = k.id AND f.deleted_at = k.deleted_at",
+            ] {
+                transaction.execute(sqlx::query(restore).bind(id)).await?;
+            }
+
+            let query = sqlx::query(
+                "UPDATE kombucha SET deleted_at = NULL WHERE id = $1 AND deleted_at IS NOT NULL",
+            )
+            .bind(id);
+
+            if transaction.execute(query).await? == 0 {
+                return Err(Error::not_found(format!("Deleted kombucha {}", id)));
+            }
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn restore_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_entry SET deleted_at = NULL WHERE id = $1 AND kombucha_id = $2 AND deleted_at IS NOT NULL AND EXISTS (SELECT 1 FROM kombucha WHERE id = $2 AND deleted_at IS NULL)",
+        )
+        .bind(entry_id)
+        .bind(kombucha_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found(format!(
+                "Deleted entry {} of kombucha {}",
+                entry_id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn restore_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_fermentation SET deleted_at = NULL WHERE id = $1 AND kombucha_id = $2 AND deleted_at IS NOT NULL AND EXISTS (SELECT 1 FROM kombucha WHERE id = $2 AND deleted_at IS NULL)",
+        )
+        .bind(fermentation_id)
+        .bind(kombucha_id);
+
+        let rows = self.db.acquire().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found(format!(
+                "Deleted fermentation {} of kombucha {}",
+                fermentation_id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn purge_deleted(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<KombuchaId>> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            // Images of purged entries are kept, `ON DELETE SET NULL` moves
+            // them to the kombucha. Everything of a purged kombucha goes with
+            // it through `ON DELETE CASCADE`
+            for purge in &[
+                "DELETE FROM kombucha_entry WHERE deleted_at < $1",
+                "DELETE FROM kombucha_fermentation WHERE deleted_at < $1",
+            ] {
+                transaction.execute(sqlx::query(purge).bind(before)).await?;
+            }
+
+            let ids = sqlx::query_as::<_, (KombuchaId,)>(
+                "DELETE FROM kombucha WHERE deleted_at < $1 RETURNING id",
+            )
+            .bind(before)
+            .fetch_all(&mut transaction)
+            .await?;
+
+            Ok(ids.into_iter().map(|(id,)| id).collect())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+}
+
+#[async_trait]
+impl Migrate for PostgresStore {
+    fn migrations(&self) -> &'static [Migration] {
+        migrate::POSTGRES
+    }
+
+    async fn applied_versions(&self) -> Result<Vec<String>> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            transaction
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS schema_migration (version TEXT PRIMARY KEY, applied TIMESTAMPTZ NOT NULL DEFAULT NOW())",
+                )
+                .await?;
+
+            let (migrant,) = sqlx::query_as::<_, (bool,)>(
+                "SELECT to_regclass('__migrant_migrations') IS NOT NULL",
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+
+            if migrant {
+                transaction
+                    .execute(
+                        "INSERT INTO schema_migration (version) SELECT tag FROM __migrant_migrations ON CONFLICT DO NOTHING",
+                    )
+                    .await?;
+            }
+
+            let versions = sqlx::query_as::<_, (String,)>(
+                "SELECT version FROM schema_migration ORDER BY version",
+            )
+            .fetch_all(&mut transaction)
+            .await?;
+
+            Ok(versions.into_iter().map(|(version,)| version).collect())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn apply(&self, migration: &Migration) -> Result<()> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            transaction.execute(migration.up).await?;
+            transaction
+                .execute(
+                    sqlx::query(
+                        "INSERT INTO schema_migration (version) VALUES ($1)",
+                    )
+                    .bind(migration.version),
+                )
+                .await?;
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn revert(&self, migration: &Migration) -> Result<()> {
+        let mut transaction = self.db.begin().await?;
+
+        let result = async {
+            transaction.execute(migration.down).await?;
+            transaction
+                .execute(
+                    sqlx::query(
+                        "DELETE FROM schema_migration WHERE version = $1",
+                    )
+                    .bind(migration.version),
+                )
+                .await?;
+
+            // Keeps `migrant` from bringing the migration back
+            let (migrant,) = sqlx::query_as::<_, (bool,)>(
+                "SELECT to_regclass('__migrant_migrations') IS NOT NULL",
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+
+            if migrant {
+                transaction
+                    .execute(
+                        sqlx::query(
+                            "DELETE FROM __migrant_migrations WHERE tag = $1",
+                        )
+                        .bind(migration.version),
+                    )
+                    .await?;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+}
+
+/// Commits the transaction when its statements went through and rolls it back
+/// otherwise. A dropped transaction is rolled back only once its connection is
+/// closed in the background, its rows stay locked until then
+async fn finish<T>(
+    transaction: Transaction<PoolConnection<PgConnection>>,
+    result: Result<T>,
+) -> Result<T> {
+    match result {
+        Ok(value) => {
+            transaction.commit().await?;
+            Ok(value)
+        }
+        Err(err) => {
+            transaction.rollback().await?;
+            Err(err)
+        }
+    }
+}
+
+async fn insert_kombucha(
+    transaction: &mut Transaction<PoolConnection<PgConnection>>,
+    name: &str,
+) -> Result<KombuchaId> {
+    let (id,) = sqlx::query_as::<_, (KombuchaId,)>(
+        "INSERT INTO kombucha (name, added) VALUES ($1, NOW()) RETURNING id",
+    )
+    .bind(name)
+    .fetch_one(transaction)
+    .await?;
+
+    Ok(id)
+}
+
+async fn insert_fermentation(
+    transaction: &mut Transaction<PoolConnection<PgConnection>>,
+    kombucha_id: KombuchaId,
+    fermentation: &NewFermentation,
+) -> Result<FermentationId> {
+    let (id,) = sqlx::query_as::<_, (FermentationId,)>(
+        "INSERT INTO kombucha_fermentation (kombucha_id, start_date, est_end_date, status) SELECT $1, COALESCE($2, NOW()), $3, $4 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL) RETURNING id",
+    )
+    .bind(kombucha_id)
+    .bind(fermentation.start_date)
+    .bind(fermentation.est_end_date)
+    .bind(fermentation.status)
+    .fetch_optional(transaction)
+    .await?
+    .ok_or_else(|| Error::not_found(format!("Kombucha {}", kombucha_id)))?;
+
+    Ok(id)
+}
+
+async fn replace_recipe(
+    transaction: &mut Transaction<PoolConnection<PgConnection>>,
+    kombucha_id: KombuchaId,
+    recipe: &Recipe,
+) -> Result<()> {
+    let query = sqlx::query(
+        "INSERT INTO kombucha_recipe (kombucha_id, sugar_type, sugar_grams, water_ml, starter_ml, vessel, vessel_ml) SELECT $1, $2, $3, $4, $5, $6, $7 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = $1 AND deleted_at IS NULL) ON CONFLICT (kombucha_id) DO UPDATE SET (sugar_type, sugar_grams, water_ml, starter_ml, vessel, vessel_ml) = (EXCLUDED.sugar_type, EXCLUDED.sugar_grams, EXCLUDED.water_ml, EXCLUDED.starter_ml, EXCLUDED.vessel, EXCLUDED.vessel_ml)",
+    )
+    .bind(kombucha_id)
+    .bind(&recipe.sugar_type)
+    .bind(recipe.sugar_grams)
+    .bind(recipe.water_ml)
+    .bind(recipe.starter_ml)
+    .bind(&recipe.vessel)
+    .bind(recipe.vessel_ml);
+
+    if transaction.execute(query).await? == 0 {
+        return Err(Error::not_found(format!("Kombucha {}", kombucha_id)));
+    }
+
+    let query =
+        sqlx::query("DELETE FROM kombucha_recipe_tea WHERE kombucha_id = $1")
+            .bind(kombucha_id);
+    transaction.execute(query).await?;
+
+    for (position, tea) in recipe.teas.iter().enumerate() {
+        let query = sqlx::query(
+            "INSERT INTO kombucha_recipe_tea (kombucha_id, position, variety, grams) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(kombucha_id)
+        .bind(position as i32)
+        .bind(&tea.variety)
+        .bind(tea.grams);
+
+        transaction.execute(query).await?;
+    }
+
+    let query =
+        sqlx::query("DELETE FROM kombucha_recipe_stage WHERE kombucha_id = $1")
+            .bind(kombucha_id);
+    transaction.execute(query).await?;
+
+    for (position, stage) in recipe.stages.iter().enumerate() {
+        let query = sqlx::query(
+            "INSERT INTO kombucha_recipe_stage (kombucha_id, position, status, days) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(kombucha_id)
+        .bind(position as i32)
+        .bind(stage.status)
+        .bind(stage.days as i32);
+
+        transaction.execute(query).await?;
+    }
+
+    let query = sqlx::query(
+        "DELETE FROM kombucha_recipe_flavouring WHERE kombucha_id = $1",
+    )
+    .bind(kombucha_id);
+    transaction.execute(query).await?;
+
+    for (position, flavouring) in recipe.flavourings.iter().enumerate() {
+        let query = sqlx::query(
+            "INSERT INTO kombucha_recipe_flavouring (kombucha_id, position, ingredient, amount, stage) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(kombucha_id)
+        .bind(position as i32)
+        .bind(&flavouring.ingredient)
+        .bind(&flavouring.amount)
+        .bind(flavouring.stage);
+
+        transaction.execute(query).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+//! Full-text search over kombucha names and entries. Postgres matches and
+//! ranks with `tsvector` indexes, the other stores rank candidates here
+
+use crate::error::{Error, Result};
+use data_types::{EntryId, KombuchaId, SearchHit, SearchQuery, SnippetPart};
+use std::cmp::Ordering;
+
+pub const DEFAULT_LIMIT: u32 = 20;
+pub const MAX_LIMIT: u32 = 100;
+
+/// Marks where a highlighted word starts, control characters don't turn up in
+/// names or notes typed into a browser
+pub const START: &str = "\u{2}";
+/// Marks where a highlighted word ends
+pub const STOP: &str = "\u{3}";
+
+/// Words of an entry shown around its first match, as many as `ts_headline`
+/// shows by default
+const SNIPPET_WORDS: usize = 35;
+/// Words shown before the first match
+const SNIPPET_WORDS_BEFORE: usize = 5;
+/// Stands for the text cut off before or after a snippet, like the
+/// `FragmentDelimiter` of `ts_headline`
+const ELLIPSIS: &str = " ... ";
+
+pub fn limit(query: &SearchQuery) -> Result<u32> {
+    match query.limit {
+        None => Ok(DEFAULT_LIMIT),
+        Some(limit) if (1..=MAX_LIMIT).contains(&limit) => Ok(limit),
+        Some(limit) => Err(Error::Validation(format!(
+            "Limit {} isn't between 1 and {}",
+            limit, MAX_LIMIT
+        ))),
+    }
+}
+
+/// Lowercase words of the query, anything but letters and digits separates
+/// them so that nothing typed is taken as search syntax
+pub fn terms(q: &str) -> Vec<String> {
+    q.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Every term as a prefix, for `to_tsquery`
+pub fn tsquery(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|term| format!("{}:*", term))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+/// Splits a snippet marked with `START` and `STOP` into its parts
+pub fn snippet(marked: &str) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+
+    for (i, piece) in marked.split(START).enumerate() {
+        // Only the first piece doesn't start with a highlighted word
+        let (highlighted, rest) = match piece.find(STOP) {
+            Some(end) if i > 0 => (&piece[..end], &piece[end + STOP.len()..]),
+            _ => ("", piece),
+        };
+
+        if !highlighted.is_empty() {
+            parts.push(SnippetPart {
+                text: highlighted.to_string(),
+                highlighted: true,
+            });
+        }
+        if !rest.is_empty() {
+            parts.push(SnippetPart {
+                text: rest.to_string(),
+                highlighted: false,
+            });
+        }
+    }
+
+    parts
+}
+
+/// Ranks names (without an entry id) and entry contents that could match,
+/// keeping the best `limit` of those where every term starts a word. Names
+/// are highlighted whole, entries cut down to the words around their first
+/// match
+pub fn rank<'a>(
+    candidates: impl IntoIterator<
+        Item = (KombuchaId, String, Option<EntryId>, &'a str),
+    >,
+    terms: &[String],
+    limit: u32,
+) -> Vec<SearchHit> {
+    let mut hits: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|(kombucha_id, kombucha_name, entry_id, text)| {
+            let (pieces, rank) = highlight(text, terms)?;
+            let snippet = match entry_id {
+                Some(_) => window(&pieces),
+                None => parts(&pieces),
+            };
+
+            Some(SearchHit {
+                kombucha_id,
+                kombucha_name,
+                entry_id,
+                snippet,
+                rank,
+            })
+        })
+        .collect();
+
+    hits.sort_by(|a, b| {
+        b.rank
+            .partial_cmp(&a.rank)
+            .unwrap_or(Ordering::Equal)
+            .then(a.kombucha_id.cmp(&b.kombucha_id))
+            .then(a.entry_id.cmp(&b.entry_id))
+    });
+    hits.truncate(limit as usize);
+
+    hits
+}
+
+/// A word or what's between two words
+struct Piece<'a> {
+    text: &'a str,
+    is_word: bool,
+    highlighted: bool,
+}
+
+/// Splits `text` into pieces and highlights the words that start with one of
+/// the terms. `None` unless every term matched, otherwise the pieces and the
+/// share of words that matched as the rank
+fn highlight<'a>(
+    text: &'a str,
+    terms: &[String],
+) -> Option<(Vec<Piece<'a>>, f32)> {
+    let mut pieces = Vec::new();
+    let mut matched_terms = vec![false; terms.len()];
+    let mut words = 0;
+    let mut matches = 0;
+
+    let mut rest = text;
+    while let Some(first) = rest.chars().next() {
+        let is_word = first.is_alphanumeric();
+        let end = rest
+            .find(|c: char| c.is_alphanumeric() != is_word)
+            .unwrap_or(rest.len());
+        let (piece, tail) = rest.split_at(end);
+        rest = tail;
+
+        let mut highlighted = false;
+        if is_word {
+            words += 1;
+            let word = piece.to_lowercase();
+            for (term, matched) in terms.iter().zip(&mut matched_terms) {
+                if word.starts_with(term.as_str()) {
+                    *matched = true;
+                    highlighted = true;
+                }
+            }
+            if highlighted {
+                matches += 1;
+            }
+        }
+
+        pieces.push(Piece {
+            text: piece,
+            is_word,
+            highlighted,
+        });
+    }
+
+    if matched_terms.iter().all(|matched| *matched) {
+        Some((pieces, matches as f32 / words as f32))
+    } else {
+        None
+    }
+}
+
+/// The pieces from a few words before the first highlighted one, at most
+/// `SNIPPET_WORDS` words
+fn window(pieces: &[Piece]) -> Vec<SnippetPart> {
+    let words: Vec<usize> =
+        (0..pieces.len()).filter(|&i| pieces[i].is_word).collect();
+    let first_match = words
+        .iter()
+        .position(|&i| pieces[i].highlighted)
+        .unwrap_or(0);
+
+    let start = first_match.saturating_sub(SNIPPET_WORDS_BEFORE);
+    let end = words.len().min(start + SNIPPET_WORDS);
+    let from = if start == 0 { 0 } else { words[start] };
+    let to = if end == words.len() {
+        pieces.len()
+    } else {
+        words[end - 1] + 1
+    };
+
+    let mut snippet = Vec::new();
+    if from > 0 {
+        push_part(&mut snippet, ELLIPSIS.trim_start(), false);
+    }
+    for piece in &pieces[from..to] {
+        push_part(&mut snippet, piece.text, piece.highlighted);
+    }
+    if to < pieces.len() {
+        push_part(&mut snippet, ELLIPSIS.trim_end(), false);
+    }
+
+    snippet
+}
+
+fn parts(pieces: &[Piece]) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+    for piece in pieces {
+        push_part(&mut parts, piece.text, piece.highlighted);
+    }
+
+    parts
+}
+
+/// Appends to the last part when it's highlighted the same
+fn push_part(parts: &mut Vec<SnippetPart>, text: &str, highlighted: bool) {
+    match parts.last_mut() {
+        Some(last) if last.highlighted == highlighted => {
+            last.text.push_str(text)
+        }
+        _ => parts.push(SnippetPart {
+            text: text.to_string(),
+            highlighted,
+        }),
+    }
+}
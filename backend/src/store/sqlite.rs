@@ -0,0 +1,1900 @@
+//! SQLite has no timestamp type, dates are stored as RFC 3339 text in UTC
+//! with a fixed precision so that they sort chronologically
+
+use super::{
+    assemble_kombuchas, assemble_recipes,
+    list::{self, Cursor, Param},
+    next_status, search, template, template_recipe, KombuchaStore,
+};
+use crate::{
+    error::{Error, Result},
+    migrate::{self, Migrate, Migration},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use data_types::db::{
+    Entry as DbEntry, Fermentation as DbFermentation, Image as DbImage,
+    Kombucha as DbKombucha, Measurement as DbMeasurement, Recipe as DbRecipe,
+    RecipeFlavouring as DbRecipeFlavouring, RecipeStage as DbRecipeStage,
+    RecipeTea as DbRecipeTea,
+};
+use data_types::{
+    AdvanceFermentation, DeletedEntry, DeletedFermentation, DeletedKombucha,
+    Entry, EntryId, EntryPatch, Fermentation, FermentationId,
+    FermentationStatus, Image, ImageId, Kombucha, KombuchaId, KombuchaPage,
+    KombuchaPatch, KombuchaQuery, KombuchaSummary, Measurement, MeasurementId,
+    MeasurementKind, NewFermentation, NewMeasurement, NewRecipeTemplate,
+    Recipe, RecipeTemplate, SearchHit, SearchQuery, TemplateId, Trash,
+};
+use sqlx::{
+    pool::PoolConnection,
+    sqlite::{SqliteConnection, SqliteQueryAs},
+    Connection, Executor, SqlitePool, Transaction,
+};
+
+type KombuchaRow = (KombuchaId, String, String, i32, String);
+type EntryRow = (EntryId, KombuchaId, String, String, i32, String);
+type FermentationRow = (
+    FermentationId,
+    KombuchaId,
+    String,
+    Option<String>,
+    Option<String>,
+    FermentationStatus,
+);
+type ImageRow = (ImageId, KombuchaId, Option<EntryId>, String, String, String);
+type MeasurementRow = (
+    MeasurementId,
+    KombuchaId,
+    MeasurementKind,
+    f64,
+    String,
+    String,
+    Option<FermentationId>,
+);
+type RecipeRow = (KombuchaId, String, f64, f64, f64, String, Option<f64>);
+type RecipeTeaRow = (KombuchaId, i32, String, f64);
+type RecipeStageRow = (KombuchaId, i32, FermentationStatus, i32);
+type RecipeFlavouringRow =
+    (KombuchaId, i32, String, String, Option<FermentationStatus>);
+type TemplateRow = (TemplateId, String, String);
+type SummaryRow = (
+    KombuchaId,
+    String,
+    String,
+    Option<FermentationStatus>,
+    Option<FermentationId>,
+    Option<FermentationStatus>,
+    Option<String>,
+    i64,
+);
+type SearchRow = (KombuchaId, String, Option<EntryId>, String);
+type DeletedEntryRow = (
+    EntryId,
+    KombuchaId,
+    String,
+    String,
+    i32,
+    String,
+    String,
+    String,
+);
+type DeletedFermentationRow = (
+    FermentationId,
+    KombuchaId,
+    String,
+    Option<String>,
+    Option<String>,
+    FermentationStatus,
+    String,
+    String,
+);
+
+#[derive(Clone, Debug)]
+pub struct SqliteStore {
+    db: SqlitePool,
+}
+
+impl SqliteStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Foreign keys are off by default in SQLite and have to be enabled on
+    /// every connection
+    async fn connection(&self) -> Result<PoolConnection<SqliteConnection>> {
+        let mut connection = self.db.acquire().await?;
+        connection.execute("PRAGMA foreign_keys = ON").await?;
+
+        Ok(connection)
+    }
+
+    /// Migrations rebuild tables, which enforced foreign keys would cascade
+    /// into, so they run without them and `check_foreign_keys` afterwards
+    async fn migration_connection(
+        &self,
+    ) -> Result<PoolConnection<SqliteConnection>> {
+        let mut connection = self.db.acquire().await?;
+        connection.execute("PRAGMA foreign_keys = OFF").await?;
+
+        Ok(connection)
+    }
+
+    async fn get_db_entries_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbEntry>> {
+        sqlx::query_as::<_, EntryRow>(
+            "SELECT id, kombucha_id, content, added, version, updated_at FROM kombucha_entry WHERE kombucha_id = ? AND deleted_at IS NULL ORDER BY added, id",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut self.connection().await?)
+        .await?
+        .into_iter()
+        .map(db_entry)
+        .collect()
+    }
+
+    async fn get_db_fermentations_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbFermentation>> {
+        sqlx::query_as::<_, FermentationRow>(
+            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE kombucha_id = ? AND deleted_at IS NULL ORDER BY start_date, id"
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut self.connection().await?)
+        .await?
+        .into_iter()
+        .map(db_fermentation)
+        .collect()
+    }
+
+    async fn get_db_images_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbImage>> {
+        sqlx::query_as::<_, ImageRow>(
+            "SELECT i.id, i.kombucha_id, i.entry_id, i.file_name, i.content_type, i.added FROM kombucha_image i JOIN kombucha k ON k.id = i.kombucha_id WHERE i.kombucha_id = ? AND k.deleted_at IS NULL ORDER BY i.added, i.id"
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut self.connection().await?)
+        .await?
+        .into_iter()
+        .map(db_image)
+        .collect()
+    }
+
+    async fn get_db_measurements_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbMeasurement>> {
+        sqlx::query_as::<_, MeasurementRow>(
+            "SELECT m.id, m.kombucha_id, m.kind, m.value, m.unit, m.taken_at, m.fermentation_id FROM kombucha_measurement m JOIN kombucha k ON k.id = m.kombucha_id WHERE m.kombucha_id = ? AND k.deleted_at IS NULL ORDER BY m.taken_at, m.id"
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut self.connection().await?)
+        .await?
+        .into_iter()
+        .map(db_measurement)
+        .collect()
+    }
+
+    async fn get_latest_db_measurements_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<DbMeasurement>> {
+        sqlx::query_as::<_, MeasurementRow>(
+            "SELECT id, kombucha_id, kind, value, unit, taken_at, fermentation_id FROM (SELECT m.* FROM kombucha_measurement m JOIN kombucha k ON k.id = m.kombucha_id WHERE m.kombucha_id = ? AND k.deleted_at IS NULL ORDER BY m.taken_at DESC, m.id DESC LIMIT ?) ORDER BY taken_at, id"
+        )
+        .bind(kombucha_id)
+        .bind(Kombucha::LATEST_MEASUREMENTS as i64)
+        .fetch_all(&mut self.connection().await?)
+        .await?
+        .into_iter()
+        .map(db_measurement)
+        .collect()
+    }
+
+    async fn get_recipe_for_kombucha(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Recipe>> {
+        let mut connection = self.connection().await?;
+
+        let db_recipe = match sqlx::query_as::<_, RecipeRow>(
+            "SELECT r.kombucha_id, r.sugar_type, r.sugar_grams, r.water_ml, r.starter_ml, r.vessel, r.vessel_ml FROM kombucha_recipe r JOIN kombucha k ON k.id = r.kombucha_id WHERE r.kombucha_id = ? AND k.deleted_at IS NULL"
+        )
+        .bind(kombucha_id)
+        .fetch_optional(&mut connection)
+        .await?
+        {
+            Some(row) => db_recipe(row),
+            None => return Ok(None),
+        };
+
+        let db_teas = sqlx::query_as::<_, RecipeTeaRow>(
+            "SELECT kombucha_id, position, variety, grams FROM kombucha_recipe_tea WHERE kombucha_id = ? ORDER BY position",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe_tea)
+        .collect();
+
+        let db_stages = sqlx::query_as::<_, RecipeStageRow>(
+            "SELECT kombucha_id, position, status, days FROM kombucha_recipe_stage WHERE kombucha_id = ? ORDER BY position",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe_stage)
+        .collect();
+
+        let db_flavourings = sqlx::query_as::<_, RecipeFlavouringRow>(
+            "SELECT kombucha_id, position, ingredient, amount, stage FROM kombucha_recipe_flavouring WHERE kombucha_id = ? ORDER BY position",
+        )
+        .bind(kombucha_id)
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe_flavouring)
+        .collect();
+
+        Ok(Some(Recipe::from_db(
+            db_recipe,
+            db_teas,
+            db_stages,
+            db_flavourings,
+        )))
+    }
+
+    async fn assemble_kombucha(
+        &self,
+        db_kombucha: DbKombucha,
+    ) -> Result<Kombucha> {
+        let db_entries =
+            self.get_db_entries_for_kombucha(db_kombucha.id).await?;
+        let db_fermentations = self
+            .get_db_fermentations_for_kombucha(db_kombucha.id)
+            .await?;
+        let db_images = self.get_db_images_for_kombucha(db_kombucha.id).await?;
+        let db_measurements = self
+            .get_latest_db_measurements_for_kombucha(db_kombucha.id)
+            .await?;
+        let recipe = self.get_recipe_for_kombucha(db_kombucha.id).await?;
+
+        let DbKombucha {
+            id,
+            name,
+            added,
+            version,
+            updated_at,
+        } = db_kombucha;
+
+        let entries = db_entries.into_iter().map(Entry::from).collect();
+        let fermentations = db_fermentations
+            .into_iter()
+            .map(Fermentation::from)
+            .collect();
+        let images = db_images.into_iter().map(Image::from).collect();
+        let measurements =
+            db_measurements.into_iter().map(Measurement::from).collect();
+
+        Ok(Kombucha {
+            id,
+            name,
+            added,
+            version,
+            updated_at,
+            entries,
+            fermentations,
+            images,
+            measurements,
+            recipe,
+        })
+    }
+}
+
+fn to_text(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn from_text(text: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|err| {
+            Error::Internal(anyhow::anyhow!("Invalid date {}: {}", text, err))
+        })
+}
+
+fn from_optional_text(text: Option<String>) -> Result<Option<DateTime<Utc>>> {
+    text.as_deref().map(from_text).transpose()
+}
+
+fn db_kombucha(
+    (id, name, added, version, updated_at): KombuchaRow,
+) -> Result<DbKombucha> {
+    Ok(DbKombucha {
+        id,
+        name,
+        added: from_text(&added)?,
+        version,
+        updated_at: from_text(&updated_at)?,
+    })
+}
+
+fn db_entry(
+    (id, kombucha_id, content, added, version, updated_at): EntryRow,
+) -> Result<DbEntry> {
+    Ok(DbEntry {
+        id,
+        kombucha_id,
+        content,
+        added: from_text(&added)?,
+        version,
+        updated_at: from_text(&updated_at)?,
+    })
+}
+
+fn db_fermentation(
+    (id, kombucha_id, start_date, end_date, est_end_date, status): FermentationRow,
+) -> Result<DbFermentation> {
+    Ok(DbFermentation {
+        id,
+        kombucha_id,
+        start_date: from_text(&start_date)?,
+        end_date: from_optional_text(end_date)?,
+        est_end_date: from_optional_text(est_end_date)?,
+        status,
+    })
+}
+
+fn summary(
+    (
+        id,
+        name,
+        added,
+        status,
+        fermentation_id,
+        milestone_status,
+        est_end_date,
+        entry_count,
+    ): SummaryRow,
+) -> Result<KombuchaSummary> {
+    Ok(list::summary(
+        id,
+        name,
+        from_text(&added)?,
+        status,
+        (
+            fermentation_id,
+            milestone_status,
+            from_optional_text(est_end_date)?,
+        ),
+        entry_count,
+    ))
+}
+
+fn db_image(
+    (id, kombucha_id, entry_id, file_name, content_type, added): ImageRow,
+) -> Result<DbImage> {
+    Ok(DbImage {
+        id,
+        kombucha_id,
+        entry_id,
+        file_name,
+        content_type,
+        added: from_text(&added)?,
+    })
+}
+
+fn db_measurement(
+    (id, kombucha_id, kind, value, unit, taken_at, fermentation_id): MeasurementRow,
+) -> Result<DbMeasurement> {
+    Ok(DbMeasurement {
+        id,
+        kombucha_id,
+        kind,
+        value,
+        unit,
+        taken_at: from_text(&taken_at)?,
+        fermentation_id,
+    })
+}
+
+fn db_recipe(
+    (
+        kombucha_id,
+        sugar_type,
+        sugar_grams,
+        water_ml,
+        starter_ml,
+        vessel,
+        vessel_ml,
+    ): RecipeRow,
+) -> DbRecipe {
+    DbRecipe {
+        kombucha_id,
+        sugar_type,
+        sugar_grams,
+        water_ml,
+        starter_ml,
+        vessel,
+        vessel_ml,
+    }
+}
+
+fn db_recipe_tea(
+    (kombucha_id, position, variety, grams): RecipeTeaRow,
+) -> DbRecipeTea {
+    DbRecipeTea {
+        kombucha_id,
+        position,
+        variety,
+        grams,
+    }
+}
+
+fn db_recipe_stage(
+    (kombucha_id, position, status, days): RecipeStageRow,
+) -> DbRecipeStage {
+    DbRecipeStage {
+        kombucha_id,
+        position,
+        status,
+        days,
+    }
+}
+
+fn db_recipe_flavouring(
+    (kombucha_id, position, ingredient, amount, stage): RecipeFlavouringRow,
+) -> DbRecipeFlavouring {
+    DbRecipeFlavouring {
+        kombucha_id,
+        position,
+        ingredient,
+        amount,
+        stage,
+    }
+}
+
+#[async_trait]
+impl KombuchaStore for SqliteStore {
+    // CREATE
+
+    async fn create_kombucha(&self, name: &str) -> Result<KombuchaId> {
+        let mut transaction = self.connection().await?.begin().await?;
+        let result = insert_kombucha(&mut transaction, name).await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_kombucha_with_recipe(
+        &self,
+        name: &str,
+        recipe: &Recipe,
+        fermentation: Option<&NewFermentation>,
+    ) -> Result<KombuchaId> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let id = insert_kombucha(&mut transaction, name).await?;
+            replace_recipe(&mut transaction, id, recipe).await?;
+            if let Some(fermentation) = fermentation {
+                insert_fermentation(&mut transaction, id, fermentation).await?;
+            }
+
+            Ok(id)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_entry(&self, kombucha_id: KombuchaId) -> Result<EntryId> {
+        let mut connection = self.connection().await?;
+
+        let query = sqlx::query(
+            "INSERT INTO kombucha_entry (kombucha_id, content, added, updated_at) SELECT ?1, '', ?2, ?2 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+        )
+        .bind(kombucha_id)
+        .bind(to_text(Utc::now()));
+
+        if connection.execute(query).await? == 0 {
+            return Err(Error::not_found(format!("Kombucha {}", kombucha_id)));
+        }
+
+        let (id,) =
+            sqlx::query_as::<_, (EntryId,)>("SELECT last_insert_rowid()")
+                .fetch_one(&mut connection)
+                .await?;
+
+        Ok(id)
+    }
+
+    async fn create_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &NewFermentation,
+    ) -> Result<FermentationId> {
+        let mut transaction = self.connection().await?.begin().await?;
+        let result =
+            insert_fermentation(&mut transaction, kombucha_id, fermentation)
+                .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_image(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: Option<EntryId>,
+        content_type: &str,
+        file_name: &(dyn Fn(ImageId) -> String + Sync),
+    ) -> Result<DbImage> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let added = Utc::now();
+
+            let query = sqlx::query(
+                "INSERT INTO kombucha_image (kombucha_id, entry_id, file_name, content_type, added) SELECT ?1, ?2, '', ?3, ?4 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+            )
+            .bind(kombucha_id)
+            .bind(entry_id)
+            .bind(content_type)
+            .bind(to_text(added));
+
+            if transaction.execute(query).await? == 0 {
+                return Err(Error::not_found(format!(
+                    "Kombucha {}",
+                    kombucha_id
+                )));
+            }
+
+            let (id,) =
+                sqlx::query_as::<_, (ImageId,)>("SELECT last_insert_rowid()")
+                    .fetch_one(&mut transaction)
+                    .await?;
+
+            let file_name = file_name(id);
+
+            let query =
+                sqlx::query("UPDATE kombucha_image SET file_name = ? WHERE id = ?")
+                    .bind(&file_name)
+                    .bind(id);
+
+            transaction.execute(query).await?;
+
+            Ok(DbImage {
+                id,
+                kombucha_id,
+                entry_id,
+                file_name,
+                content_type: content_type.to_string(),
+                added,
+            })
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn create_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &NewMeasurement,
+    ) -> Result<MeasurementId> {
+        let mut connection = self.connection().await?;
+
+        let query = sqlx::query(
+            "INSERT INTO kombucha_measurement (kombucha_id, fermentation_id, kind, value, unit, taken_at) SELECT ?1, ?2, ?3, ?4, ?5, ?6 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+        )
+        .bind(kombucha_id)
+        .bind(measurement.fermentation_id)
+        .bind(measurement.kind)
+        .bind(measurement.value)
+        .bind(measurement.unit())
+        .bind(to_text(measurement.taken_at.unwrap_or_else(Utc::now)));
+
+        if connection.execute(query).await? == 0 {
+            return Err(Error::not_found(format!("Kombucha {}", kombucha_id)));
+        }
+
+        let (id,) =
+            sqlx::query_as::<_, (MeasurementId,)>("SELECT last_insert_rowid()")
+                .fetch_one(&mut connection)
+                .await?;
+
+        Ok(id)
+    }
+
+    async fn create_template(
+        &self,
+        template: &NewRecipeTemplate,
+    ) -> Result<TemplateId> {
+        let mut connection = self.connection().await?;
+
+        let query = sqlx::query(
+            "INSERT INTO recipe_template (name, recipe) VALUES (?, ?)",
+        )
+        .bind(&template.name)
+        .bind(template_recipe(&template.recipe)?);
+
+        connection.execute(query).await?;
+
+        let (id,) =
+            sqlx::query_as::<_, (TemplateId,)>("SELECT last_insert_rowid()")
+                .fetch_one(&mut connection)
+                .await?;
+
+        Ok(id)
+    }
+
+    // GET
+
+    async fn get_all_kombuchas(&self) -> Result<Vec<Kombucha>> {
+        let mut connection = self.connection().await?;
+
+        // Every kombucha is listed, so the rows attached to them are loaded
+        // whole rather than bound to thousands of ids. Images of deleted
+        // kombuchas have nothing to attach to and are dropped
+        let db_kombuchas = sqlx::query_as::<_, KombuchaRow>(
+            "SELECT id, name, added, version, updated_at FROM kombucha WHERE deleted_at IS NULL ORDER BY id",
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_kombucha)
+        .collect::<Result<_>>()?;
+
+        let db_entries = sqlx::query_as::<_, EntryRow>(
+            "SELECT id, kombucha_id, content, added, version, updated_at FROM kombucha_entry WHERE deleted_at IS NULL ORDER BY added, id",
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_entry)
+        .collect::<Result<_>>()?;
+
+        let db_fermentations = sqlx::query_as::<_, FermentationRow>(
+            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE deleted_at IS NULL ORDER BY start_date, id"
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_fermentation)
+        .collect::<Result<_>>()?;
+
+        let db_images = sqlx::query_as::<_, ImageRow>(
+            "SELECT id, kombucha_id, entry_id, file_name, content_type, added FROM kombucha_image ORDER BY added, id"
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_image)
+        .collect::<Result<_>>()?;
+
+        let db_measurements = sqlx::query_as::<_, MeasurementRow>(
+            "SELECT id, kombucha_id, kind, value, unit, taken_at, fermentation_id FROM (SELECT *, ROW_NUMBER() OVER (PARTITION BY kombucha_id ORDER BY taken_at DESC, id DESC) AS position FROM kombucha_measurement) WHERE position <= ? ORDER BY taken_at, id"
+        )
+        .bind(Kombucha::LATEST_MEASUREMENTS as i64)
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_measurement)
+        .collect::<Result<_>>()?;
+
+        let db_recipes = sqlx::query_as::<_, RecipeRow>(
+            "SELECT kombucha_id, sugar_type, sugar_grams, water_ml, starter_ml, vessel, vessel_ml FROM kombucha_recipe"
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe)
+        .collect();
+
+        let db_recipe_teas = sqlx::query_as::<_, RecipeTeaRow>(
+            "SELECT kombucha_id, position, variety, grams FROM kombucha_recipe_tea ORDER BY kombucha_id, position"
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe_tea)
+        .collect();
+
+        let db_recipe_stages = sqlx::query_as::<_, RecipeStageRow>(
+            "SELECT kombucha_id, position, status, days FROM kombucha_recipe_stage ORDER BY kombucha_id, position"
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe_stage)
+        .collect();
+
+        let db_recipe_flavourings = sqlx::query_as::<_, RecipeFlavouringRow>(
+            "SELECT kombucha_id, position, ingredient, amount, stage FROM kombucha_recipe_flavouring ORDER BY kombucha_id, position"
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(db_recipe_flavouring)
+        .collect();
+
+        Ok(assemble_kombuchas(
+            db_kombuchas,
+            db_entries,
+            db_fermentations,
+            db_images,
+            db_measurements,
+            assemble_recipes(
+                db_recipes,
+                db_recipe_teas,
+                db_recipe_stages,
+                db_recipe_flavourings,
+            ),
+        ))
+    }
+
+    async fn list_kombuchas(
+        &self,
+        query: &KombuchaQuery,
+    ) -> Result<KombuchaPage> {
+        let limit = list::limit(query)?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(query.sort, cursor))
+            .transpose()?;
+
+        // LIKE already ignores the case of ASCII letters in SQLite
+        let (sql, params) =
+            list::summaries_sql(query, cursor.as_ref(), limit, '?', "LIKE");
+
+        let mut rows = sqlx::query_as::<_, SummaryRow>(&sql);
+        for param in params {
+            rows = match param {
+                Param::Status(status) => rows.bind(status),
+                Param::Text(text) => rows.bind(text),
+                Param::Date(date) => rows.bind(to_text(date)),
+                Param::Id(id) => rows.bind(id),
+                Param::Limit(limit) => rows.bind(limit),
+            };
+        }
+
+        let summaries = rows
+            .fetch_all(&mut self.connection().await?)
+            .await?
+            .into_iter()
+            .map(summary)
+            .collect::<Result<_>>()?;
+
+        Ok(list::page(summaries, query.sort, limit))
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let limit = search::limit(query)?;
+        let terms = search::terms(&query.q);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // sqlx prepares statements with `SQLITE_PREPARE_NO_VTAB`, which rules
+        // out FTS5. LIKE narrows down the candidates, terms are letters and
+        // digits only so they need no escaping
+        let matches = |column: &str| {
+            (1..=terms.len())
+                .map(|i| format!("{} LIKE ?{}", column, i))
+                .collect::<Vec<_>>()
+                .join(" AND ")
+        };
+        let sql = format!(
+            "SELECT k.id, k.name, NULL, k.name FROM kombucha k
+            WHERE k.deleted_at IS NULL AND {}
+            UNION ALL
+            SELECT e.kombucha_id, k.name, e.id, e.content
+            FROM kombucha_entry e
+            JOIN kombucha k ON k.id = e.kombucha_id
+            WHERE e.deleted_at IS NULL AND k.deleted_at IS NULL AND {}",
+            matches("k.name"),
+            matches("e.content"),
+        );
+
+        let mut rows = sqlx::query_as::<_, SearchRow>(&sql);
+        for term in &terms {
+            rows = rows.bind(format!("%{}%", term));
+        }
+        let rows = rows.fetch_all(&mut self.connection().await?).await?;
+
+        Ok(search::rank(
+            rows.iter()
+                .map(|(kombucha_id, kombucha_name, entry_id, text)| {
+                    (
+                        *kombucha_id,
+                        kombucha_name.clone(),
+                        *entry_id,
+                        text.as_str(),
+                    )
+                }),
+            &terms,
+            limit,
+        ))
+    }
+
+    async fn get_kombucha(&self, id: KombuchaId) -> Result<Option<Kombucha>> {
+        match self.get_kombucha_row(id).await? {
+            Some(db_kombucha) => {
+                Ok(Some(self.assemble_kombucha(db_kombucha).await?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_kombucha_row(
+        &self,
+        id: KombuchaId,
+    ) -> Result<Option<DbKombucha>> {
+        sqlx::query_as::<_, KombuchaRow>(
+            "SELECT id, name, added, version, updated_at FROM kombucha WHERE id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_optional(&mut self.connection().await?)
+        .await?
+        .map(db_kombucha)
+        .transpose()
+    }
+
+    async fn get_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<Option<Entry>> {
+        let maybe_entry = sqlx::query_as::<_, EntryRow>(
+            "SELECT id, kombucha_id, content, added, version, updated_at FROM kombucha_entry WHERE id = ? AND kombucha_id = ? AND deleted_at IS NULL"
+        )
+        .bind(entry_id)
+        .bind(kombucha_id)
+        .fetch_optional(&mut self.connection().await?)
+        .await?
+        .map(db_entry)
+        .transpose()?;
+
+        Ok(maybe_entry.map(Entry::from))
+    }
+
+    async fn get_entries(&self, kombucha_id: KombuchaId) -> Result<Vec<Entry>> {
+        let entries = self
+            .get_db_entries_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Entry::from)
+            .collect();
+
+        Ok(entries)
+    }
+
+    async fn get_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<Option<Fermentation>> {
+        let maybe_fermentation = sqlx::query_as::<_, FermentationRow>(
+            "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE id = ? AND kombucha_id = ? AND deleted_at IS NULL"
+        )
+        .bind(fermentation_id)
+        .bind(kombucha_id)
+        .fetch_optional(&mut self.connection().await?)
+        .await?
+        .map(db_fermentation)
+        .transpose()?;
+
+        Ok(maybe_fermentation.map(Fermentation::from))
+    }
+
+    async fn get_fermentations(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Fermentation>> {
+        let fermentations = self
+            .get_db_fermentations_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Fermentation::from)
+            .collect();
+
+        Ok(fermentations)
+    }
+
+    async fn get_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<Option<DbImage>> {
+        sqlx::query_as::<_, ImageRow>(
+            "SELECT i.id, i.kombucha_id, i.entry_id, i.file_name, i.content_type, i.added FROM kombucha_image i JOIN kombucha k ON k.id = i.kombucha_id WHERE i.id = ? AND i.kombucha_id = ? AND k.deleted_at IS NULL"
+        )
+        .bind(image_id)
+        .bind(kombucha_id)
+        .fetch_optional(&mut self.connection().await?)
+        .await?
+        .map(db_image)
+        .transpose()
+    }
+
+    async fn get_images(&self, kombucha_id: KombuchaId) -> Result<Vec<Image>> {
+        let images = self
+            .get_db_images_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Image::from)
+            .collect();
+
+        Ok(images)
+    }
+
+    async fn get_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<Option<Measurement>> {
+        sqlx::query_as::<_, MeasurementRow>(
+            "SELECT m.id, m.kombucha_id, m.kind, m.value, m.unit, m.taken_at, m.fermentation_id FROM kombucha_measurement m JOIN kombucha k ON k.id = m.kombucha_id WHERE m.id = ? AND m.kombucha_id = ? AND k.deleted_at IS NULL"
+        )
+        .bind(measurement_id)
+        .bind(kombucha_id)
+        .fetch_optional(&mut self.connection().await?)
+        .await?
+        .map(|row| db_measurement(row).map(Measurement::from))
+        .transpose()
+    }
+
+    async fn get_measurements(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Vec<Measurement>> {
+        let measurements = self
+            .get_db_measurements_for_kombucha(kombucha_id)
+            .await?
+            .into_iter()
+            .map(Measurement::from)
+            .collect();
+
+        Ok(measurements)
+    }
+
+    async fn get_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+    ) -> Result<Option<Recipe>> {
+        self.get_recipe_for_kombucha(kombucha_id).await
+    }
+
+    async fn get_templates(&self) -> Result<Vec<RecipeTemplate>> {
+        sqlx::query_as::<_, TemplateRow>(
+            "SELECT id, name, recipe FROM recipe_template ORDER BY name, id",
+        )
+        .fetch_all(&mut self.connection().await?)
+        .await?
+        .into_iter()
+        .map(template)
+        .collect()
+    }
+
+    async fn get_template(
+        &self,
+        id: TemplateId,
+    ) -> Result<Option<RecipeTemplate>> {
+        sqlx::query_as::<_, TemplateRow>(
+            "SELECT id, name, recipe FROM recipe_template WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&mut self.connection().await?)
+        .await?
+        .map(template)
+        .transpose()
+    }
+
+    // UPDATE
+
+    async fn update_kombucha(&self, kombucha: &Kombucha) -> Result<i32> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let now = to_text(Utc::now());
+
+            let query =
+                sqlx::query("UPDATE kombucha SET name = ?, added = ?, version = version + 1, updated_at = ? WHERE id = ? AND version = ? AND deleted_at IS NULL")
+                    .bind(&kombucha.name)
+                    .bind(to_text(kombucha.added))
+                    .bind(&now)
+                    .bind(kombucha.id)
+                    .bind(kombucha.version);
+
+            if transaction.execute(query).await? == 0 {
+                let current = sqlx::query_as::<_, (i32,)>(
+                    "SELECT version FROM kombucha WHERE id = ? AND deleted_at IS NULL",
+                )
+                .bind(kombucha.id)
+                .fetch_optional(&mut transaction)
+                .await?;
+
+                return Err(Error::stale(
+                    format!("Kombucha {}", kombucha.id),
+                    kombucha.version,
+                    current.map(|(version,)| version),
+                ));
+            }
+
+            for entry in &kombucha.entries {
+                let query = sqlx::query("UPDATE kombucha_entry SET added = ?, content = ?, version = version + 1, updated_at = ? WHERE id = ? AND kombucha_id = ? AND deleted_at IS NULL")
+                    .bind(to_text(entry.added))
+                    .bind(&entry.content)
+                    .bind(&now)
+                    .bind(entry.id)
+                    .bind(kombucha.id);
+
+                if transaction.execute(query).await? == 0 {
+                    return Err(Error::not_found(format!(
+                        "Entry {} of kombucha {}",
+                        entry.id, kombucha.id
+                    )));
+                }
+            }
+
+            Ok(kombucha.version + 1)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn patch_kombucha(
+        &self,
+        id: KombuchaId,
+        version: i32,
+        patch: &KombuchaPatch,
+    ) -> Result<i32> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let query = sqlx::query(
+                "UPDATE kombucha SET name = COALESCE(?, name), added = COALESCE(?, added), version = version + 1, updated_at = ? WHERE id = ? AND version = ? AND deleted_at IS NULL",
+            )
+            .bind(&patch.name)
+            .bind(patch.added.map(to_text))
+            .bind(to_text(Utc::now()))
+            .bind(id)
+            .bind(version);
+
+            if transaction.execute(query).await? == 0 {
+                let current = sqlx::query_as::<_, (i32,)>(
+                    "SELECT version FROM kombucha WHERE id = ? AND deleted_at IS NULL",
+                )
+                .bind(id)
+                .fetch_optional(&mut transaction)
+                .await?;
+
+                return Err(Error::stale(
+                    format!("Kombucha {}", id),
+                    version,
+                    current.map(|(version,)| version),
+                ));
+            }
+
+            Ok(version + 1)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn patch_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+        version: i32,
+        patch: &EntryPatch,
+    ) -> Result<i32> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let now = to_text(Utc::now());
+
+            let query = sqlx::query(
+                "UPDATE kombucha_entry SET added = COALESCE(?, added), content = COALESCE(?, content), version = version + 1, updated_at = ? WHERE id = ? AND kombucha_id = ? AND version = ? AND deleted_at IS NULL",
+            )
+            .bind(patch.added.map(to_text))
+            .bind(&patch.content)
+            .bind(&now)
+            .bind(entry_id)
+            .bind(kombucha_id)
+            .bind(version);
+
+            if transaction.execute(query).await? == 0 {
+                let current = sqlx::query_as::<_, (i32,)>(
+                    "SELECT version FROM kombucha_entry WHERE id = ? AND kombucha_id = ? AND deleted_at IS NULL",
+                )
+                .bind(entry_id)
+                .bind(kombucha_id)
+                .fetch_optional(&mut transaction)
+                .await?;
+
+                return Err(Error::stale(
+                    format!("Entry {} of kombucha {}", entry_id, kombucha_id),
+                    version,
+                    current.map(|(version,)| version),
+                ));
+            }
+
+            transaction
+                .execute(
+                    sqlx::query(
+                        "UPDATE kombucha SET version = version + 1, updated_at = ? WHERE id = ?",
+                    )
+                    .bind(&now)
+                    .bind(kombucha_id),
+                )
+                .await?;
+
+            Ok(version + 1)
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn update_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation: &Fermentation,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_fermentation SET start_date = ?, end_date = ?, est_end_date = ?, status = ? WHERE id = ? AND kombucha_id = ? AND deleted_at IS NULL",
+        )
+        .bind(to_text(fermentation.start_date))
+        .bind(fermentation.end_date.map(to_text))
+        .bind(fermentation.est_end_date.map(to_text))
+        .bind(fermentation.status)
+        .bind(fermentation.id)
+        .bind(kombucha_id);
+
+        let updated = self.connection().await?.execute(query).await?;
+
+        if updated == 0 {
+            return Err(Error::not_found(format!(
+                "Fermentation {} of kombucha {}",
+                fermentation.id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn advance_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        advance: &AdvanceFermentation,
+    ) -> Result<Fermentation> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let now = advance.start_date.unwrap_or_else(Utc::now);
+
+            let current = sqlx::query_as::<_, FermentationRow>(
+                "SELECT id, kombucha_id, start_date, end_date, est_end_date, status FROM kombucha_fermentation WHERE kombucha_id = ? AND deleted_at IS NULL ORDER BY start_date DESC, id DESC LIMIT 1",
+            )
+            .bind(kombucha_id)
+            .fetch_optional(&mut transaction)
+            .await?
+            .map(db_fermentation)
+            .transpose()?;
+
+            let status = next_status(
+                current.as_ref().map(|current| current.status),
+                advance.status,
+            )?;
+
+            if let Some(current) = &current {
+                let query = sqlx::query(
+                    "UPDATE kombucha_fermentation SET end_date = ? WHERE id = ? AND end_date IS NULL",
+                )
+                .bind(to_text(now))
+                .bind(current.id);
+
+                transaction.execute(query).await?;
+            }
+
+            let end_date = if status.is_terminal() {
+                Some(now)
+            } else {
+                None
+            };
+
+            let query = sqlx::query(
+                "INSERT INTO kombucha_fermentation (kombucha_id, start_date, end_date, est_end_date, status) SELECT ?1, ?2, ?3, ?4, ?5 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+            )
+            .bind(kombucha_id)
+            .bind(to_text(now))
+            .bind(end_date.map(to_text))
+            .bind(advance.est_end_date.map(to_text))
+            .bind(status);
+
+            if transaction.execute(query).await? == 0 {
+                return Err(Error::not_found(format!(
+                    "Kombucha {}",
+                    kombucha_id
+                )));
+            }
+
+            let (id,) = sqlx::query_as::<_, (FermentationId,)>(
+                "SELECT last_insert_rowid()",
+            )
+            .fetch_one(&mut transaction)
+            .await?;
+
+            Ok(Fermentation {
+                id,
+                start_date: now,
+                end_date,
+                est_end_date: advance.est_end_date,
+                status,
+            })
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn update_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement: &Measurement,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_measurement SET fermentation_id = ?1, kind = ?2, value = ?3, unit = ?4, taken_at = ?5 WHERE id = ?6 AND kombucha_id = ?7 AND EXISTS (SELECT 1 FROM kombucha WHERE id = ?7 AND deleted_at IS NULL)",
+        )
+        .bind(measurement.fermentation_id)
+        .bind(measurement.kind)
+        .bind(measurement.value)
+        .bind(&measurement.unit)
+        .bind(to_text(measurement.taken_at))
+        .bind(measurement.id)
+        .bind(kombucha_id);
+
+        let updated = self.connection().await?.execute(query).await?;
+
+        if updated == 0 {
+            return Err(Error::not_found(format!(
+                "Measurement {} of kombucha {}",
+                measurement.id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn set_recipe(
+        &self,
+        kombucha_id: KombuchaId,
+        recipe: &Recipe,
+    ) -> Result<()> {
+        let mut transaction = self.connection().await?.begin().await?;
+        let result =
+            replace_recipe(&mut transaction, kombucha_id, recipe).await;
+
+        finish(transaction, result).await
+    }
+
+    // DELETE
+
+    async fn delete_kombucha(&self, id: KombuchaId) -> Result<()> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            // Children are marked with the same date, that's how a restore
+            // tells them from those deleted before
+            let now = to_text(Utc::now());
+
+            let query = sqlx::query(
+                "UPDATE kombucha SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+            )
+            .bind(&now)
+            .bind(id);
+
+            if transaction.execute(query).await? == 0 {
+                return Err(Error::not_found("kombucha"));
+            }
+
+            for delete in &[
+                "UPDATE kombucha_entry SET deleted_at = ? WHERE kombucha_id = ? AND deleted_at IS NULL",
+                "UPDATE kombucha_fermentation SET deleted_at = ? WHERE kombucha_id = ? AND deleted_at IS NULL",
+            ] {
+                transaction
+                    .execute(sqlx::query(delete).bind(&now).bind(id))
+                    .await?;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn delete_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_entry SET deleted_at = ? WHERE kombucha_id = ? AND id = ? AND deleted_at IS NULL",
+        )
+        .bind(to_text(Utc::now()))
+        .bind(kombucha_id)
+        .bind(entry_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("entry"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_fermentation SET deleted_at = ? WHERE kombucha_id = ? AND id = ? AND deleted_at IS NULL",
+        )
+        .bind(to_text(Utc::now()))
+        .bind(kombucha_id)
+        .bind(fermentation_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("fermentation"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_image(
+        &self,
+        kombucha_id: KombuchaId,
+        image_id: ImageId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "DELETE FROM kombucha_image WHERE kombucha_id = ? AND id = ?",
+        )
+        .bind(kombucha_id)
+        .bind(image_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("image"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_measurement(
+        &self,
+        kombucha_id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "DELETE FROM kombucha_measurement WHERE kombucha_id = ?1 AND id = ?2 AND EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+        )
+        .bind(kombucha_id)
+        .bind(measurement_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("measurement"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_recipe(&self, kombucha_id: KombuchaId) -> Result<()> {
+        // The teas and stages go with it through `ON DELETE CASCADE`
+        let query = sqlx::query(
+            "DELETE FROM kombucha_recipe WHERE kombucha_id = ?1 AND EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+        )
+        .bind(kombucha_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("recipe"));
+        }
+
+        Ok(())
+    }
+
+    async fn delete_template(&self, id: TemplateId) -> Result<()> {
+        let query =
+            sqlx::query("DELETE FROM recipe_template WHERE id = ?").bind(id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found("template"));
+        }
+
+        Ok(())
+    }
+
+    // TRASH
+
+    async fn get_trash(&self) -> Result<Trash> {
+        let mut connection = self.connection().await?;
+
+        let kombuchas = sqlx::query_as::<_, (KombuchaId, String, String)>(
+            "SELECT id, name, deleted_at FROM kombucha WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC",
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(|(id, name, deleted_at)| {
+            Ok(DeletedKombucha {
+                id,
+                name,
+                deleted_at: from_text(&deleted_at)?,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+        let entries = sqlx::query_as::<_, DeletedEntryRow>(
+            "SELECT e.id, e.kombucha_id, e.content, e.added, e.version, e.updated_at, k.name, e.deleted_at FROM kombucha_entry e JOIN kombucha k ON k.id = e.kombucha_id WHERE e.deleted_at IS NOT NULL AND k.deleted_at IS NULL ORDER BY e.deleted_at DESC, e.id DESC",
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(
+            |(
+                id,
+                kombucha_id,
+                content,
+                added,
+                version,
+                updated_at,
+                kombucha_name,
+                deleted_at,
+            )| {
+                Ok(DeletedEntry {
+                    kombucha_id,
+                    kombucha_name,
+                    entry: db_entry((
+                        id,
+                        kombucha_id,
+                        content,
+                        added,
+                        version,
+                        updated_at,
+                    ))?
+                    .into(),
+                    deleted_at: from_text(&deleted_at)?,
+                })
+            },
+        )
+        .collect::<Result<_>>()?;
+
+        let fermentations = sqlx::query_as::<_, DeletedFermentationRow>(
+            "SELECT f.id, f.kombucha_id, f.start_date, f.end_date, f.est_end_date, f.status, k.name, f.deleted_at FROM kombucha_fermentation f JOIN kombucha k ON k.id = f.kombucha_id WHERE f.deleted_at IS NOT NULL AND k.deleted_at IS NULL ORDER BY f.deleted_at DESC, f.id DESC",
+        )
+        .fetch_all(&mut connection)
+        .await?
+        .into_iter()
+        .map(
+            |(
+                id,
+                kombucha_id,
+                start_date,
+                end_date,
+                est_end_date,
+                status,
+                kombucha_name,
+                deleted_at,
+            )| {
+                Ok(DeletedFermentation {
+                    kombucha_id,
+                    kombucha_name,
+                    fermentation: db_fermentation((
+                        id,
+                        kombucha_id,
+                        start_date,
+                        end_date,
+                        est_end_date,
+                        status,
+                    ))?
+                    .into(),
+                    deleted_at: from_text(&deleted_at)?,
+                })
+            },
+        )
+        .collect::<Result<_>>()?;
+
+        Ok(Trash {
+            kombuchas,
+            entries,
+            fermentations,
+        })
+    }
+
+    async fn restore_kombucha(&self, id: KombuchaId) -> Result<()> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            // What was deleted before the kombucha stays in the trash
+            for restore in &[
+                "UPDATE kombucha_entry SET deleted_at = NULL WHERE kombucha_id = ?1 AND deleted_at = (SELECT deleted_at FROM kombucha WHERE id = ?1)",
+                "UPDATE kombucha_fermentation SET deleted_at = NULL WHERE kombucha_id = ?1 AND deleted_at = (SELECT deleted_at FROM kombucha WHERE id = ?1)",
+            ] {
+                transaction.execute(sqlx::query(restore).bind(id)).await?;
+            }
+
+            let query = sqlx::query(
+                "UPDATE kombucha SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+            )
+            .bind(id);
+
+            if transaction.execute(query).await? == 0 {
+                return Err(Error::not_found(format!("Deleted kombucha {}", id)));
+            }
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn restore_entry(
+        &self,
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_entry SET deleted_at = NULL WHERE id = ?1 AND kombucha_id = ?2 AND deleted_at IS NOT NULL AND EXISTS (SELECT 1 FROM kombucha WHERE id = ?2 AND deleted_at IS NULL)",
+        )
+        .bind(entry_id)
+        .bind(kombucha_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found(format!(
+                "Deleted entry {} of kombucha {}",
+                entry_id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn restore_fermentation(
+        &self,
+        kombucha_id: KombuchaId,
+        fermentation_id: FermentationId,
+    ) -> Result<()> {
+        let query = sqlx::query(
+            "UPDATE kombucha_fermentation SET deleted_at = NULL WHERE id = ?1 AND kombucha_id = ?2 AND deleted_at IS NOT NULL AND EXISTS (SELECT 1 FROM kombucha WHERE id = ?2 AND deleted_at IS NULL)",
+        )
+        .bind(fermentation_id)
+        .bind(kombucha_id);
+
+        let rows = self.connection().await?.execute(query).await?;
+        if rows == 0 {
+            return Err(Error::not_found(format!(
+                "Deleted fermentation {} of kombucha {}",
+                fermentation_id, kombucha_id
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn purge_deleted(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<KombuchaId>> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            let before = to_text(before);
+
+            let ids = sqlx::query_as::<_, (KombuchaId,)>(
+                "SELECT id FROM kombucha WHERE deleted_at < ? ORDER BY id",
+            )
+            .bind(&before)
+            .fetch_all(&mut transaction)
+            .await?;
+
+            // Images of purged entries are kept, `ON DELETE SET NULL` moves
+            // them to the kombucha. Everything of a purged kombucha goes with
+            // it through `ON DELETE CASCADE`
+            for purge in &[
+                "DELETE FROM kombucha_entry WHERE deleted_at < ?1",
+                "DELETE FROM kombucha_fermentation WHERE deleted_at < ?1",
+                "DELETE FROM kombucha WHERE deleted_at < ?1",
+            ] {
+                transaction
+                    .execute(sqlx::query(purge).bind(&before))
+                    .await?;
+            }
+
+            Ok(ids.into_iter().map(|(id,)| id).collect())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+}
+
+#[async_trait]
+impl Migrate for SqliteStore {
+    fn migrations(&self) -> &'static [Migration] {
+        migrate::SQLITE
+    }
+
+    async fn applied_versions(&self) -> Result<Vec<String>> {
+        let mut transaction = self.connection().await?.begin().await?;
+
+        let result = async {
+            transaction
+                .execute(
+                    "CREATE TABLE IF NOT EXISTS schema_migration (version TEXT PRIMARY KEY, applied TEXT NOT NULL)",
+                )
+                .await?;
+
+            if has_migrant_table(&mut transaction).await? {
+                let query = sqlx::query(
+                    "INSERT OR IGNORE INTO schema_migration (version, applied) SELECT tag, ? FROM __migrant_migrations",
+                )
+                .bind(to_text(Utc::now()));
+
+                transaction.execute(query).await?;
+            }
+
+            let versions = sqlx::query_as::<_, (String,)>(
+                "SELECT version FROM schema_migration ORDER BY version",
+            )
+            .fetch_all(&mut transaction)
+            .await?;
+
+            Ok(versions.into_iter().map(|(version,)| version).collect())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn apply(&self, migration: &Migration) -> Result<()> {
+        let mut transaction =
+            self.migration_connection().await?.begin().await?;
+
+        let result = async {
+            transaction.execute(migration.up).await?;
+            check_foreign_keys(&mut transaction, migration).await?;
+
+            let query = sqlx::query(
+                "INSERT INTO schema_migration (version, applied) VALUES (?, ?)",
+            )
+            .bind(migration.version)
+            .bind(to_text(Utc::now()));
+
+            transaction.execute(query).await?;
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+
+    async fn revert(&self, migration: &Migration) -> Result<()> {
+        let mut transaction =
+            self.migration_connection().await?.begin().await?;
+
+        let result = async {
+            transaction.execute(migration.down).await?;
+            check_foreign_keys(&mut transaction, migration).await?;
+            transaction
+                .execute(
+                    sqlx::query(
+                        "DELETE FROM schema_migration WHERE version = ?",
+                    )
+                    .bind(migration.version),
+                )
+                .await?;
+
+            // Keeps `migrant` from bringing the migration back
+            if has_migrant_table(&mut transaction).await? {
+                transaction
+                    .execute(
+                        sqlx::query(
+                            "DELETE FROM __migrant_migrations WHERE tag = ?",
+                        )
+                        .bind(migration.version),
+                    )
+                    .await?;
+            }
+
+            Ok(())
+        }
+        .await;
+
+        finish(transaction, result).await
+    }
+}
+
+/// Commits the transaction when its statements went through and rolls it back
+/// otherwise. A dropped transaction is rolled back only once its connection is
+/// closed in the background, the shared cache keeps the tables locked until
+/// then
+async fn finish<T>(
+    transaction: Transaction<PoolConnection<SqliteConnection>>,
+    result: Result<T>,
+) -> Result<T> {
+    match result {
+        Ok(value) => {
+            transaction.commit().await?;
+            Ok(value)
+        }
+        Err(err) => {
+            transaction.rollback().await?;
+            Err(err)
+        }
+    }
+}
+
+async fn insert_kombucha(
+    transaction: &mut Transaction<PoolConnection<SqliteConnection>>,
+    name: &str,
+) -> Result<KombuchaId> {
+    let query = sqlx::query(
+        "INSERT INTO kombucha (name, added, updated_at) VALUES (?1, ?2, ?2)",
+    )
+    .bind(name)
+    .bind(to_text(Utc::now()));
+
+    transaction.execute(query).await?;
+
+    let (id,) =
+        sqlx::query_as::<_, (KombuchaId,)>("SELECT last_insert_rowid()")
+            .fetch_one(transaction)
+            .await?;
+
+    Ok(id)
+}
+
+async fn insert_fermentation(
+    transaction: &mut Transaction<PoolConnection<SqliteConnection>>,
+    kombucha_id: KombuchaId,
+    fermentation: &NewFermentation,
+) -> Result<FermentationId> {
+    let query = sqlx::query(
+        "INSERT INTO kombucha_fermentation (kombucha_id, start_date, est_end_date, status) SELECT ?1, ?2, ?3, ?4 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+    )
+    .bind(kombucha_id)
+    .bind(to_text(fermentation.start_date.unwrap_or_else(Utc::now)))
+    .bind(fermentation.est_end_date.map(to_text))
+    .bind(fermentation.status);
+
+    if transaction.execute(query).await? == 0 {
+        return Err(Error::not_found(format!("Kombucha {}", kombucha_id)));
+    }
+
+    let (id,) =
+        sqlx::query_as::<_, (FermentationId,)>("SELECT last_insert_rowid()")
+            .fetch_one(transaction)
+            .await?;
+
+    Ok(id)
+}
+
+async fn replace_recipe(
+    transaction: &mut Transaction<PoolConnection<SqliteConnection>>,
+    kombucha_id: KombuchaId,
+    recipe: &Recipe,
+) -> Result<()> {
+    // Takes the teas, stages and flavourings of the old recipe with it
+    let query =
+        sqlx::query("DELETE FROM kombucha_recipe WHERE kombucha_id = ?")
+            .bind(kombucha_id);
+    transaction.execute(query).await?;
+
+    let query = sqlx::query(
+        "INSERT INTO kombucha_recipe (kombucha_id, sugar_type, sugar_grams, water_ml, starter_ml, vessel, vessel_ml) SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7 WHERE EXISTS (SELECT 1 FROM kombucha WHERE id = ?1 AND deleted_at IS NULL)",
+    )
+    .bind(kombucha_id)
+    .bind(&recipe.sugar_type)
+    .bind(recipe.sugar_grams)
+    .bind(recipe.water_ml)
+    .bind(recipe.starter_ml)
+    .bind(&recipe.vessel)
+    .bind(recipe.vessel_ml);
+
+    if transaction.execute(query).await? == 0 {
+        return Err(Error::not_found(format!("Kombucha {}", kombucha_id)));
+    }
+
+    for (position, tea) in recipe.teas.iter().enumerate() {
+        let query = sqlx::query(
+            "INSERT INTO kombucha_recipe_tea (kombucha_id, position, variety, grams) VALUES (?, ?, ?, ?)",
+        )
+        .bind(kombucha_id)
+        .bind(position as i32)
+        .bind(&tea.variety)
+        .bind(tea.grams);
+
+        transaction.execute(query).await?;
+    }
+
+    for (position, stage) in recipe.stages.iter().enumerate() {
+        let query = sqlx::query(
+            "INSERT INTO kombucha_recipe_stage (kombucha_id, position, status, days) VALUES (?, ?, ?, ?)",
+        )
+        .bind(kombucha_id)
+        .bind(position as i32)
+        .bind(stage.status)
+        .bind(stage.days as i32);
+
+        transaction.execute(query).await?;
+    }
+
+    for (position, flavouring) in recipe.flavourings.iter().enumerate() {
+        let query = sqlx::query(
+            "INSERT INTO kombucha_recipe_flavouring (kombucha_id, position, ingredient, amount, stage) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(kombucha_id)
+        .bind(position as i32)
+        .bind(&flavouring.ingredient)
+        .bind(&flavouring.amount)
+        .bind(flavouring.stage);
+
+        transaction.execute(query).await?;
+    }
+
+    Ok(())
+}
+
+/// Reads every row, a statement that is left mid-way keeps its tables locked
+/// and makes dropping them fail
+async fn has_migrant_table(
+    transaction: &mut Transaction<PoolConnection<SqliteConnection>>,
+) -> Result<bool> {
+    let tables = sqlx::query_as::<_, (String,)>(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = '__migrant_migrations'",
+    )
+    .fetch_all(transaction)
+    .await?;
+
+    Ok(!tables.is_empty())
+}
+
+/// Fails when the script left rows pointing at rows that don't exist
+async fn check_foreign_keys(
+    transaction: &mut Transaction<PoolConnection<SqliteConnection>>,
+    migration: &Migration,
+) -> Result<()> {
+    let violations = sqlx::query_as::<_, (String, Option<i64>, String, i64)>(
+        "PRAGMA foreign_key_check",
+    )
+    .fetch_all(transaction)
+    .await?;
+
+    match violations.first() {
+        Some((table, _, parent, _)) => Err(Error::Internal(anyhow::anyhow!(
+            "Migration {} leaves rows of {} without their {}",
+            migration.version,
+            table,
+            parent
+        ))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,145 @@
+//! The embedded migrations against an SQLite file
+
+use kombucha_tracker_server::{
+    migrate::{self, Migration},
+    SqliteStore,
+};
+use sqlx::{sqlite::SqliteQueryAs, Executor, SqlitePool};
+use std::path::{Path, PathBuf};
+
+async fn sqlite_pool(name: &str) -> SqlitePool {
+    let dir = std::env::temp_dir().join(format!(
+        "kombucha-tracker-migrations-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    SqlitePool::new(&format!("sqlite://{}", dir.join("kombucha.db").display()))
+        .await
+        .unwrap()
+}
+
+fn versions_on_disk(dir: &Path) -> Vec<String> {
+    let mut versions: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    versions.sort();
+
+    versions
+}
+
+fn versions<'a>(
+    migrations: impl IntoIterator<Item = &'a Migration>,
+) -> Vec<String> {
+    migrations
+        .into_iter()
+        .map(|migration| migration.version.to_string())
+        .collect()
+}
+
+#[test]
+fn embeds_every_migration() {
+    let db = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../db");
+
+    assert_eq!(
+        versions(migrate::POSTGRES),
+        versions_on_disk(&db.join("migrations"))
+    );
+    assert_eq!(
+        versions(migrate::SQLITE),
+        versions_on_disk(&db.join("sqlite/migrations"))
+    );
+}
+
+#[tokio::test]
+async fn applies_and_reverts_everything() {
+    let store = SqliteStore::new(sqlite_pool("roundtrip").await);
+
+    let pending = migrate::up(&store, true).await.unwrap();
+    assert_eq!(versions(pending), versions(migrate::SQLITE));
+    assert!(migrate::status(&store).await.unwrap().applied.is_empty());
+
+    migrate::up(&store, false).await.unwrap();
+    let status = migrate::status(&store).await.unwrap();
+    assert!(status.pending.is_empty());
+    assert_eq!(status.applied.len(), migrate::SQLITE.len());
+
+    for migration in migrate::SQLITE.iter().rev() {
+        let reverted = migrate::down(&store, false).await.unwrap().unwrap();
+        assert_eq!(reverted.version, migration.version);
+    }
+    assert!(migrate::down(&store, false).await.unwrap().is_none());
+
+    // Everything reverted cleanly, so it all applies again
+    migrate::up(&store, false).await.unwrap();
+    assert!(migrate::status(&store).await.unwrap().pending.is_empty());
+}
+
+#[tokio::test]
+async fn refuses_schema_ahead_of_binary() {
+    let pool = sqlite_pool("ahead").await;
+    let store = SqliteStore::new(pool.clone());
+
+    migrate::up(&store, false).await.unwrap();
+    pool.acquire()
+        .await
+        .unwrap()
+        .execute(
+            "INSERT INTO schema_migration (version, applied) VALUES ('29990101000000_future', '2999-01-01T00:00:00.000000Z')",
+        )
+        .await
+        .unwrap();
+
+    let status = migrate::status(&store).await.unwrap();
+    assert_eq!(status.unknown, vec!["29990101000000_future".to_string()]);
+    assert!(migrate::up(&store, false).await.is_err());
+    assert!(migrate::down(&store, false).await.is_err());
+}
+
+#[tokio::test]
+async fn deleting_kombucha_cascades_to_everything_attached() {
+    let pool = sqlite_pool("cascade").await;
+    migrate::up(&SqliteStore::new(pool.clone()), false)
+        .await
+        .unwrap();
+
+    let mut connection = pool.acquire().await.unwrap();
+    connection
+        .execute(
+            "PRAGMA foreign_keys = ON;
+            INSERT INTO kombucha (id, name, added) VALUES (1, 'Cascade', '2020-04-17T00:00:00.000000Z');
+            INSERT INTO kombucha_entry (id, kombucha_id, content, added) VALUES (1, 1, 'Entry', '2020-04-17T00:00:00.000000Z');
+            INSERT INTO kombucha_fermentation (kombucha_id, start_date, status) VALUES (1, '2020-04-17T00:00:00.000000Z', 'primary');
+            INSERT INTO kombucha_image (kombucha_id, entry_id, file_name, content_type, added) VALUES (1, 1, 'image.png', 'image/png', '2020-04-17T00:00:00.000000Z');
+            INSERT INTO kombucha_measurement (kombucha_id, fermentation_id, kind, value, unit, taken_at) VALUES (1, 1, 'ph', 3.2, 'pH', '2020-04-17T00:00:00.000000Z');
+            INSERT INTO kombucha_recipe (kombucha_id, sugar_type, sugar_grams, water_ml, starter_ml, vessel) VALUES (1, 'Cane', 70, 1000, 100, 'Jar');
+            INSERT INTO kombucha_recipe_tea (kombucha_id, position, variety, grams) VALUES (1, 0, 'Black', 5);
+            INSERT INTO kombucha_recipe_stage (kombucha_id, position, status, days) VALUES (1, 0, 'primary', 10);
+            INSERT INTO kombucha_recipe_flavouring (kombucha_id, position, ingredient, amount, stage) VALUES (1, 0, 'Ginger', '2 slices', 'secondary');
+            DELETE FROM kombucha WHERE id = 1",
+        )
+        .await
+        .unwrap();
+
+    for table in &[
+        "kombucha_entry",
+        "kombucha_fermentation",
+        "kombucha_image",
+        "kombucha_measurement",
+        "kombucha_recipe",
+        "kombucha_recipe_tea",
+        "kombucha_recipe_stage",
+        "kombucha_recipe_flavouring",
+    ] {
+        let (count,) = sqlx::query_as::<_, (i64,)>(&format!(
+            "SELECT COUNT(*) FROM {}",
+            table
+        ))
+        .fetch_one(&mut connection)
+        .await
+        .unwrap();
+        assert_eq!(count, 0, "{} wasn't emptied", table);
+    }
+}
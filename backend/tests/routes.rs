@@ -0,0 +1,2237 @@
+//! Exercises the whole API through `routes()`, every test runs against both
+//! the in-memory and the SQLite store
+
+use bytes::Bytes;
+use data_types::{
+    ApiError, Entry, EntryId, ErrorCode, Fermentation, FermentationId,
+    FermentationStatus, Image, ImageId, Kombucha, KombuchaId, KombuchaPage,
+    Measurement, MeasurementId, MeasurementKind, MeasurementSeries,
+    NewKombucha, NewRecipeTemplate, PlannedStage, Recipe, RecipeFlavouring,
+    RecipeTea, RecipeTemplate, RecipeWarning, SearchHit, SnippetPart,
+    TemplateId, Trash,
+};
+use kombucha_tracker_server::{migrate, routes, App, MemoryStore, SqliteStore};
+use serde::de::DeserializeOwned;
+use sqlx::SqlitePool;
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use warp::{
+    http::{header, Response, StatusCode},
+    test::request,
+    Filter, Rejection, Reply,
+};
+
+fn temp_dir() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    std::env::temp_dir().join(format!(
+        "kombucha-tracker-test-{}-{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
+fn api(
+    app: App,
+    dir: PathBuf,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone + 'static {
+    routes(Arc::new(app), dir.to_string_lossy().into_owned())
+}
+
+fn memory_app(dir: &Path) -> App {
+    App::new(MemoryStore::new(), dir.join("images"))
+}
+
+async fn memory_api(
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone + 'static {
+    let dir = temp_dir();
+
+    api(memory_app(&dir), dir)
+}
+
+async fn sqlite_app(dir: &Path) -> App {
+    std::fs::create_dir_all(dir).unwrap();
+
+    let pool = SqlitePool::new(&format!(
+        "sqlite://{}",
+        dir.join("kombucha.db").display()
+    ))
+    .await
+    .unwrap();
+
+    let store = SqliteStore::new(pool);
+    migrate::up(&store, false).await.unwrap();
+
+    App::new(store, dir.join("images"))
+}
+
+async fn sqlite_api(
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone + 'static {
+    let dir = temp_dir();
+
+    api(sqlite_app(&dir).await, dir)
+}
+
+/// Runs every listed test once per store
+macro_rules! store_tests {
+    ($($test:ident),* $(,)?) => {
+        mod memory {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(super::memory_api().await).await
+                }
+            )*
+        }
+
+        mod sqlite {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(super::sqlite_api().await).await
+                }
+            )*
+        }
+    };
+}
+
+store_tests!(
+    creates_and_gets_kombucha,
+    missing_kombucha_is_not_found,
+    adding_to_missing_kombucha_is_not_found,
+    invalid_body_is_bad_request,
+    rejects_oversized_bodies,
+    updates_and_deletes_entries,
+    advances_fermentations,
+    adds_fermentations_in_order,
+    logs_measurements,
+    downsamples_measurement_series,
+    saves_recipes,
+    brews_again_from_templates_and_earlier_batches,
+    deletes_kombucha_with_everything_attached,
+    uploads_images,
+    links_images_to_entries,
+    detects_image_types,
+    lists_kombucha_summaries,
+    searches_names_and_entries,
+    restores_deleted_kombuchas_and_entries,
+    adds_nothing_to_trashed_kombuchas,
+    rejects_stale_updates,
+    failed_writes_leave_the_tables_unlocked,
+    patches_only_the_given_fields,
+    keeps_entries_after_their_kombucha,
+);
+
+// Purging isn't exposed through the API, so these drive `App` directly
+
+#[tokio::test]
+async fn purges_expired_trash_in_memory() {
+    let dir = temp_dir();
+    purges_expired_trash(memory_app(&dir), dir).await
+}
+
+#[tokio::test]
+async fn purges_expired_trash_in_sqlite() {
+    let dir = temp_dir();
+    purges_expired_trash(sqlite_app(&dir).await, dir).await
+}
+
+#[tokio::test]
+async fn caps_measurements_of_kombuchas_in_memory() {
+    let dir = temp_dir();
+    caps_measurements_of_kombuchas(memory_app(&dir), dir).await
+}
+
+#[tokio::test]
+async fn caps_measurements_of_kombuchas_in_sqlite() {
+    let dir = temp_dir();
+    caps_measurements_of_kombuchas(sqlite_app(&dir).await, dir).await
+}
+
+/// A kombucha whose recipe can't be stored isn't created either
+#[tokio::test]
+async fn creates_nothing_when_the_recipe_fails_in_sqlite() {
+    let dir = temp_dir();
+    let api = api(sqlite_app(&dir).await, dir.clone());
+
+    let response = request()
+        .method("POST")
+        .path("/api/1/template")
+        .json(&NewRecipeTemplate {
+            name: "House blend".to_string(),
+            recipe: Recipe {
+                teas: vec![RecipeTea {
+                    variety: "Assam".to_string(),
+                    grams: 10.0,
+                }],
+                sugar_type: "Cane".to_string(),
+                sugar_grams: 140.0,
+                water_ml: 2000.0,
+                starter_ml: 300.0,
+                vessel: "Glass jar".to_string(),
+                vessel_ml: None,
+                stages: vec![PlannedStage {
+                    status: FermentationStatus::Primary,
+                    days: 10,
+                }],
+                flavourings: vec![],
+            },
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let template_id: TemplateId = json(response.body());
+
+    let pool = SqlitePool::new(&format!(
+        "sqlite://{}",
+        dir.join("kombucha.db").display()
+    ))
+    .await
+    .unwrap();
+    sqlx::query(
+        "CREATE TRIGGER fail_recipe BEFORE INSERT ON kombucha_recipe BEGIN SELECT RAISE(ABORT, 'no recipes'); END",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let response = request()
+        .method("POST")
+        .path("/api/1/kombucha")
+        .json(&NewKombucha {
+            template_id: Some(template_id),
+            ..NewKombucha::default()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let response = request().path("/api/1/kombucha").reply(&api).await;
+    let page: KombuchaPage = json(response.body());
+    assert_eq!(page.kombuchas, vec![]);
+
+    // The rolled back transaction doesn't keep the tables locked
+    let id = create_kombucha(&api).await;
+    let response = request().path("/api/1/kombucha").reply(&api).await;
+    let page: KombuchaPage = json(response.body());
+    let ids: Vec<_> = page.kombuchas.iter().map(|summary| summary.id).collect();
+    assert_eq!(ids, vec![id]);
+}
+
+fn json<T: DeserializeOwned>(body: &[u8]) -> T {
+    serde_json::from_slice(body).expect("Invalid JSON body")
+}
+
+/// The ETag handed out for `version`, as sent back in `If-Match`
+fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+async fn create_kombucha(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+) -> KombuchaId {
+    let response = request()
+        .method("POST")
+        .path("/api/1/kombucha")
+        .reply(api)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    json(response.body())
+}
+
+async fn creates_and_gets_kombucha(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}", id))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let kombucha: Kombucha = json(response.body());
+    assert_eq!(kombucha.id, id);
+
+    let response = request().path("/api/1/kombucha").reply(&api).await;
+    let page: KombuchaPage = json(response.body());
+    assert_eq!(page.kombuchas, vec![kombucha.summary()]);
+    assert_eq!(page.next_cursor, None);
+}
+
+async fn rename_kombucha(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+    id: KombuchaId,
+    name: &str,
+) -> Kombucha {
+    let path = format!("/api/1/kombucha/{}", id);
+    let kombucha: Kombucha =
+        json(request().path(&path).reply(api).await.body());
+
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&Kombucha {
+            name: name.to_string(),
+            ..kombucha
+        })
+        .reply(api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    json(request().path(&path).reply(api).await.body())
+}
+
+async fn write_entry(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+    id: KombuchaId,
+    entry_id: Option<EntryId>,
+    content: &str,
+) -> EntryId {
+    let entry_id = match entry_id {
+        Some(entry_id) => entry_id,
+        None => json(
+            request()
+                .method("POST")
+                .path(&format!("/api/1/kombucha/{}/entry", id))
+                .reply(api)
+                .await
+                .body(),
+        ),
+    };
+
+    let path = format!("/api/1/kombucha/{}/entry/{}", id, entry_id);
+    let entry: Entry = json(request().path(&path).reply(api).await.body());
+    let response = request()
+        .method("PUT")
+        .path(&path)
+        .header(header::IF_MATCH, etag(entry.version))
+        .json(&Entry {
+            content: content.to_string(),
+            ..entry
+        })
+        .reply(api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    entry_id
+}
+
+async fn list(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+    query: &str,
+) -> KombuchaPage {
+    let response = request()
+        .path(&format!("/api/1/kombucha?{}", query))
+        .reply(api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    json(response.body())
+}
+
+fn names(page: &KombuchaPage) -> Vec<&str> {
+    page.kombuchas
+        .iter()
+        .map(|kombucha| kombucha.name.as_str())
+        .collect()
+}
+
+async fn missing_kombucha_is_not_found(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let response = request().path("/api/1/kombucha/42").reply(&api).await;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let error: ApiError = json(response.body());
+    assert_eq!(error.code, ErrorCode::NotFound);
+}
+
+/// Rows of a missing kombucha are refused by the insert itself rather than
+/// checked for up front
+async fn adding_to_missing_kombucha_is_not_found(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let bodies = [
+        ("entry", serde_json::json!(null)),
+        ("fermentation", serde_json::json!({ "status": "Primary" })),
+        (
+            "measurement",
+            serde_json::json!({ "kind": "ph", "value": 3.2 }),
+        ),
+    ];
+
+    for (path, body) in bodies.iter() {
+        let mut request = request()
+            .method("POST")
+            .path(&format!("/api/1/kombucha/42/{}", path));
+        if !body.is_null() {
+            request = request.json(body);
+        }
+        let response = request.reply(&api).await;
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+        let error: ApiError = json(response.body());
+        assert_eq!(error.code, ErrorCode::NotFound);
+        assert_eq!(error.details, None);
+    }
+}
+
+async fn invalid_body_is_bad_request(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/fermentation", id))
+        .header("content-type", "application/json")
+        .body("{\"status\": \"brewing\"}")
+        .reply(&api)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let error: ApiError = json(response.body());
+    assert_eq!(error.code, ErrorCode::BadRequest);
+}
+
+async fn rejects_oversized_bodies(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let name = "x".repeat(2 * 1024 * 1024);
+
+    for path in &["/api/1/kombucha", "/api/1/template"] {
+        let response = request()
+            .method("POST")
+            .path(path)
+            .json(&serde_json::json!({ "name": name }))
+            .reply(&api)
+            .await;
+
+        assert_eq!(
+            response.status(),
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "{}",
+            path
+        );
+        let error: ApiError = json(response.body());
+        assert_eq!(error.code, ErrorCode::PayloadTooLarge);
+    }
+
+    // Not a body at all
+    let response = request()
+        .method("POST")
+        .path("/api/1/kombucha")
+        .header("transfer-encoding", "chunked")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::LENGTH_REQUIRED);
+}
+
+async fn updates_and_deletes_entries(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/entry", id))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let entry_id: EntryId = json(response.body());
+
+    let entry_path = format!("/api/1/kombucha/{}/entry/{}", id, entry_id);
+    let response = request().path(&entry_path).reply(&api).await;
+    let entry: Entry = json(response.body());
+
+    let response = request()
+        .method("PUT")
+        .path(&entry_path)
+        .header(header::IF_MATCH, etag(entry.version))
+        .json(&Entry {
+            content: "Added 2 tbsp of sugar".to_string(),
+            ..entry.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&entry_path).reply(&api).await;
+    let updated: Entry = json(response.body());
+    assert_eq!(updated.content, "Added 2 tbsp of sugar");
+
+    let response = request()
+        .method("PUT")
+        .path(&entry_path)
+        .header(header::IF_MATCH, etag(updated.version))
+        .json(&Entry {
+            added: entry.added - chrono::Duration::days(1),
+            ..updated
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = request()
+        .method("DELETE")
+        .path(&entry_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&entry_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = request()
+        .method("DELETE")
+        .path(&entry_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn advances_fermentations(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let advance_path = format!("/api/1/kombucha/{}/fermentation/advance", id);
+
+    let response = request()
+        .method("POST")
+        .path(&advance_path)
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let primary: Fermentation = json(response.body());
+    assert_eq!(primary.status, FermentationStatus::Primary);
+    assert!(primary.is_active());
+
+    let response = request()
+        .method("POST")
+        .path(&advance_path)
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+    let secondary: Fermentation = json(response.body());
+    assert_eq!(secondary.status, FermentationStatus::Secondary);
+
+    let response = request()
+        .method("POST")
+        .path(&advance_path)
+        .json(&serde_json::json!({ "status": "Primary" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Editing a fermentation can't move it back either
+    let secondary_path =
+        format!("/api/1/kombucha/{}/fermentation/{}", id, secondary.id);
+    let response = request()
+        .method("PUT")
+        .path(&secondary_path)
+        .json(&Fermentation {
+            status: FermentationStatus::Primary,
+            ..secondary.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: ApiError = json(response.body());
+    assert_eq!(error.code, ErrorCode::Validation);
+
+    let est_end_date = secondary.start_date + chrono::Duration::days(5);
+    let response = request()
+        .method("PUT")
+        .path(&secondary_path)
+        .json(&Fermentation {
+            est_end_date: Some(est_end_date),
+            ..secondary.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = request().path(&secondary_path).reply(&api).await;
+    let saved: Fermentation = json(response.body());
+    assert_eq!(saved.status, FermentationStatus::Secondary);
+
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}/fermentation", id))
+        .reply(&api)
+        .await;
+    let fermentations: Vec<Fermentation> = json(response.body());
+    assert_eq!(fermentations.len(), 2);
+    assert!(!fermentations[0].is_active());
+    assert!(fermentations[1].is_active());
+}
+
+async fn adds_fermentations_in_order(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let fermentations_path = format!("/api/1/kombucha/{}/fermentation", id);
+    let add = |status: &'static str| {
+        request()
+            .method("POST")
+            .path(&fermentations_path)
+            .json(&serde_json::json!({ "status": status }))
+            .reply(&api)
+    };
+
+    // A kombucha starts in primary
+    let response = add("Bottled").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = add("Primary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let primary_id: FermentationId = json(response.body());
+
+    // Not next to a stage that is still going
+    let response = add("Secondary").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: ApiError = json(response.body());
+    assert_eq!(error.code, ErrorCode::Validation);
+
+    let response = request()
+        .path(&format!("{}/{}", fermentations_path, primary_id))
+        .reply(&api)
+        .await;
+    let primary: Fermentation = json(response.body());
+    let response = request()
+        .method("PUT")
+        .path(&format!("{}/{}", fermentations_path, primary_id))
+        .json(&Fermentation {
+            end_date: Some(chrono::Utc::now()),
+            ..primary
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Once it's over only the stages it moves on to can follow
+    let response = add("Primary").await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = add("Secondary").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&fermentations_path).reply(&api).await;
+    let fermentations: Vec<Fermentation> = json(response.body());
+    let statuses: Vec<_> = fermentations.iter().map(|f| f.status).collect();
+    assert_eq!(
+        statuses,
+        vec![FermentationStatus::Primary, FermentationStatus::Secondary]
+    );
+}
+
+async fn logs_measurements(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let measurements_path = format!("/api/1/kombucha/{}/measurement", id);
+
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/fermentation/advance", id))
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+    let primary: Fermentation = json(response.body());
+
+    let response = request()
+        .method("POST")
+        .path(&measurements_path)
+        .json(&serde_json::json!({
+            "kind": "temperature",
+            "value": 75.2,
+            "unit": "°F",
+            "taken_at": "2020-04-18T08:00:00Z",
+        }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let temperature_id: MeasurementId = json(response.body());
+
+    let response = request()
+        .method("POST")
+        .path(&measurements_path)
+        .json(&serde_json::json!({
+            "kind": "specific_gravity",
+            "value": 1.012,
+            "taken_at": "2020-04-17T08:00:00Z",
+            "fermentation_id": primary.id,
+        }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let gravity_id: MeasurementId = json(response.body());
+
+    // Oldest first, the unit defaults to the one of the kind
+    let kombucha: Kombucha = json(
+        request()
+            .path(&format!("/api/1/kombucha/{}", id))
+            .reply(&api)
+            .await
+            .body(),
+    );
+    let ids: Vec<_> = kombucha.measurements.iter().map(|m| m.id).collect();
+    assert_eq!(ids, vec![gravity_id, temperature_id]);
+    assert_eq!(
+        kombucha.measurements[0].kind,
+        MeasurementKind::SpecificGravity
+    );
+    assert_eq!(kombucha.measurements[0].unit, "SG");
+    assert_eq!(kombucha.measurements[0].fermentation_id, Some(primary.id));
+
+    for invalid in &[
+        serde_json::json!({ "kind": "ph", "value": 15.0 }),
+        serde_json::json!({ "kind": "ph", "value": 3.0, "unit": "°C" }),
+        serde_json::json!({ "kind": "ph", "value": 3.0, "fermentation_id": 9999 }),
+    ] {
+        let response = request()
+            .method("POST")
+            .path(&measurements_path)
+            .json(invalid)
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let response = request()
+        .method("POST")
+        .path("/api/1/kombucha/9999/measurement")
+        .json(&serde_json::json!({ "kind": "ph", "value": 3.0 }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let temperature_path = format!("{}/{}", measurements_path, temperature_id);
+    let temperature: Measurement =
+        json(request().path(&temperature_path).reply(&api).await.body());
+    let response = request()
+        .method("PUT")
+        .path(&temperature_path)
+        .json(&Measurement {
+            value: 24.0,
+            unit: "°C".to_string(),
+            ..temperature
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let temperature: Measurement =
+        json(request().path(&temperature_path).reply(&api).await.body());
+    assert_eq!((temperature.value, temperature.unit.as_str()), (24.0, "°C"));
+
+    let response = request()
+        .method("DELETE")
+        .path(&temperature_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = request().path(&temperature_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let measurements: Vec<Measurement> =
+        json(request().path(&measurements_path).reply(&api).await.body());
+    assert_eq!(measurements.len(), 1);
+}
+
+async fn downsamples_measurement_series(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let measurements_path = format!("/api/1/kombucha/{}/measurement", id);
+    let start: chrono::DateTime<chrono::Utc> =
+        "2020-04-17T08:00:00Z".parse().unwrap();
+
+    // A slow drop in pH with a single spike that has to survive
+    for i in 0..50 {
+        let value = if i == 23 {
+            4.5
+        } else {
+            3.5 - f64::from(i) * 0.01
+        };
+        let response = request()
+            .method("POST")
+            .path(&measurements_path)
+            .json(&serde_json::json!({
+                "kind": "ph",
+                "value": value,
+                "taken_at": start + chrono::Duration::hours(i.into()),
+            }))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    request()
+        .method("POST")
+        .path(&measurements_path)
+        .json(&serde_json::json!({
+            "kind": "temperature",
+            "value": 77.0,
+            "unit": "°F",
+        }))
+        .reply(&api)
+        .await;
+
+    let response = request()
+        .path(&format!("{}/series?points=10", measurements_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let series: Vec<MeasurementSeries> = json(response.body());
+    let kinds: Vec<_> = series.iter().map(|series| series.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![MeasurementKind::Ph, MeasurementKind::Temperature]
+    );
+
+    let ph = &series[0];
+    assert_eq!((ph.total, ph.points.len()), (50, 10));
+    assert_eq!(ph.points[0].taken_at, start);
+    assert_eq!(ph.points[9].taken_at, start + chrono::Duration::hours(49));
+    assert!(ph.points.iter().any(|point| point.value == 4.5));
+
+    // Temperatures are charted in °C whatever they were logged in
+    assert_eq!(series[1].unit, "°C");
+    assert_eq!(series[1].points[0].value, 25.0);
+
+    let response = request()
+        .path(&format!("{}/series", measurements_path))
+        .reply(&api)
+        .await;
+    let series: Vec<MeasurementSeries> = json(response.body());
+    assert_eq!(series[0].points.len(), 50);
+
+    let response = request()
+        .path(&format!("{}/series?points=2", measurements_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = request()
+        .path("/api/1/kombucha/9999/measurement/series")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn saves_recipes(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let recipe_path = format!("/api/1/kombucha/{}/recipe", id);
+
+    let response = request().path(&recipe_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let recipe = Recipe {
+        teas: vec![
+            RecipeTea {
+                variety: "Assam".to_string(),
+                grams: 6.0,
+            },
+            RecipeTea {
+                variety: "Sencha".to_string(),
+                grams: 4.0,
+            },
+        ],
+        sugar_type: "Cane".to_string(),
+        sugar_grams: 140.0,
+        water_ml: 2000.0,
+        starter_ml: 300.0,
+        vessel: "Glass jar".to_string(),
+        vessel_ml: Some(3000.0),
+        stages: vec![],
+        flavourings: vec![
+            RecipeFlavouring {
+                ingredient: "Ginger".to_string(),
+                amount: "3 slices".to_string(),
+                stage: Some(FermentationStatus::Secondary),
+            },
+            RecipeFlavouring {
+                ingredient: "Lemon".to_string(),
+                amount: "1 tbsp juice".to_string(),
+                stage: None,
+            },
+        ],
+    };
+    let response = request()
+        .method("PUT")
+        .path(&recipe_path)
+        .json(&recipe)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let warnings: Vec<RecipeWarning> = json(response.body());
+    assert_eq!(warnings, vec![]);
+
+    let saved: Recipe =
+        json(request().path(&recipe_path).reply(&api).await.body());
+    assert_eq!(saved, recipe);
+
+    // Replacing keeps only the new teas and flavourings, a thin starter is
+    // saved with a warning
+    let recipe = Recipe {
+        teas: vec![RecipeTea {
+            variety: "Darjeeling".to_string(),
+            grams: 10.0,
+        }],
+        starter_ml: 100.0,
+        flavourings: vec![RecipeFlavouring {
+            ingredient: "Raspberries".to_string(),
+            amount: "50 g".to_string(),
+            stage: Some(FermentationStatus::Secondary),
+        }],
+        ..recipe
+    };
+    let response = request()
+        .method("PUT")
+        .path(&recipe_path)
+        .json(&recipe)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let warnings: Vec<RecipeWarning> = json(response.body());
+    assert!(matches!(warnings[..], [RecipeWarning::LowStarter(_)]));
+
+    let kombucha: Kombucha = json(
+        request()
+            .path(&format!("/api/1/kombucha/{}", id))
+            .reply(&api)
+            .await
+            .body(),
+    );
+    assert_eq!(kombucha.recipe.as_ref(), Some(&recipe));
+
+    for invalid in &[
+        Recipe {
+            water_ml: 0.0,
+            ..recipe.clone()
+        },
+        Recipe {
+            sugar_grams: -10.0,
+            ..recipe.clone()
+        },
+        Recipe {
+            teas: vec![RecipeTea {
+                variety: " ".to_string(),
+                grams: 5.0,
+            }],
+            ..recipe.clone()
+        },
+        Recipe {
+            flavourings: vec![RecipeFlavouring {
+                ingredient: "".to_string(),
+                amount: "1 tsp".to_string(),
+                stage: None,
+            }],
+            ..recipe.clone()
+        },
+    ] {
+        let response = request()
+            .method("PUT")
+            .path(&recipe_path)
+            .json(invalid)
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha/9999/recipe")
+        .json(&recipe)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = request()
+        .method("DELETE")
+        .path(&recipe_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = request().path(&recipe_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = request()
+        .method("DELETE")
+        .path(&recipe_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn brew(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+    new_kombucha: &NewKombucha,
+) -> Kombucha {
+    let response = request()
+        .method("POST")
+        .path("/api/1/kombucha")
+        .json(new_kombucha)
+        .reply(api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let id: KombuchaId = json(response.body());
+
+    json(
+        request()
+            .path(&format!("/api/1/kombucha/{}", id))
+            .reply(api)
+            .await
+            .body(),
+    )
+}
+
+async fn brews_again_from_templates_and_earlier_batches(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let recipe = Recipe {
+        teas: vec![RecipeTea {
+            variety: "Assam".to_string(),
+            grams: 10.0,
+        }],
+        sugar_type: "Cane".to_string(),
+        sugar_grams: 140.0,
+        water_ml: 2000.0,
+        starter_ml: 300.0,
+        vessel: "Glass jar".to_string(),
+        vessel_ml: None,
+        stages: vec![
+            PlannedStage {
+                status: FermentationStatus::Primary,
+                days: 10,
+            },
+            PlannedStage {
+                status: FermentationStatus::Secondary,
+                days: 3,
+            },
+        ],
+        flavourings: vec![RecipeFlavouring {
+            ingredient: "Ginger".to_string(),
+            amount: "3 slices".to_string(),
+            stage: Some(FermentationStatus::Secondary),
+        }],
+    };
+    let template = NewRecipeTemplate {
+        name: "House blend".to_string(),
+        recipe: recipe.clone(),
+    };
+
+    let response = request()
+        .method("POST")
+        .path("/api/1/template")
+        .json(&NewRecipeTemplate {
+            name: " ".to_string(),
+            ..template.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = request()
+        .method("POST")
+        .path("/api/1/template")
+        .json(&template)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let template_id: TemplateId = json(response.body());
+    let template_path = format!("/api/1/template/{}", template_id);
+
+    let saved: RecipeTemplate =
+        json(request().path(&template_path).reply(&api).await.body());
+    assert_eq!(saved.name, template.name);
+    assert_eq!(saved.recipe, recipe);
+    let templates: Vec<RecipeTemplate> =
+        json(request().path("/api/1/template").reply(&api).await.body());
+    assert_eq!(templates, vec![saved]);
+
+    // A batch from the template is named after it, gets the whole recipe
+    // flavourings included and starts its primary fermentation for as long as
+    // planned
+    let from_template = brew(
+        &api,
+        &NewKombucha {
+            template_id: Some(template_id),
+            ..NewKombucha::default()
+        },
+    )
+    .await;
+    assert_eq!(from_template.name, "House blend");
+    assert_eq!(from_template.recipe.as_ref(), Some(&recipe));
+    let primary = &from_template.fermentations[..];
+    assert!(matches!(primary, [fermentation] if fermentation.is_active()));
+    assert_eq!(
+        primary[0].est_end_date,
+        Some(primary[0].start_date + chrono::Duration::days(10))
+    );
+
+    // The next stage lasts as long as planned too
+    let response = request()
+        .method("POST")
+        .path(&format!(
+            "/api/1/kombucha/{}/fermentation/advance",
+            from_template.id
+        ))
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+    let secondary: Fermentation = json(response.body());
+    assert_eq!(secondary.status, FermentationStatus::Secondary);
+    assert_eq!(
+        secondary.est_end_date,
+        Some(secondary.start_date + chrono::Duration::days(3))
+    );
+
+    // An earlier batch without planned stages passes on how long its
+    // fermentations were estimated to take
+    let earlier =
+        rename_kombucha(&api, create_kombucha(&api).await, "Earlier").await;
+    let response = request()
+        .method("PUT")
+        .path(&format!("/api/1/kombucha/{}/recipe", earlier.id))
+        .json(&Recipe {
+            stages: vec![],
+            ..recipe.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let start = chrono::Utc::now() - chrono::Duration::days(30);
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/fermentation", earlier.id))
+        .json(&serde_json::json!({
+            "status": "Primary",
+            "start_date": start,
+            "est_end_date": start + chrono::Duration::days(7),
+        }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let again = brew(
+        &api,
+        &NewKombucha {
+            name: Some("Again".to_string()),
+            clone_from: Some(earlier.id),
+            ..NewKombucha::default()
+        },
+    )
+    .await;
+    assert_eq!(again.name, "Again");
+    assert_eq!(
+        again.recipe,
+        Some(Recipe {
+            stages: vec![PlannedStage {
+                status: FermentationStatus::Primary,
+                days: 7,
+            }],
+            ..recipe.clone()
+        })
+    );
+    assert_eq!(again.fermentations.len(), 1);
+
+    // Brewing again from a batch without a recipe only copies the name
+    let plain =
+        rename_kombucha(&api, create_kombucha(&api).await, "Plain").await;
+    let plain = brew(
+        &api,
+        &NewKombucha {
+            clone_from: Some(plain.id),
+            ..NewKombucha::default()
+        },
+    )
+    .await;
+    assert_eq!(plain.name, "Plain");
+    assert_eq!(plain.recipe, None);
+    assert_eq!(plain.fermentations, vec![]);
+
+    for invalid in &[
+        NewKombucha {
+            template_id: Some(template_id),
+            clone_from: Some(earlier.id),
+            ..NewKombucha::default()
+        },
+        NewKombucha {
+            clone_from: Some(KombuchaId::from(9999)),
+            ..NewKombucha::default()
+        },
+    ] {
+        let response = request()
+            .method("POST")
+            .path("/api/1/kombucha")
+            .json(invalid)
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let response = request()
+        .method("POST")
+        .path("/api/1/kombucha")
+        .body("{\"clone\": 1}")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = request()
+        .method("DELETE")
+        .path(&template_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = request().path(&template_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = request()
+        .method("DELETE")
+        .path(&template_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The batch keeps its copy of the recipe
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}/recipe", from_template.id))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+async fn deletes_kombucha_with_everything_attached(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let kombucha_path = format!("/api/1/kombucha/{}", id);
+
+    request()
+        .method("POST")
+        .path(&format!("{}/entry", kombucha_path))
+        .reply(&api)
+        .await;
+    request()
+        .method("POST")
+        .path(&format!("{}/fermentation/advance", kombucha_path))
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+
+    let response = request()
+        .method("DELETE")
+        .path(&kombucha_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&kombucha_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Posts `data` as the `file` part, uploaded as `content_type`
+async fn post_image(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+    id: KombuchaId,
+    entry_id: Option<&str>,
+    content_type: &str,
+    data: &[u8],
+) -> Response<Bytes> {
+    let boundary = "kombucha-boundary";
+    let mut body = Vec::new();
+    if let Some(entry_id) = entry_id {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"entry_id\"\r\n\r\n{}\r\n",
+                boundary, entry_id
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"scoby\"\r\nContent-Type: {}\r\n\r\n",
+            boundary, content_type
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(data);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/image", id))
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(body)
+        .reply(api)
+        .await
+}
+
+fn png() -> Vec<u8> {
+    let mut png = Vec::new();
+    image::DynamicImage::new_rgb8(4, 4)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+
+    png
+}
+
+/// Uploads a small PNG, returns its id and contents
+async fn upload_image(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+    id: KombuchaId,
+) -> (ImageId, Vec<u8>) {
+    let png = png();
+    let response = post_image(api, id, None, "image/png", &png).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    (json(response.body()), png)
+}
+
+async fn links_images_to_entries(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let entry_id = write_entry(&api, id, None, "Fresh scoby").await;
+
+    for invalid in &["abc", "12x"] {
+        let response =
+            post_image(&api, id, Some(invalid), "image/png", &png()).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            invalid
+        );
+        let error: ApiError = json(response.body());
+        assert_eq!(error.code, ErrorCode::Validation);
+    }
+
+    let response = post_image(
+        &api,
+        id,
+        Some(&format!(" {} ", entry_id)),
+        "image/png",
+        &png(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let linked: ImageId = json(response.body());
+
+    // An empty part is the same as none
+    let response = post_image(&api, id, Some(" "), "image/png", &png()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let unlinked: ImageId = json(response.body());
+
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}/image", id))
+        .reply(&api)
+        .await;
+    let images: Vec<Image> = json(response.body());
+    let links: Vec<_> = images
+        .iter()
+        .map(|image| (image.id, image.entry_id))
+        .collect();
+    assert_eq!(links, vec![(linked, Some(entry_id)), (unlinked, None)]);
+}
+
+async fn uploads_images(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let (image_id, png) = upload_image(&api, id).await;
+
+    let image_path = format!("/api/1/kombucha/{}/image/{}", id, image_id);
+    let response = request().path(&image_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.body().as_ref(), png.as_slice());
+
+    let response = request()
+        .path(&format!("{}/thumbnail", image_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/jpeg");
+
+    let response = request()
+        .method("DELETE")
+        .path(&image_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&image_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn detects_image_types(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+
+    // Served as what it is rather than as what it was uploaded as
+    let response = post_image(&api, id, None, "image/jpeg", &png()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let image_id: ImageId = json(response.body());
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}/image/{}", id, image_id))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+
+    let bmp = b"BM\x1e\0\0\0\0\0\0\0\x1a\0\0\0";
+    for (content_type, data) in &[
+        ("image/png", &b"not an image"[..]),
+        ("image/bmp", &bmp[..]),
+        ("image/webp", &b"RIFF\x1a\0\0\0WEBPVP8 "[..]),
+    ] {
+        let response = post_image(&api, id, None, content_type, data).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "{}",
+            content_type
+        );
+    }
+
+    let images: Vec<Image> = json(
+        request()
+            .path(&format!("/api/1/kombucha/{}/image", id))
+            .reply(&api)
+            .await
+            .body(),
+    );
+    assert_eq!(images.len(), 1);
+}
+
+async fn lists_kombucha_summaries(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let ginger =
+        rename_kombucha(&api, create_kombucha(&api).await, "Ginger").await;
+    let apple =
+        rename_kombucha(&api, create_kombucha(&api).await, "apple").await;
+    let blueberry =
+        rename_kombucha(&api, create_kombucha(&api).await, "Blueberry").await;
+
+    request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/entry", ginger.id))
+        .reply(&api)
+        .await;
+    for (kombucha, days) in &[(&apple, 3), (&blueberry, 1)] {
+        let response = request()
+            .method("POST")
+            .path(&format!("/api/1/kombucha/{}/fermentation/advance", kombucha.id))
+            .json(&serde_json::json!({
+                "est_end_date": chrono::Utc::now() + chrono::Duration::days(*days),
+            }))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let page = list(&api, "").await;
+    assert_eq!(names(&page), vec!["Ginger", "apple", "Blueberry"]);
+    assert_eq!(page.kombuchas[0].entry_count, 1);
+    assert_eq!(page.kombuchas[0].status, None);
+    assert_eq!(page.kombuchas[1].status, Some(FermentationStatus::Primary));
+    assert!(page.kombuchas[1].next_milestone.is_some());
+
+    let first = list(&api, "sort=name&limit=2").await;
+    assert_eq!(names(&first), vec!["Blueberry", "Ginger"]);
+    let cursor = first.next_cursor.expect("No cursor to the second page");
+    let second =
+        list(&api, &format!("sort=name&limit=2&cursor={}", cursor)).await;
+    assert_eq!(names(&second), vec!["apple"]);
+    assert_eq!(second.next_cursor, None);
+
+    let page = list(&api, "sort=next_milestone").await;
+    assert_eq!(names(&page), vec!["Blueberry", "apple", "Ginger"]);
+    let first = list(&api, "sort=next_milestone&limit=1").await;
+    let second = list(
+        &api,
+        &format!(
+            "sort=next_milestone&limit=2&cursor={}",
+            first.next_cursor.unwrap()
+        ),
+    )
+    .await;
+    assert_eq!(names(&second), vec!["apple", "Ginger"]);
+
+    let page = list(&api, "status=Primary").await;
+    assert_eq!(names(&page), vec!["apple", "Blueberry"]);
+
+    let page = list(&api, "q=BERRY").await;
+    assert_eq!(names(&page), vec!["Blueberry"]);
+
+    let added_after = ginger
+        .added
+        .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+    let page = list(&api, &format!("added_after={}", added_after)).await;
+    assert_eq!(names(&page), vec!["apple", "Blueberry"]);
+
+    for query in &[
+        "limit=0",
+        "cursor=zz",
+        &format!("sort=added&cursor={}", cursor),
+    ] {
+        let response = request()
+            .path(&format!("/api/1/kombucha?{}", query))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let response = request()
+        .path("/api/1/kombucha?sort=popularity")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+async fn searches_names_and_entries(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let search = |q: &'static str| {
+        let api = &api;
+        async move {
+            let response = request()
+                .path(&format!("/api/1/search?q={}", q))
+                .reply(api)
+                .await;
+            assert_eq!(response.status(), StatusCode::OK);
+            json::<Vec<SearchHit>>(response.body())
+        }
+    };
+    let highlights = |hit: &SearchHit| -> Vec<String> {
+        hit.snippet
+            .iter()
+            .filter(|part| part.highlighted)
+            .map(|part| part.text.to_lowercase())
+            .collect()
+    };
+
+    let ginger =
+        rename_kombucha(&api, create_kombucha(&api).await, "Ginger lemon")
+            .await;
+    let entry_id = write_entry(
+        &api,
+        ginger.id,
+        None,
+        "Added fresh ginger root, tasted sour",
+    )
+    .await;
+    let hibiscus =
+        rename_kombucha(&api, create_kombucha(&api).await, "Hibiscus").await;
+    write_entry(&api, hibiscus.id, None, "Bottled with blueberries").await;
+
+    let hits = search("GING").await;
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().all(|hit| hit.kombucha_id == ginger.id));
+    assert!(hits.iter().any(|hit| hit.entry_id.is_none()));
+    assert!(hits.iter().any(|hit| hit.entry_id == Some(entry_id)));
+    assert!(hits.iter().all(|hit| highlights(hit) == vec!["ginger"]));
+
+    let hits = search("blueb").await;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].kombucha_name, "Hibiscus");
+    assert!(hits[0].entry_id.is_some());
+
+    assert!(search("ginger%20hibiscus").await.is_empty());
+    assert!(search("").await.is_empty());
+    assert!(search("%25%22*").await.is_empty());
+
+    write_entry(&api, ginger.id, Some(entry_id), "Tasted sour").await;
+    let hits = search("ginger").await;
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].entry_id, None);
+
+    request()
+        .method("DELETE")
+        .path(&format!("/api/1/kombucha/{}", ginger.id))
+        .reply(&api)
+        .await;
+    assert!(search("ginger").await.is_empty());
+
+    let response = request()
+        .path("/api/1/search?q=a&limit=0")
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+/// Writes the same kombuchas, returns what each search found without the
+/// ids, which the stores count differently
+async fn search_results(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+    queries: &[&str],
+) -> Vec<Vec<(String, bool, Vec<SnippetPart>, f32)>> {
+    let ginger =
+        rename_kombucha(&api, create_kombucha(&api).await, "Ginger lemon")
+            .await;
+    write_entry(&api, ginger.id, None, "Added fresh ginger root").await;
+    let long_entry = format!(
+        "{}The scoby looks healthy, topped up with ginger tea. {}",
+        "Nothing to note yet. ".repeat(10),
+        "Still waiting. ".repeat(20)
+    );
+    write_entry(&api, ginger.id, None, &long_entry).await;
+    let hibiscus =
+        rename_kombucha(&api, create_kombucha(&api).await, "Hibiscus").await;
+    write_entry(&api, hibiscus.id, None, "Bottled with ginger and hibiscus")
+        .await;
+
+    let mut results = Vec::new();
+    for q in queries {
+        let response = request()
+            .path(&format!("/api/1/search?q={}", q))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let hits: Vec<SearchHit> = json(response.body());
+        results.push(
+            hits.into_iter()
+                .map(|hit| {
+                    (
+                        hit.kombucha_name,
+                        hit.entry_id.is_some(),
+                        hit.snippet,
+                        hit.rank,
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    results
+}
+
+#[tokio::test]
+async fn searches_the_same_in_memory_and_sqlite() {
+    let queries = ["ginger", "GIN%20te", "hibiscus", "scoby", "lemon%20tea"];
+    let memory = search_results(memory_api().await, &queries).await;
+    let sqlite = search_results(sqlite_api().await, &queries).await;
+
+    assert_eq!(memory, sqlite);
+    assert_eq!(memory[0].len(), 4);
+    assert!(memory[4].is_empty());
+
+    // Only the words around the match are shown of the long entry
+    let snippet: String = memory[3][0]
+        .2
+        .iter()
+        .map(|part| part.text.as_str())
+        .collect();
+    assert_eq!(
+        snippet,
+        "... Nothing to note yet. The scoby looks healthy, topped up with \
+         ginger tea. Still waiting. Still waiting. Still waiting. Still \
+         waiting. Still waiting. Still waiting. Still waiting. Still \
+         waiting. Still waiting. Still waiting. Still waiting ..."
+    );
+}
+
+/// A write that fails half way through is undone and doesn't keep the tables
+/// locked for the ones after it
+async fn failed_writes_leave_the_tables_unlocked(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    write_entry(&api, id, None, "Still here").await;
+
+    // Rejected once the current stage has been read
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/fermentation/advance", id))
+        .json(&serde_json::json!({ "status": "Secondary" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let kombucha = rename_kombucha(&api, id, "Renamed").await;
+
+    // Fails on the entry after the kombucha itself was updated
+    let mut missing_entry = kombucha.clone();
+    missing_entry.name = "Half renamed".to_string();
+    missing_entry.entries[0].id = EntryId::from(9999);
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&missing_entry)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let renamed = rename_kombucha(&api, id, "Renamed again").await;
+    assert_eq!(renamed.version, kombucha.version + 1);
+}
+
+async fn rejects_stale_updates(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let entry_id = write_entry(&api, id, None, "First draft").await;
+    let kombucha_path = format!("/api/1/kombucha/{}", id);
+    let entry_path = format!("{}/entry/{}", kombucha_path, entry_id);
+
+    let response = request().path(&kombucha_path).reply(&api).await;
+    let kombucha: Kombucha = json(response.body());
+    // Writing the entry bumped its kombucha as well
+    assert_eq!(kombucha.version, 2);
+    assert_eq!(response.headers()[header::ETAG], etag(2).as_str());
+
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .json(&kombucha)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::PRECONDITION_REQUIRED);
+
+    // Renamed in another tab first
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&Kombucha {
+            name: "Renamed elsewhere".to_string(),
+            ..kombucha.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::ETAG], etag(3).as_str());
+
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&Kombucha {
+            name: "Renamed here".to_string(),
+            ..kombucha.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    let error: ApiError = json(response.body());
+    assert_eq!(error.code, ErrorCode::PreconditionFailed);
+
+    let response = request().path(&kombucha_path).reply(&api).await;
+    let renamed: Kombucha = json(response.body());
+    assert_eq!(renamed.name, "Renamed elsewhere");
+
+    // The rename rewrote the entry too, so the one read before is stale
+    let stale = &kombucha.entries[0];
+    let response = request()
+        .method("PUT")
+        .path(&entry_path)
+        .header(header::IF_MATCH, etag(stale.version))
+        .json(&Entry {
+            content: "Second draft".to_string(),
+            ..stale.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+
+    let response = request().path(&entry_path).reply(&api).await;
+    let entry: Entry = json(response.body());
+    assert_eq!(
+        response.headers()[header::ETAG],
+        etag(entry.version).as_str()
+    );
+    let response = request()
+        .method("PUT")
+        .path(&entry_path)
+        .header(header::IF_MATCH, etag(entry.version))
+        .json(&Entry {
+            content: "Second draft".to_string(),
+            ..entry.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::ETAG],
+        etag(entry.version + 1).as_str()
+    );
+
+    let response = request().path(&kombucha_path).reply(&api).await;
+    assert_eq!(response.headers()[header::ETAG], etag(4).as_str());
+
+    for if_match in &["*", "W/\"4\"", "4"] {
+        let response = request()
+            .method("PUT")
+            .path("/api/1/kombucha")
+            .header(header::IF_MATCH, *if_match)
+            .json(&renamed)
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    }
+
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha/42/entry/42")
+        .header(header::IF_MATCH, etag(1))
+        .json(&entry)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn keeps_entries_after_their_kombucha(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    write_entry(&api, id, None, "Brewed with oolong").await;
+    let kombucha_path = format!("/api/1/kombucha/{}", id);
+    let kombucha: Kombucha =
+        json(request().path(&kombucha_path).reply(&api).await.body());
+    let later = kombucha.entries[0].added + chrono::Duration::days(1);
+
+    let response = request()
+        .method("PATCH")
+        .path(&kombucha_path)
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&serde_json::json!({ "added": later }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let error: ApiError = json(response.body());
+    assert_eq!(error.code, ErrorCode::Validation);
+
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&Kombucha {
+            added: later,
+            ..kombucha.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Fine when the entries move along
+    let mut moved = kombucha.clone();
+    moved.added = later;
+    moved.entries[0].added = later;
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&moved)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let kombucha: Kombucha =
+        json(request().path(&kombucha_path).reply(&api).await.body());
+    assert_eq!(kombucha.added, later);
+}
+
+async fn patches_only_the_given_fields(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let entry_id = write_entry(&api, id, None, "Brewed with oolong").await;
+    let kombucha_path = format!("/api/1/kombucha/{}", id);
+    let entry_path = format!("{}/entry/{}", kombucha_path, entry_id);
+    let kombucha: Kombucha =
+        json(request().path(&kombucha_path).reply(&api).await.body());
+
+    let response = request()
+        .method("PATCH")
+        .path(&kombucha_path)
+        .header(header::IF_MATCH, etag(kombucha.version))
+        .json(&serde_json::json!({ "name": "Oolong" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let patched: Kombucha = json(response.body());
+    assert_eq!(
+        response.headers()[header::ETAG],
+        etag(patched.version).as_str()
+    );
+    assert!(patched.updated_at > kombucha.updated_at);
+    assert_eq!(
+        patched,
+        Kombucha {
+            name: "Oolong".to_string(),
+            version: kombucha.version + 1,
+            updated_at: patched.updated_at,
+            ..kombucha.clone()
+        }
+    );
+
+    let entry = &kombucha.entries[0];
+    let response = request()
+        .method("PATCH")
+        .path(&entry_path)
+        .header(header::IF_MATCH, etag(entry.version))
+        .json(
+            &serde_json::json!({ "content": "Brewed with oolong and sencha" }),
+        )
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let patched: Entry = json(response.body());
+    assert!(patched.updated_at > entry.updated_at);
+    assert_eq!(
+        patched,
+        Entry {
+            content: "Brewed with oolong and sencha".to_string(),
+            version: entry.version + 1,
+            updated_at: patched.updated_at,
+            ..entry.clone()
+        }
+    );
+    // Updating the entry updated its kombucha as well
+    let kombucha_now: Kombucha =
+        json(request().path(&kombucha_path).reply(&api).await.body());
+    assert_eq!(kombucha_now.updated_at, patched.updated_at);
+
+    let response = request()
+        .method("PATCH")
+        .path(&entry_path)
+        .header(header::IF_MATCH, etag(patched.version))
+        .json(&serde_json::json!({
+            "added": kombucha.added - chrono::Duration::days(1)
+        }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = request()
+        .method("PATCH")
+        .path(&kombucha_path)
+        .header(header::IF_MATCH, etag(kombucha.version + 2))
+        .json(&serde_json::json!({ "nmae": "Typo" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Entries of another kombucha are neither patched nor updated through it
+    let other = create_kombucha(&api).await;
+    let response = request()
+        .method("PATCH")
+        .path(&format!("/api/1/kombucha/{}/entry/{}", other, entry_id))
+        .header(header::IF_MATCH, etag(patched.version))
+        .json(&serde_json::json!({ "content": "Hijacked" }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let other: Kombucha = json(
+        request()
+            .path(&format!("/api/1/kombucha/{}", other))
+            .reply(&api)
+            .await
+            .body(),
+    );
+    let response = request()
+        .method("PUT")
+        .path("/api/1/kombucha")
+        .header(header::IF_MATCH, etag(other.version))
+        .json(&Kombucha {
+            name: "Hijacker".to_string(),
+            entries: vec![Entry {
+                content: "Hijacked".to_string(),
+                ..patched.clone()
+            }],
+            ..other.clone()
+        })
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}", other.id))
+        .reply(&api)
+        .await;
+    assert_eq!(json::<Kombucha>(response.body()), other);
+    let response = request().path(&entry_path).reply(&api).await;
+    assert_eq!(json::<Entry>(response.body()), patched);
+}
+
+async fn get_trash(
+    api: &(impl Filter<Extract = impl Reply, Error = Rejection> + 'static),
+) -> Trash {
+    let response = request().path("/api/1/trash").reply(api).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    json(response.body())
+}
+
+async fn adds_nothing_to_trashed_kombuchas(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    let kombucha_path = format!("/api/1/kombucha/{}", id);
+    let response = request()
+        .method("DELETE")
+        .path(&kombucha_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    for (path, body) in &[
+        ("entry", serde_json::json!(null)),
+        ("fermentation", serde_json::json!({ "status": "Primary" })),
+        ("fermentation/advance", serde_json::json!({})),
+        (
+            "measurement",
+            serde_json::json!({ "kind": "ph", "value": 3.2 }),
+        ),
+    ] {
+        let mut request = request()
+            .method("POST")
+            .path(&format!("{}/{}", kombucha_path, path));
+        if !body.is_null() {
+            request = request.json(body);
+        }
+        let response = request.reply(&api).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{}", path);
+    }
+    let response = post_image(&api, id, None, "image/png", &png()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Nor do they turn up once it's restored
+    let response = request()
+        .method("POST")
+        .path(&format!("{}/restore", kombucha_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let kombucha: Kombucha =
+        json(request().path(&kombucha_path).reply(&api).await.body());
+    assert_eq!(kombucha.entries, vec![]);
+    assert_eq!(kombucha.fermentations, vec![]);
+    assert_eq!(kombucha.measurements, vec![]);
+    assert_eq!(kombucha.images, vec![]);
+}
+
+async fn restores_deleted_kombuchas_and_entries(
+    api: impl Filter<Extract = impl Reply, Error = Rejection> + 'static,
+) {
+    let id = create_kombucha(&api).await;
+    rename_kombucha(&api, id, "Jun").await;
+    let early = write_entry(&api, id, None, "Started with green tea").await;
+    let late = write_entry(&api, id, None, "Moved to the pantry").await;
+    request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/fermentation/advance", id))
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+
+    let kombucha_path = format!("/api/1/kombucha/{}", id);
+    let early_path = format!("{}/entry/{}", kombucha_path, early);
+
+    let response = request()
+        .method("DELETE")
+        .path(&early_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let trash = get_trash(&api).await;
+    assert!(trash.kombuchas.is_empty());
+    assert_eq!(trash.entries.len(), 1);
+    assert_eq!(trash.entries[0].entry.id, early);
+    assert_eq!(trash.entries[0].kombucha_name, "Jun");
+
+    let response = request()
+        .method("DELETE")
+        .path(&kombucha_path)
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&kombucha_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(list(&api, "").await.kombuchas.is_empty());
+
+    let response = request().path("/api/1/search?q=jun").reply(&api).await;
+    let hits: Vec<SearchHit> = json(response.body());
+    assert!(hits.is_empty());
+
+    // The entry is listed under the kombucha until that's restored
+    let trash = get_trash(&api).await;
+    assert_eq!(trash.kombuchas.len(), 1);
+    assert_eq!(trash.kombuchas[0].id, id);
+    assert!(trash.entries.is_empty());
+
+    let response = request()
+        .method("POST")
+        .path(&format!("{}/restore", early_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = request()
+        .method("POST")
+        .path(&format!("{}/restore", kombucha_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Only what was deleted along with the kombucha comes back with it
+    let response = request().path(&kombucha_path).reply(&api).await;
+    let kombucha: Kombucha = json(response.body());
+    let entry_ids: Vec<_> =
+        kombucha.entries.iter().map(|entry| entry.id).collect();
+    assert_eq!(entry_ids, vec![late]);
+    assert_eq!(kombucha.fermentations.len(), 1);
+    assert_eq!(get_trash(&api).await.entries.len(), 1);
+
+    let response = request()
+        .method("POST")
+        .path(&format!("{}/restore", early_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = request().path(&early_path).reply(&api).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(get_trash(&api).await, Trash::default());
+
+    let response = request()
+        .method("POST")
+        .path(&format!("{}/restore", kombucha_path))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+async fn purges_expired_trash(app: App, dir: PathBuf) {
+    let app = Arc::new(app);
+    let api = routes(app.clone(), dir.to_string_lossy().into_owned());
+
+    // Purging a kombucha with a fermentation used to trip over its foreign key
+    let deleted = create_kombucha(&api).await;
+    write_entry(&api, deleted, None, "Gone for good").await;
+    let (image_id, _) = upload_image(&api, deleted).await;
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/fermentation/advance", deleted))
+        .json(&serde_json::json!({}))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/measurement", deleted))
+        .json(&serde_json::json!({ "kind": "ph", "value": 3.2 }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let kept = create_kombucha(&api).await;
+    let entry_id = write_entry(&api, kept, None, "Gone for good too").await;
+
+    request()
+        .method("DELETE")
+        .path(&format!("/api/1/kombucha/{}", deleted))
+        .reply(&api)
+        .await;
+    request()
+        .method("DELETE")
+        .path(&format!("/api/1/kombucha/{}/entry/{}", kept, entry_id))
+        .reply(&api)
+        .await;
+
+    let purged = app.purge_trash(chrono::Duration::days(1)).await.unwrap();
+    assert!(purged.is_empty());
+    assert_eq!(get_trash(&api).await.kombuchas.len(), 1);
+    assert!(dir.join("images").join(deleted.to_string()).exists());
+
+    let purged = app.purge_trash(chrono::Duration::zero()).await.unwrap();
+    assert_eq!(purged, vec![deleted]);
+    assert_eq!(get_trash(&api).await, Trash::default());
+    assert!(!dir.join("images").join(deleted.to_string()).exists());
+
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/restore", deleted))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = request()
+        .path(&format!("/api/1/kombucha/{}/image/{}", deleted, image_id))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+// `get_all_kombuchas` is only reachable through the calendar, which doesn't
+// show measurements
+async fn caps_measurements_of_kombuchas(app: App, dir: PathBuf) {
+    let app = Arc::new(app);
+    let api = routes(app.clone(), dir.to_string_lossy().into_owned());
+    let start: chrono::DateTime<chrono::Utc> =
+        "2020-04-17T08:00:00Z".parse().unwrap();
+
+    let logged = create_kombucha(&api).await;
+    let measurements_path = format!("/api/1/kombucha/{}/measurement", logged);
+    let mut ids = Vec::new();
+    // Logged newest first, so that ids and dates don't agree on the order
+    for i in (0..Kombucha::LATEST_MEASUREMENTS as i64 + 5).rev() {
+        let response = request()
+            .method("POST")
+            .path(&measurements_path)
+            .json(&serde_json::json!({
+                "kind": "ph",
+                "value": 3.5,
+                "taken_at": start + chrono::Duration::hours(i),
+            }))
+            .reply(&api)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        ids.insert(0, json::<MeasurementId>(response.body()));
+    }
+    let latest = ids[5..].to_vec();
+
+    let barely_logged = create_kombucha(&api).await;
+    let response = request()
+        .method("POST")
+        .path(&format!("/api/1/kombucha/{}/measurement", barely_logged))
+        .json(&serde_json::json!({ "kind": "ph", "value": 3.2 }))
+        .reply(&api)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let measurement_ids = |kombucha: &Kombucha| -> Vec<MeasurementId> {
+        kombucha.measurements.iter().map(|m| m.id).collect()
+    };
+
+    let kombucha: Kombucha = json(
+        request()
+            .path(&format!("/api/1/kombucha/{}", logged))
+            .reply(&api)
+            .await
+            .body(),
+    );
+    assert_eq!(measurement_ids(&kombucha), latest);
+
+    let measurements: Vec<Measurement> =
+        json(request().path(&measurements_path).reply(&api).await.body());
+    assert_eq!(measurements.len(), ids.len());
+
+    let kombuchas = app.get_all_kombuchas().await.unwrap();
+    assert_eq!(measurement_ids(&kombuchas[0]), latest);
+    assert_eq!(kombuchas[1].measurements.len(), 1);
+}
@@ -0,0 +1,26 @@
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    BadRequest,
+    Validation,
+    Conflict,
+    PreconditionFailed,
+    PreconditionRequired,
+    PayloadTooLarge,
+    Database,
+    Internal,
+}
+
+/// Body of every error response of the API
+#[derive(Debug, Clone, PartialEq, Display, Deserialize, Serialize)]
+#[display(fmt = "{}", message)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default)]
+    pub details: Option<String>,
+}
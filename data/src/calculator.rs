@@ -0,0 +1,165 @@
+use crate::{Recipe, RecipeTea};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+pub const GRAMS_PER_OUNCE: f64 = 28.349_523_125;
+/// US liquid gallon
+pub const ML_PER_GALLON: f64 = 3_785.411_784;
+/// US cup, a sixteenth of a gallon
+pub const ML_PER_CUP: f64 = ML_PER_GALLON / 16.0;
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Display, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MassUnit {
+    #[display(fmt = "g")]
+    Gram,
+    #[display(fmt = "oz")]
+    Ounce,
+}
+
+impl MassUnit {
+    pub const ALL: [MassUnit; 2] = [MassUnit::Gram, MassUnit::Ounce];
+
+    fn grams(self) -> f64 {
+        match self {
+            MassUnit::Gram => 1.0,
+            MassUnit::Ounce => GRAMS_PER_OUNCE,
+        }
+    }
+
+    pub fn to_grams(self, value: f64) -> f64 {
+        value * self.grams()
+    }
+
+    pub fn from_grams(self, grams: f64) -> f64 {
+        grams / self.grams()
+    }
+}
+
+impl FromStr for MassUnit {
+    type Err = anyhow::Error;
+
+    /// Takes the symbols the units are displayed with
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|unit| unit.to_string() == s)
+            .ok_or_else(|| anyhow::anyhow!("{} isn't a unit of mass", s))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Display, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VolumeUnit {
+    #[display(fmt = "ml")]
+    Millilitre,
+    #[display(fmt = "L")]
+    Litre,
+    #[display(fmt = "gal")]
+    Gallon,
+    #[display(fmt = "cup")]
+    Cup,
+}
+
+impl VolumeUnit {
+    pub const ALL: [VolumeUnit; 4] = [
+        VolumeUnit::Millilitre,
+        VolumeUnit::Litre,
+        VolumeUnit::Gallon,
+        VolumeUnit::Cup,
+    ];
+
+    fn millilitres(self) -> f64 {
+        match self {
+            VolumeUnit::Millilitre => 1.0,
+            VolumeUnit::Litre => 1000.0,
+            VolumeUnit::Gallon => ML_PER_GALLON,
+            VolumeUnit::Cup => ML_PER_CUP,
+        }
+    }
+
+    pub fn to_ml(self, value: f64) -> f64 {
+        value * self.millilitres()
+    }
+
+    pub fn from_ml(self, ml: f64) -> f64 {
+        ml / self.millilitres()
+    }
+}
+
+impl FromStr for VolumeUnit {
+    type Err = anyhow::Error;
+
+    /// Takes the symbols the units are displayed with
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|unit| unit.to_string() == s)
+            .ok_or_else(|| anyhow::anyhow!("{} isn't a unit of volume", s))
+    }
+}
+
+pub fn convert_mass(value: f64, from: MassUnit, to: MassUnit) -> f64 {
+    to.from_grams(from.to_grams(value))
+}
+
+pub fn convert_volume(value: f64, from: VolumeUnit, to: VolumeUnit) -> f64 {
+    to.from_ml(from.to_ml(value))
+}
+
+/// How strong a batch is, sugar and tea are per litre of water
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+pub struct Strength {
+    pub sugar_per_litre: f64,
+    pub tea_per_litre: f64,
+    /// Share of the starter in the whole batch, between 0 and 1
+    pub starter_share: f64,
+}
+
+impl Strength {
+    pub fn of(recipe: &Recipe) -> Self {
+        let litres = recipe.water_ml / 1000.0;
+
+        Self {
+            sugar_per_litre: recipe.sugar_grams / litres,
+            tea_per_litre: recipe.tea_grams() / litres,
+            starter_share: recipe.starter_ml / recipe.total_ml(),
+        }
+    }
+
+    pub fn starter_percent(&self) -> f64 {
+        self.starter_share * 100.0
+    }
+}
+
+/// The recipe for a batch of `total_ml`, water and starter together, at the
+/// same strength. The vessel, the planned stages and the flavourings, whose
+/// amounts are free text, stay as they are
+pub fn scale(recipe: &Recipe, total_ml: f64) -> Result<Recipe, anyhow::Error> {
+    if !total_ml.is_finite() || total_ml <= 0.0 {
+        return Err(anyhow::anyhow!("Can't scale a batch to {} ml", total_ml));
+    }
+    if recipe.total_ml() <= 0.0 {
+        return Err(anyhow::anyhow!("Can't scale a batch without liquid"));
+    }
+
+    let factor = total_ml / recipe.total_ml();
+
+    Ok(Recipe {
+        teas: recipe
+            .teas
+            .iter()
+            .map(|tea| RecipeTea {
+                variety: tea.variety.clone(),
+                grams: tea.grams * factor,
+            })
+            .collect(),
+        sugar_grams: recipe.sugar_grams * factor,
+        water_ml: recipe.water_ml * factor,
+        starter_ml: recipe.starter_ml * factor,
+        ..recipe.clone()
+    })
+}
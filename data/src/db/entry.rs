@@ -8,4 +8,6 @@ pub struct Entry {
     pub kombucha_id: KombuchaId,
     pub content: String,
     pub added: DateTime<Utc>,
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
 }
@@ -0,0 +1,16 @@
+use crate::{
+    id::{EntryId, ImageId},
+    KombuchaId,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Image {
+    pub id: ImageId,
+    pub kombucha_id: KombuchaId,
+    pub entry_id: Option<EntryId>,
+    pub file_name: String,
+    pub content_type: String,
+    pub added: DateTime<Utc>,
+}
@@ -7,4 +7,6 @@ pub struct Kombucha {
     pub id: KombuchaId,
     pub name: String,
     pub added: DateTime<Utc>,
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
 }
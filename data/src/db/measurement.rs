@@ -0,0 +1,17 @@
+use crate::{
+    id::{FermentationId, MeasurementId},
+    KombuchaId, MeasurementKind,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Measurement {
+    pub id: MeasurementId,
+    pub kombucha_id: KombuchaId,
+    pub kind: MeasurementKind,
+    pub value: f64,
+    pub unit: String,
+    pub taken_at: DateTime<Utc>,
+    pub fermentation_id: Option<FermentationId>,
+}
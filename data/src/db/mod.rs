@@ -1,7 +1,13 @@
 mod entry;
 mod fermentation;
+mod image;
 mod kombucha;
+mod measurement;
+mod recipe;
 
 pub use self::entry::Entry;
 pub use self::fermentation::Fermentation;
+pub use self::image::Image;
 pub use self::kombucha::Kombucha;
+pub use self::measurement::Measurement;
+pub use self::recipe::{Recipe, RecipeFlavouring, RecipeStage, RecipeTea};
@@ -0,0 +1,38 @@
+use crate::{FermentationStatus, KombuchaId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct Recipe {
+    pub kombucha_id: KombuchaId,
+    pub sugar_type: String,
+    pub sugar_grams: f64,
+    pub water_ml: f64,
+    pub starter_ml: f64,
+    pub vessel: String,
+    pub vessel_ml: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct RecipeTea {
+    pub kombucha_id: KombuchaId,
+    pub position: i32,
+    pub variety: String,
+    pub grams: f64,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct RecipeStage {
+    pub kombucha_id: KombuchaId,
+    pub position: i32,
+    pub status: FermentationStatus,
+    pub days: i32,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize, sqlx::FromRow)]
+pub struct RecipeFlavouring {
+    pub kombucha_id: KombuchaId,
+    pub position: i32,
+    pub ingredient: String,
+    pub amount: String,
+    pub stage: Option<FermentationStatus>,
+}
@@ -7,6 +7,23 @@ pub struct Entry {
     pub id: EntryId,
     pub added: DateTime<Utc>,
     pub content: String,
+    /// Bumped whenever the entry is updated, sent as its ETag
+    #[serde(default)]
+    pub version: i32,
+    /// When the version was last bumped
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `PATCH /api/1/kombucha/{id}/entry/{id}`, fields that are left out
+/// stay as they are
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntryPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
 }
 
 impl Entry {
@@ -15,6 +32,18 @@ impl Entry {
             id,
             added,
             content: text,
+            version: 1,
+            updated_at: added,
+        }
+    }
+}
+
+impl From<&Entry> for EntryPatch {
+    /// Every field, as for a whole `Entry` sent with `PUT`
+    fn from(entry: &Entry) -> Self {
+        Self {
+            added: Some(entry.added),
+            content: Some(entry.content.clone()),
         }
     }
 }
@@ -33,10 +62,21 @@ mod db {
     impl From<DbEntry> for Entry {
         fn from(
             DbEntry {
-                id, added, content, ..
+                id,
+                added,
+                content,
+                version,
+                updated_at,
+                ..
             }: DbEntry,
         ) -> Self {
-            Self { id, added, content }
+            Self {
+                id,
+                added,
+                content,
+                version,
+                updated_at,
+            }
         }
     }
 }
@@ -10,3 +10,77 @@ pub struct Fermentation {
     pub est_end_date: Option<DateTime<Utc>>,
     pub status: FermentationStatus,
 }
+
+impl Fermentation {
+    pub fn is_active(&self) -> bool {
+        self.end_date.is_none()
+    }
+
+    /// Fraction of the estimated duration that has elapsed by `now` (or by the
+    /// end of the fermentation if it already ended), clamped to `0.0..=1.0`
+    pub fn progress(&self, now: DateTime<Utc>) -> Option<f64> {
+        let est_end_date = self.est_end_date?;
+        let until = self.end_date.unwrap_or(now);
+
+        let total = est_end_date - self.start_date;
+        if total.num_seconds() <= 0 {
+            return Some(1.0);
+        }
+
+        let elapsed = until - self.start_date;
+        let progress = elapsed.num_seconds() as f64 / total.num_seconds() as f64;
+
+        Some(progress.clamp(0.0, 1.0))
+    }
+}
+
+/// Body of a request creating a new fermentation, `start_date` defaults to now
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct NewFermentation {
+    #[serde(default)]
+    pub start_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub est_end_date: Option<DateTime<Utc>>,
+    pub status: FermentationStatus,
+}
+
+/// Body of a request moving a kombucha to another stage, when `status` is
+/// missing the kombucha moves to the next stage of its current fermentation
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct AdvanceFermentation {
+    #[serde(default)]
+    pub status: Option<FermentationStatus>,
+    #[serde(default)]
+    pub est_end_date: Option<DateTime<Utc>>,
+    /// Set by the server when it estimates the end, so that the estimate
+    /// counts from the moment the stage starts
+    #[serde(skip)]
+    pub start_date: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "db")]
+mod db {
+    use super::Fermentation;
+    use crate::db::Fermentation as DbFermentation;
+
+    impl From<DbFermentation> for Fermentation {
+        fn from(
+            DbFermentation {
+                id,
+                start_date,
+                end_date,
+                est_end_date,
+                status,
+                ..
+            }: DbFermentation,
+        ) -> Self {
+            Self {
+                id,
+                start_date,
+                end_date,
+                est_end_date,
+                status,
+            }
+        }
+    }
+}
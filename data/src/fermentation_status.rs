@@ -8,6 +8,82 @@ use serde::{Deserialize, Serialize};
 pub enum FermentationStatus {
     Primary,
     Secondary,
+    Bottled,
+    Conditioning,
+    Refrigerated,
+    Finished,
+    Failed,
+    Discarded,
+}
+
+impl FermentationStatus {
+    pub const ALL: [FermentationStatus; 8] = [
+        FermentationStatus::Primary,
+        FermentationStatus::Secondary,
+        FermentationStatus::Bottled,
+        FermentationStatus::Conditioning,
+        FermentationStatus::Refrigerated,
+        FermentationStatus::Finished,
+        FermentationStatus::Failed,
+        FermentationStatus::Discarded,
+    ];
+
+    /// Terminal stages can't be advanced any further
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            FermentationStatus::Finished
+                | FermentationStatus::Failed
+                | FermentationStatus::Discarded
+        )
+    }
+
+    /// The stage a batch normally moves to after this one
+    pub fn next(self) -> Option<FermentationStatus> {
+        match self {
+            FermentationStatus::Primary => Some(FermentationStatus::Secondary),
+            FermentationStatus::Secondary => Some(FermentationStatus::Bottled),
+            FermentationStatus::Bottled => Some(FermentationStatus::Conditioning),
+            FermentationStatus::Conditioning => Some(FermentationStatus::Refrigerated),
+            FermentationStatus::Refrigerated => Some(FermentationStatus::Finished),
+            FermentationStatus::Finished
+            | FermentationStatus::Failed
+            | FermentationStatus::Discarded => None,
+        }
+    }
+
+    pub fn can_transition_to(self, to: FermentationStatus) -> bool {
+        use FermentationStatus::*;
+
+        match (self, to) {
+            (from, _) if from.is_terminal() => false,
+            (_, Failed) | (_, Discarded) => true,
+            (Primary, Secondary)
+            | (Secondary, Bottled)
+            | (Secondary, Conditioning)
+            | (Bottled, Conditioning)
+            | (Bottled, Refrigerated)
+            | (Conditioning, Refrigerated)
+            | (Refrigerated, Finished) => true,
+            _ => false,
+        }
+    }
+
+    /// Validates a move from `self` to `to`, returning the new stage
+    pub fn transition_to(
+        self,
+        to: FermentationStatus,
+    ) -> Result<FermentationStatus, anyhow::Error> {
+        if self.can_transition_to(to) {
+            Ok(to)
+        } else {
+            Err(anyhow::anyhow!(
+                "Invalid fermentation transition from {} to {}",
+                self,
+                to
+            ))
+        }
+    }
 }
 
 mod from_str {
@@ -22,8 +98,83 @@ mod from_str {
             match s {
                 "Primary" => Ok(FermentationStatus::Primary),
                 "Secondary" => Ok(FermentationStatus::Secondary),
+                "Bottled" => Ok(FermentationStatus::Bottled),
+                "Conditioning" => Ok(FermentationStatus::Conditioning),
+                "Refrigerated" => Ok(FermentationStatus::Refrigerated),
+                "Finished" => Ok(FermentationStatus::Finished),
+                "Failed" => Ok(FermentationStatus::Failed),
+                "Discarded" => Ok(FermentationStatus::Discarded),
                 _ => Err(Error::msg("Invalid fermentation")),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::FermentationStatus::{self, *};
+
+    #[test]
+    fn allows_only_listed_transitions() {
+        let allowed = [
+            (Primary, Secondary),
+            (Secondary, Bottled),
+            (Secondary, Conditioning),
+            (Bottled, Conditioning),
+            (Bottled, Refrigerated),
+            (Conditioning, Refrigerated),
+            (Refrigerated, Finished),
+            (Primary, Failed),
+            (Primary, Discarded),
+            (Secondary, Failed),
+            (Bottled, Discarded),
+            (Conditioning, Failed),
+            (Refrigerated, Discarded),
+        ];
+        let rejected = [
+            (Primary, Primary),
+            (Primary, Bottled),
+            (Primary, Finished),
+            (Secondary, Primary),
+            (Secondary, Refrigerated),
+            (Bottled, Secondary),
+            (Conditioning, Bottled),
+            (Refrigerated, Conditioning),
+            (Finished, Primary),
+            (Finished, Failed),
+            (Failed, Discarded),
+            (Discarded, Primary),
+        ];
+
+        for &(from, to) in &allowed {
+            assert!(from.can_transition_to(to), "{} to {}", from, to);
+            assert_eq!(from.transition_to(to).unwrap(), to);
+        }
+        for &(from, to) in &rejected {
+            assert!(!from.can_transition_to(to), "{} to {}", from, to);
+            assert!(from.transition_to(to).is_err(), "{} to {}", from, to);
+        }
+    }
+
+    #[test]
+    fn next_stage_is_an_allowed_transition() {
+        let cases: [(FermentationStatus, Option<FermentationStatus>); 8] = [
+            (Primary, Some(Secondary)),
+            (Secondary, Some(Bottled)),
+            (Bottled, Some(Conditioning)),
+            (Conditioning, Some(Refrigerated)),
+            (Refrigerated, Some(Finished)),
+            (Finished, None),
+            (Failed, None),
+            (Discarded, None),
+        ];
+
+        for &(status, next) in &cases {
+            assert_eq!(status.next(), next, "after {}", status);
+            if let Some(next) = next {
+                assert!(status.can_transition_to(next));
+            }
+            assert_eq!(next.is_none(), status.is_terminal());
+        }
+    }
+}
@@ -1,14 +1,116 @@
 use derive_more::{Display, From, FromStr, Into};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Display, PartialEq, Clone, Copy, Deserialize, Serialize, From, Into, FromStr)]
+#[derive(
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    From,
+    Into,
+    FromStr,
+)]
 #[cfg_attr(feature = "db", derive(sqlx::Type), sqlx(transparent))]
 pub struct KombuchaId(i32);
 
-#[derive(Debug, Display, PartialEq, Clone, Copy, Deserialize, Serialize, From, Into, FromStr)]
+#[derive(
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    From,
+    Into,
+    FromStr,
+)]
 #[cfg_attr(feature = "db", derive(sqlx::Type), sqlx(transparent))]
 pub struct EntryId(i32);
 
-#[derive(Debug, Display, PartialEq, Clone, Copy, Deserialize, Serialize, From, Into, FromStr)]
+#[derive(
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    From,
+    Into,
+    FromStr,
+)]
 #[cfg_attr(feature = "db", derive(sqlx::Type), sqlx(transparent))]
 pub struct FermentationId(i32);
+
+#[derive(
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    From,
+    Into,
+    FromStr,
+)]
+#[cfg_attr(feature = "db", derive(sqlx::Type), sqlx(transparent))]
+pub struct ImageId(i32);
+
+#[derive(
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    From,
+    Into,
+    FromStr,
+)]
+#[cfg_attr(feature = "db", derive(sqlx::Type), sqlx(transparent))]
+pub struct MeasurementId(i32);
+
+#[derive(
+    Debug,
+    Display,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    From,
+    Into,
+    FromStr,
+)]
+#[cfg_attr(feature = "db", derive(sqlx::Type), sqlx(transparent))]
+pub struct TemplateId(i32);
@@ -0,0 +1,36 @@
+use crate::id::{EntryId, ImageId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Image {
+    pub id: ImageId,
+    pub entry_id: Option<EntryId>,
+    pub content_type: String,
+    pub added: DateTime<Utc>,
+}
+
+#[cfg(feature = "db")]
+mod db {
+    use super::Image;
+    use crate::db::Image as DbImage;
+
+    impl From<DbImage> for Image {
+        fn from(
+            DbImage {
+                id,
+                entry_id,
+                content_type,
+                added,
+                ..
+            }: DbImage,
+        ) -> Self {
+            Self {
+                id,
+                entry_id,
+                content_type,
+                added,
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use super::Entry;
-use crate::{Fermentation, KombuchaId};
+use crate::{Fermentation, Image, KombuchaId, Measurement, Milestone, Recipe, TemplateId};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -8,11 +8,55 @@ pub struct Kombucha {
     pub id: KombuchaId,
     pub name: String,
     pub added: DateTime<Utc>,
+    /// Bumped whenever the kombucha or one of its entries is updated, sent as
+    /// its ETag
+    #[serde(default)]
+    pub version: i32,
+    /// When the version was last bumped
+    #[serde(default = "Utc::now")]
+    pub updated_at: DateTime<Utc>,
     pub entries: Vec<Entry>,
     pub fermentations: Vec<Fermentation>,
+    #[serde(default)]
+    pub images: Vec<Image>,
+    /// The latest `LATEST_MEASUREMENTS`, oldest first. All of them are at
+    /// `/api/1/kombucha/{id}/measurement`
+    #[serde(default)]
+    pub measurements: Vec<Measurement>,
+    #[serde(default)]
+    pub recipe: Option<Recipe>,
+}
+
+/// Body of `PATCH /api/1/kombucha/{id}`, fields that are left out stay as
+/// they are
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct KombuchaPatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub added: Option<DateTime<Utc>>,
+}
+
+/// Body of `POST /api/1/kombucha`, a batch is brewed either from a template
+/// or again from an earlier one, or from scratch when neither is given
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewKombucha {
+    /// Defaults to the name of the template or the earlier batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_id: Option<TemplateId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clone_from: Option<KombuchaId>,
 }
 
 impl Kombucha {
+    /// How many measurements a kombucha comes with, a sensor that logs every
+    /// few minutes would otherwise blow up every fetch of it
+    pub const LATEST_MEASUREMENTS: usize = 20;
+
     pub fn new_without_id(
         name: impl ToString,
         added: DateTime<Utc>,
@@ -23,8 +67,34 @@ impl Kombucha {
             id: 0.into(),
             name: name.to_string(),
             added,
+            version: 1,
+            updated_at: added,
             entries,
             fermentations,
+            images: vec![],
+            measurements: vec![],
+            recipe: None,
         }
     }
+
+    /// The fermentation that hasn't ended yet, the latest one if there are
+    /// several
+    pub fn current_fermentation(&self) -> Option<&Fermentation> {
+        self.fermentations
+            .iter()
+            .filter(|fermentation| fermentation.is_active())
+            .max_by_key(|fermentation| fermentation.start_date)
+    }
+
+    /// The estimated end of the current fermentation, `None` when nothing is
+    /// fermenting or the end wasn't estimated
+    pub fn next_milestone(&self) -> Option<Milestone> {
+        let fermentation = self.current_fermentation()?;
+
+        fermentation.est_end_date.map(|date| Milestone {
+            fermentation_id: fermentation.id,
+            status: fermentation.status,
+            date,
+        })
+    }
 }
@@ -0,0 +1,69 @@
+use crate::{FermentationStatus, Kombucha, KombuchaId, Milestone};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What the list of kombuchas shows, the whole `Kombucha` is fetched by id
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct KombuchaSummary {
+    pub id: KombuchaId,
+    pub name: String,
+    pub added: DateTime<Utc>,
+    /// Status of the latest fermentation, `None` before the first one
+    pub status: Option<FermentationStatus>,
+    pub next_milestone: Option<Milestone>,
+    pub entry_count: u32,
+}
+
+/// One page of the list, `next_cursor` is passed back as `cursor` to get the
+/// page after it and is `None` on the last page
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct KombuchaPage {
+    pub kombuchas: Vec<KombuchaSummary>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KombuchaSort {
+    Name,
+    #[default]
+    Added,
+    /// Most urgent first, kombuchas without a milestone come last
+    NextMilestone,
+}
+
+/// Query string of the list, every filter is optional
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct KombuchaQuery {
+    #[serde(default)]
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: KombuchaSort,
+    /// Only kombuchas whose latest fermentation is in this stage
+    #[serde(default)]
+    pub status: Option<FermentationStatus>,
+    /// Only kombuchas whose name contains this, ignoring case
+    #[serde(default)]
+    pub q: Option<String>,
+    #[serde(default)]
+    pub added_after: Option<DateTime<Utc>>,
+}
+
+impl Kombucha {
+    pub fn summary(&self) -> KombuchaSummary {
+        KombuchaSummary {
+            id: self.id,
+            name: self.name.clone(),
+            added: self.added,
+            status: self
+                .fermentations
+                .iter()
+                .max_by_key(|fermentation| (fermentation.start_date, fermentation.id))
+                .map(|fermentation| fermentation.status),
+            next_milestone: self.next_milestone(),
+            entry_count: self.entries.len() as u32,
+        }
+    }
+}
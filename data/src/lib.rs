@@ -1,14 +1,35 @@
+mod api_error;
+pub mod calculator;
 mod entry;
 mod fermentation;
 mod fermentation_status;
 mod id;
+mod image;
 mod kombucha;
+mod kombucha_summary;
+mod measurement;
+mod milestone;
+mod recipe;
+mod search;
+mod template;
+mod trash;
 
 #[cfg(feature = "db")]
 pub mod db;
 
-pub use self::entry::Entry;
-pub use self::fermentation::Fermentation;
+pub use self::api_error::{ApiError, ErrorCode};
+pub use self::entry::{Entry, EntryPatch};
+pub use self::fermentation::{AdvanceFermentation, Fermentation, NewFermentation};
 pub use self::fermentation_status::FermentationStatus;
-pub use self::id::{EntryId, FermentationId, KombuchaId};
-pub use self::kombucha::Kombucha;
+pub use self::id::{EntryId, FermentationId, ImageId, KombuchaId, MeasurementId, TemplateId};
+pub use self::image::Image;
+pub use self::kombucha::{Kombucha, KombuchaPatch, NewKombucha};
+pub use self::kombucha_summary::{KombuchaPage, KombuchaQuery, KombuchaSort, KombuchaSummary};
+pub use self::measurement::{
+    Measurement, MeasurementKind, MeasurementSeries, NewMeasurement, SeriesPoint, SeriesQuery,
+};
+pub use self::milestone::Milestone;
+pub use self::recipe::{PlannedStage, Recipe, RecipeFlavouring, RecipeTea, RecipeWarning};
+pub use self::search::{SearchHit, SearchQuery, SnippetPart};
+pub use self::template::{NewRecipeTemplate, RecipeTemplate};
+pub use self::trash::{DeletedEntry, DeletedFermentation, DeletedKombucha, Trash};
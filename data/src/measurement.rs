@@ -0,0 +1,167 @@
+use crate::id::{FermentationId, MeasurementId};
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Display, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "db", derive(sqlx::Type))]
+#[cfg_attr(feature = "db", sqlx(rename = "measurement_kind"))]
+#[cfg_attr(feature = "db", sqlx(rename_all = "lowercase"))]
+pub enum MeasurementKind {
+    #[display(fmt = "pH")]
+    Ph,
+    #[display(fmt = "Temperature")]
+    Temperature,
+    #[display(fmt = "Specific gravity")]
+    #[cfg_attr(feature = "db", sqlx(rename = "specific_gravity"))]
+    SpecificGravity,
+}
+
+impl MeasurementKind {
+    pub const ALL: [MeasurementKind; 3] = [
+        MeasurementKind::Ph,
+        MeasurementKind::Temperature,
+        MeasurementKind::SpecificGravity,
+    ];
+
+    /// Units a reading of this kind can be in, the first one is the default
+    pub fn units(self) -> &'static [&'static str] {
+        match self {
+            MeasurementKind::Ph => &["pH"],
+            MeasurementKind::Temperature => &["°C", "°F"],
+            MeasurementKind::SpecificGravity => &["SG"],
+        }
+    }
+
+    pub fn default_unit(self) -> &'static str {
+        self.units()[0]
+    }
+
+    /// `value` in the default unit, so that readings taken in different
+    /// units can be compared
+    pub fn to_default_unit(self, value: f64, unit: &str) -> f64 {
+        match (self, unit) {
+            (MeasurementKind::Temperature, "°F") => (value - 32.0) * 5.0 / 9.0,
+            _ => value,
+        }
+    }
+
+    /// Rejects units that don't belong to this kind and values no brew could
+    /// read, most likely typos
+    pub fn check(self, value: f64, unit: &str) -> Result<(), anyhow::Error> {
+        if !self.units().contains(&unit) {
+            return Err(anyhow::anyhow!(
+                "{} isn't a unit of {}, use one of {}",
+                unit,
+                self,
+                self.units().join(", ")
+            ));
+        }
+
+        let (min, max) = match (self, unit) {
+            (MeasurementKind::Ph, _) => (0.0, 14.0),
+            (MeasurementKind::Temperature, "°F") => (14.0, 212.0),
+            (MeasurementKind::Temperature, _) => (-10.0, 100.0),
+            (MeasurementKind::SpecificGravity, _) => (0.9, 1.2),
+        };
+        if !(min..=max).contains(&value) {
+            return Err(anyhow::anyhow!(
+                "{} {} isn't between {} and {}",
+                value,
+                unit,
+                min,
+                max
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A reading taken of a kombucha, during one of its fermentations if
+/// `fermentation_id` is set
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct Measurement {
+    pub id: MeasurementId,
+    pub kind: MeasurementKind,
+    pub value: f64,
+    pub unit: String,
+    pub taken_at: DateTime<Utc>,
+    #[serde(default)]
+    pub fermentation_id: Option<FermentationId>,
+}
+
+/// Body of a request logging a measurement, `unit` defaults to the first one
+/// of the kind and `taken_at` to now
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct NewMeasurement {
+    pub kind: MeasurementKind,
+    pub value: f64,
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub taken_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub fermentation_id: Option<FermentationId>,
+}
+
+impl NewMeasurement {
+    pub fn unit(&self) -> &str {
+        self.unit
+            .as_deref()
+            .unwrap_or_else(|| self.kind.default_unit())
+    }
+}
+
+/// Readings of one kind over time, in the default unit of the kind. Long
+/// series are downsampled, `total` is the number of readings before that
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct MeasurementSeries {
+    pub kind: MeasurementKind,
+    pub unit: String,
+    pub points: Vec<SeriesPoint>,
+    pub total: u32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+pub struct SeriesPoint {
+    pub taken_at: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// Query string of the series, `points` caps the points of each series
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct SeriesQuery {
+    #[serde(default)]
+    pub points: Option<u32>,
+}
+
+#[cfg(feature = "db")]
+mod db {
+    use super::Measurement;
+    use crate::db::Measurement as DbMeasurement;
+
+    impl From<DbMeasurement> for Measurement {
+        fn from(
+            DbMeasurement {
+                id,
+                kind,
+                value,
+                unit,
+                taken_at,
+                fermentation_id,
+                ..
+            }: DbMeasurement,
+        ) -> Self {
+            Self {
+                id,
+                kind,
+                value,
+                unit,
+                taken_at,
+                fermentation_id,
+            }
+        }
+    }
+}
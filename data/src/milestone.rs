@@ -0,0 +1,94 @@
+use crate::{FermentationId, FermentationStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The estimated end of the fermentation a kombucha is currently in
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+pub struct Milestone {
+    pub fermentation_id: FermentationId,
+    pub status: FermentationStatus,
+    pub date: DateTime<Utc>,
+}
+
+impl Milestone {
+    /// Whole calendar days from `now` until the milestone, negative when the
+    /// milestone is overdue
+    pub fn days_left(&self, now: DateTime<Utc>) -> i64 {
+        let (date, today) = (self.date.naive_utc().date(), now.naive_utc().date());
+
+        date.signed_duration_since(today).num_days()
+    }
+
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.days_left(now) < 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Milestone;
+    use crate::{Fermentation, FermentationStatus, Kombucha};
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.ymd(2026, 10, day).and_hms(hour, 0, 0)
+    }
+
+    fn fermentation(
+        id: i32,
+        end_date: Option<DateTime<Utc>>,
+        est_end_date: Option<DateTime<Utc>>,
+    ) -> Fermentation {
+        Fermentation {
+            id: id.into(),
+            start_date: at(1, 12),
+            end_date,
+            est_end_date,
+            status: FermentationStatus::Primary,
+        }
+    }
+
+    #[test]
+    fn counts_calendar_days_left() {
+        let now = at(18, 23);
+        let cases = [
+            (at(10, 12), -8),
+            (at(17, 23), -1),
+            (at(18, 0), 0),
+            (at(18, 23), 0),
+            (at(19, 0), 1),
+            (at(25, 6), 7),
+        ];
+
+        for &(date, days) in &cases {
+            let milestone = Milestone {
+                fermentation_id: 1.into(),
+                status: FermentationStatus::Primary,
+                date,
+            };
+            assert_eq!(milestone.days_left(now), days, "until {}", date);
+            assert_eq!(milestone.is_overdue(now), days < 0, "until {}", date);
+        }
+    }
+
+    #[test]
+    fn picks_the_current_fermentations_end() {
+        let kombucha =
+            |fermentations| Kombucha::new_without_id("Test", at(1, 12), vec![], fermentations);
+
+        let current = kombucha(vec![
+            fermentation(1, Some(at(8, 12)), Some(at(8, 12))),
+            fermentation(2, None, Some(at(20, 12))),
+        ]);
+        let milestone = current.next_milestone().unwrap();
+        assert_eq!(milestone.fermentation_id, 2.into());
+        assert_eq!(milestone.date, at(20, 12));
+
+        // Every fermentation ended, or the end wasn't estimated
+        let ended = kombucha(vec![fermentation(1, Some(at(8, 12)), Some(at(8, 12)))]);
+        assert_eq!(ended.next_milestone(), None);
+        let unplanned = kombucha(vec![fermentation(1, None, None)]);
+        assert_eq!(unplanned.next_milestone(), None);
+        assert_eq!(kombucha(vec![]).next_milestone(), None);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{calculator::Strength, Fermentation, FermentationStatus};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The starter liquid should make up at least this share of the batch
+pub const MIN_STARTER_SHARE: f64 = 0.1;
+/// Grams of sugar per litre of sweet tea that still ferment well
+pub const SUGAR_PER_LITRE: (f64, f64) = (40.0, 120.0);
+/// Grams of tea per litre of water that still brew well
+pub const TEA_PER_LITRE: (f64, f64) = (2.0, 15.0);
+/// Longest a planned stage may last
+pub const MAX_STAGE_DAYS: u32 = 365;
+
+/// What went into a batch, amounts are in grams and millilitres
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct Recipe {
+    pub teas: Vec<RecipeTea>,
+    pub sugar_type: String,
+    pub sugar_grams: f64,
+    pub water_ml: f64,
+    pub starter_ml: f64,
+    #[serde(default)]
+    pub vessel: String,
+    /// How much the vessel holds, if known
+    #[serde(default)]
+    pub vessel_ml: Option<f64>,
+    /// How long each stage is meant to last, in the order they're brewed
+    #[serde(default)]
+    pub stages: Vec<PlannedStage>,
+    /// What is added on top of tea and sugar, in the order it's added
+    #[serde(default)]
+    pub flavourings: Vec<RecipeFlavouring>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct RecipeTea {
+    pub variety: String,
+    pub grams: f64,
+}
+
+/// Fruit, herbs or spices, the amount is free text like `2 slices`
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct RecipeFlavouring {
+    pub ingredient: String,
+    pub amount: String,
+    /// The stage it goes in at, if it matters
+    #[serde(default)]
+    pub stage: Option<FermentationStatus>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+pub struct PlannedStage {
+    pub status: FermentationStatus,
+    pub days: u32,
+}
+
+impl PlannedStage {
+    /// The estimated durations of the first fermentation of each stage,
+    /// rounded to whole days
+    pub fn from_fermentations(fermentations: &[Fermentation]) -> Vec<PlannedStage> {
+        let mut fermentations: Vec<_> = fermentations.iter().collect();
+        fermentations.sort_by_key(|fermentation| fermentation.start_date);
+
+        let mut stages: Vec<PlannedStage> = Vec::new();
+        for fermentation in fermentations {
+            let est_end_date = match fermentation.est_end_date {
+                Some(est_end_date) => est_end_date,
+                None => continue,
+            };
+            if stages
+                .iter()
+                .any(|stage| stage.status == fermentation.status)
+            {
+                continue;
+            }
+
+            let hours = (est_end_date - fermentation.start_date).num_hours();
+            let days = ((hours as f64 / 24.0).round() as u32).max(1);
+            stages.push(PlannedStage {
+                status: fermentation.status,
+                days: days.min(MAX_STAGE_DAYS),
+            });
+        }
+
+        stages
+    }
+}
+
+/// A ratio of a recipe that is likely to give a poor brew, the recipe is
+/// still saved
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipeWarning {
+    /// Share of the starter in the whole batch
+    LowStarter(f64),
+    /// Grams of sugar per litre of water
+    SugarRatio(f64),
+    /// Grams of tea per litre of water
+    TeaRatio(f64),
+    /// Millilitres of the whole batch
+    OverfilledVessel(f64),
+}
+
+impl fmt::Display for RecipeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecipeWarning::LowStarter(share) => write!(
+                f,
+                "Starter is only {:.0}% of the batch, use at least {:.0}%",
+                share * 100.0,
+                MIN_STARTER_SHARE * 100.0
+            ),
+            RecipeWarning::SugarRatio(grams) => write!(
+                f,
+                "{:.0} g of sugar per litre isn't between {} and {}",
+                grams, SUGAR_PER_LITRE.0, SUGAR_PER_LITRE.1
+            ),
+            RecipeWarning::TeaRatio(grams) => write!(
+                f,
+                "{:.1} g of tea per litre isn't between {} and {}",
+                grams, TEA_PER_LITRE.0, TEA_PER_LITRE.1
+            ),
+            RecipeWarning::OverfilledVessel(ml) => {
+                write!(f, "A batch of {:.0} ml doesn't fit into the vessel", ml)
+            }
+        }
+    }
+}
+
+impl Recipe {
+    /// Water and starter together
+    pub fn total_ml(&self) -> f64 {
+        self.water_ml + self.starter_ml
+    }
+
+    pub fn tea_grams(&self) -> f64 {
+        self.teas.iter().map(|tea| tea.grams).sum()
+    }
+
+    /// Days the stage is planned to last, if it's planned at all
+    pub fn planned_days(&self, status: FermentationStatus) -> Option<u32> {
+        self.stages
+            .iter()
+            .find(|stage| stage.status == status)
+            .map(|stage| stage.days)
+    }
+
+    /// Rejects amounts that can't be measured, ratios are left to `warnings`
+    pub fn check(&self) -> Result<(), anyhow::Error> {
+        let amounts = self
+            .teas
+            .iter()
+            .map(|tea| ("tea", tea.grams))
+            .chain(vec![
+                ("sugar", self.sugar_grams),
+                ("water", self.water_ml),
+                ("starter", self.starter_ml),
+            ])
+            .chain(self.vessel_ml.map(|ml| ("vessel", ml)));
+        for (name, amount) in amounts {
+            if !amount.is_finite() || amount < 0.0 {
+                return Err(anyhow::anyhow!("Amount of {} can't be {}", name, amount));
+            }
+        }
+
+        if self.water_ml == 0.0 {
+            return Err(anyhow::anyhow!("A recipe needs water"));
+        }
+        if self.vessel_ml == Some(0.0) {
+            return Err(anyhow::anyhow!("A vessel has to hold something"));
+        }
+        if self.teas.iter().any(|tea| tea.variety.trim().is_empty()) {
+            return Err(anyhow::anyhow!("Every tea needs a variety"));
+        }
+        if self
+            .flavourings
+            .iter()
+            .any(|flavouring| flavouring.ingredient.trim().is_empty())
+        {
+            return Err(anyhow::anyhow!("Every flavouring needs an ingredient"));
+        }
+        for (idx, stage) in self.stages.iter().enumerate() {
+            if !(1..=MAX_STAGE_DAYS).contains(&stage.days) {
+                return Err(anyhow::anyhow!(
+                    "{} days of {} isn't between 1 and {}",
+                    stage.days,
+                    stage.status,
+                    MAX_STAGE_DAYS
+                ));
+            }
+            if self.stages[..idx]
+                .iter()
+                .any(|planned| planned.status == stage.status)
+            {
+                return Err(anyhow::anyhow!(
+                    "{} is planned more than once",
+                    stage.status
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ratios outside of what usually brews well, a recipe that passes
+    /// `check` might still have some
+    pub fn warnings(&self) -> Vec<RecipeWarning> {
+        let mut warnings = Vec::new();
+        let strength = Strength::of(self);
+
+        if strength.starter_share < MIN_STARTER_SHARE {
+            warnings.push(RecipeWarning::LowStarter(strength.starter_share));
+        }
+
+        let sugar = strength.sugar_per_litre;
+        if !(SUGAR_PER_LITRE.0..=SUGAR_PER_LITRE.1).contains(&sugar) {
+            warnings.push(RecipeWarning::SugarRatio(sugar));
+        }
+
+        let tea = strength.tea_per_litre;
+        if !(TEA_PER_LITRE.0..=TEA_PER_LITRE.1).contains(&tea) {
+            warnings.push(RecipeWarning::TeaRatio(tea));
+        }
+
+        if let Some(vessel_ml) = self.vessel_ml {
+            if self.total_ml() > vessel_ml {
+                warnings.push(RecipeWarning::OverfilledVessel(self.total_ml()));
+            }
+        }
+
+        warnings
+    }
+}
+
+#[cfg(feature = "db")]
+mod db {
+    use super::{PlannedStage, Recipe, RecipeFlavouring, RecipeTea};
+    use crate::db::{
+        Recipe as DbRecipe, RecipeFlavouring as DbRecipeFlavouring, RecipeStage as DbRecipeStage,
+        RecipeTea as DbRecipeTea,
+    };
+
+    impl Recipe {
+        /// The recipe row together with its teas, stages and flavourings,
+        /// each in the order they were added
+        pub fn from_db(
+            DbRecipe {
+                sugar_type,
+                sugar_grams,
+                water_ml,
+                starter_ml,
+                vessel,
+                vessel_ml,
+                ..
+            }: DbRecipe,
+            db_teas: Vec<DbRecipeTea>,
+            db_stages: Vec<DbRecipeStage>,
+            db_flavourings: Vec<DbRecipeFlavouring>,
+        ) -> Self {
+            Self {
+                teas: db_teas.into_iter().map(RecipeTea::from).collect(),
+                sugar_type,
+                sugar_grams,
+                water_ml,
+                starter_ml,
+                vessel,
+                vessel_ml,
+                stages: db_stages.into_iter().map(PlannedStage::from).collect(),
+                flavourings: db_flavourings
+                    .into_iter()
+                    .map(RecipeFlavouring::from)
+                    .collect(),
+            }
+        }
+    }
+
+    impl From<DbRecipeTea> for RecipeTea {
+        fn from(DbRecipeTea { variety, grams, .. }: DbRecipeTea) -> Self {
+            Self { variety, grams }
+        }
+    }
+
+    impl From<DbRecipeFlavouring> for RecipeFlavouring {
+        fn from(
+            DbRecipeFlavouring {
+                ingredient,
+                amount,
+                stage,
+                ..
+            }: DbRecipeFlavouring,
+        ) -> Self {
+            Self {
+                ingredient,
+                amount,
+                stage,
+            }
+        }
+    }
+
+    impl From<DbRecipeStage> for PlannedStage {
+        fn from(DbRecipeStage { status, days, .. }: DbRecipeStage) -> Self {
+            Self {
+                status,
+                days: days as u32,
+            }
+        }
+    }
+}
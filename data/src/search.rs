@@ -0,0 +1,29 @@
+use crate::{EntryId, KombuchaId};
+use serde::{Deserialize, Serialize};
+
+/// Query string of a search, words match anything they're a prefix of
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct SearchQuery {
+    #[serde(default)]
+    pub q: String,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// A kombucha whose name matched, or one of its entries when `entry_id` is
+/// set, best matches come first
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct SearchHit {
+    pub kombucha_id: KombuchaId,
+    pub kombucha_name: String,
+    pub entry_id: Option<EntryId>,
+    pub snippet: Vec<SnippetPart>,
+    pub rank: f32,
+}
+
+/// A piece of the matched text, `highlighted` when it matched the query
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
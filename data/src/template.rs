@@ -0,0 +1,17 @@
+use crate::{id::TemplateId, Recipe};
+use serde::{Deserialize, Serialize};
+
+/// A recipe saved under a name so that new batches can be brewed from it
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct RecipeTemplate {
+    pub id: TemplateId,
+    pub name: String,
+    pub recipe: Recipe,
+}
+
+/// Body of a request saving a template
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct NewRecipeTemplate {
+    pub name: String,
+    pub recipe: Recipe,
+}
@@ -0,0 +1,36 @@
+use crate::{Entry, Fermentation, KombuchaId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Everything deleted and not purged yet, most recently deleted first. What
+/// was deleted along with a kombucha comes back with it, so it's only listed
+/// under the kombucha
+#[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
+pub struct Trash {
+    pub kombuchas: Vec<DeletedKombucha>,
+    pub entries: Vec<DeletedEntry>,
+    pub fermentations: Vec<DeletedFermentation>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct DeletedKombucha {
+    pub id: KombuchaId,
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct DeletedEntry {
+    pub kombucha_id: KombuchaId,
+    pub kombucha_name: String,
+    pub entry: Entry,
+    pub deleted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize, Serialize)]
+pub struct DeletedFermentation {
+    pub kombucha_id: KombuchaId,
+    pub kombucha_name: String,
+    pub fermentation: Fermentation,
+    pub deleted_at: DateTime<Utc>,
+}
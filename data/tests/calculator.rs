@@ -0,0 +1,167 @@
+//! The calculator over recipes whose amounts are worked out by hand
+
+use data_types::calculator::{
+    convert_mass, convert_volume, scale, MassUnit, Strength, VolumeUnit, ML_PER_CUP,
+};
+use data_types::{FermentationStatus, PlannedStage, Recipe, RecipeFlavouring, RecipeTea};
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "{} isn't close to {}",
+        actual,
+        expected
+    );
+}
+
+/// The usual gallon batch, 14 cups of sweet tea and 2 cups of starter
+fn gallon_batch() -> Recipe {
+    Recipe {
+        teas: vec![
+            RecipeTea {
+                variety: "Black".to_string(),
+                grams: 12.0,
+            },
+            RecipeTea {
+                variety: "Green".to_string(),
+                grams: 4.0,
+            },
+        ],
+        sugar_type: "Cane".to_string(),
+        sugar_grams: 200.0,
+        water_ml: 14.0 * ML_PER_CUP,
+        starter_ml: 2.0 * ML_PER_CUP,
+        vessel: "Gallon jar".to_string(),
+        vessel_ml: Some(VolumeUnit::Gallon.to_ml(1.25)),
+        stages: vec![PlannedStage {
+            status: FermentationStatus::Primary,
+            days: 10,
+        }],
+        flavourings: vec![RecipeFlavouring {
+            ingredient: "Ginger".to_string(),
+            amount: "a thumb".to_string(),
+            stage: Some(FermentationStatus::Secondary),
+        }],
+    }
+}
+
+#[test]
+fn converts_mass() {
+    assert_close(
+        convert_mass(1.0, MassUnit::Ounce, MassUnit::Gram),
+        28.349523125,
+    );
+    assert_close(
+        convert_mass(200.0, MassUnit::Gram, MassUnit::Ounce),
+        7.054792384,
+    );
+    assert_close(convert_mass(5.0, MassUnit::Gram, MassUnit::Gram), 5.0);
+}
+
+#[test]
+fn converts_volume() {
+    assert_close(
+        convert_volume(1.0, VolumeUnit::Gallon, VolumeUnit::Cup),
+        16.0,
+    );
+    assert_close(
+        convert_volume(1.0, VolumeUnit::Gallon, VolumeUnit::Litre),
+        3.785411784,
+    );
+    assert_close(
+        convert_volume(2.5, VolumeUnit::Litre, VolumeUnit::Millilitre),
+        2500.0,
+    );
+    assert_close(
+        convert_volume(1.0, VolumeUnit::Cup, VolumeUnit::Millilitre),
+        236.5882365,
+    );
+}
+
+#[test]
+fn parses_unit_symbols() {
+    for &unit in &MassUnit::ALL {
+        assert_eq!(unit.to_string().parse::<MassUnit>().unwrap(), unit);
+    }
+    for &unit in &VolumeUnit::ALL {
+        assert_eq!(unit.to_string().parse::<VolumeUnit>().unwrap(), unit);
+    }
+
+    assert!("kg".parse::<MassUnit>().is_err());
+    assert!("ml".parse::<MassUnit>().is_err());
+    assert!("pint".parse::<VolumeUnit>().is_err());
+}
+
+#[test]
+fn computes_strength() {
+    let recipe = Recipe {
+        teas: vec![RecipeTea {
+            variety: "Assam".to_string(),
+            grams: 10.0,
+        }],
+        sugar_grams: 140.0,
+        water_ml: 2000.0,
+        starter_ml: 500.0,
+        ..Recipe::default()
+    };
+
+    let strength = Strength::of(&recipe);
+    assert_close(strength.sugar_per_litre, 70.0);
+    assert_close(strength.tea_per_litre, 5.0);
+    assert_close(strength.starter_share, 0.2);
+    assert_close(strength.starter_percent(), 20.0);
+
+    // 200 g in 14 cups, an eighth of the gallon is starter
+    let strength = Strength::of(&gallon_batch());
+    assert_close(strength.sugar_per_litre, 60.3821834);
+    assert_close(strength.tea_per_litre, 4.8305747);
+    assert_close(strength.starter_percent(), 12.5);
+}
+
+#[test]
+fn scales_to_target_volume() {
+    let recipe = gallon_batch();
+
+    let doubled = scale(&recipe, VolumeUnit::Gallon.to_ml(2.0)).unwrap();
+    assert_close(doubled.sugar_grams, 400.0);
+    assert_close(doubled.teas[0].grams, 24.0);
+    assert_close(doubled.teas[1].grams, 8.0);
+    assert_close(VolumeUnit::Cup.from_ml(doubled.water_ml), 28.0);
+    assert_close(VolumeUnit::Cup.from_ml(doubled.starter_ml), 4.0);
+
+    // A 2 litre jar brews just as strong
+    let small = scale(&recipe, 2000.0).unwrap();
+    assert_close(small.total_ml(), 2000.0);
+    assert_close(small.water_ml, 1750.0);
+    assert_close(small.starter_ml, 250.0);
+    assert_close(small.sugar_grams, 105.6688209);
+    let (strength, small_strength) = (Strength::of(&recipe), Strength::of(&small));
+    assert_close(small_strength.sugar_per_litre, strength.sugar_per_litre);
+    assert_close(small_strength.tea_per_litre, strength.tea_per_litre);
+    assert_close(small_strength.starter_share, strength.starter_share);
+
+    // Only amounts change
+    assert_eq!(small.teas[0].variety, "Black");
+    assert_eq!(small.sugar_type, recipe.sugar_type);
+    assert_eq!(small.vessel, recipe.vessel);
+    assert_eq!(small.vessel_ml, recipe.vessel_ml);
+    assert_eq!(small.stages, recipe.stages);
+    assert_eq!(small.flavourings, recipe.flavourings);
+    assert_eq!(small.warnings(), recipe.warnings());
+}
+
+#[test]
+fn rejects_impossible_scales() {
+    let recipe = gallon_batch();
+
+    for &total_ml in &[0.0, -500.0, f64::NAN, f64::INFINITY] {
+        assert!(scale(&recipe, total_ml).is_err());
+    }
+
+    let dry = Recipe {
+        water_ml: 0.0,
+        starter_ml: 0.0,
+        ..recipe
+    };
+    assert!(scale(&dry, 2000.0).is_err());
+}
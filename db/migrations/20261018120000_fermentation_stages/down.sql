@@ -0,0 +1,14 @@
+-- Postgres can't drop enum values, so the type is recreated and fermentations
+-- in the removed stages are folded back into 'secondary'
+UPDATE kombucha_fermentation SET status = 'secondary'
+WHERE status NOT IN ('primary', 'secondary');
+
+ALTER TYPE fermentation_status RENAME TO fermentation_status_old;
+
+CREATE TYPE fermentation_status AS ENUM ('primary', 'secondary');
+
+ALTER TABLE kombucha_fermentation
+    ALTER COLUMN status TYPE fermentation_status
+    USING status::text::fermentation_status;
+
+DROP TYPE fermentation_status_old;
@@ -0,0 +1,21 @@
+-- `ALTER TYPE ... ADD VALUE` can't run inside the transaction a migration is
+-- applied in before Postgres 12, and even then the new values can't be used
+-- in it. Recreating the type works in a transaction on every version
+ALTER TYPE fermentation_status RENAME TO fermentation_status_old;
+
+CREATE TYPE fermentation_status AS ENUM (
+    'primary',
+    'secondary',
+    'bottled',
+    'conditioning',
+    'refrigerated',
+    'finished',
+    'failed',
+    'discarded'
+);
+
+ALTER TABLE kombucha_fermentation
+    ALTER COLUMN status TYPE fermentation_status
+    USING status::text::fermentation_status;
+
+DROP TYPE fermentation_status_old;
@@ -0,0 +1,8 @@
+CREATE TABLE kombucha_image (
+    id SERIAL PRIMARY KEY,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    entry_id INTEGER REFERENCES kombucha_entry(id) ON DELETE SET NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    added TIMESTAMPTZ NOT NULL
+);
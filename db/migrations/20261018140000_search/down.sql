@@ -0,0 +1,2 @@
+DROP INDEX kombucha_entry_content_search;
+DROP INDEX kombucha_name_search;
@@ -0,0 +1,4 @@
+CREATE INDEX kombucha_name_search ON kombucha
+    USING GIN (to_tsvector('english', name));
+CREATE INDEX kombucha_entry_content_search ON kombucha_entry
+    USING GIN (to_tsvector('english', content));
@@ -0,0 +1,14 @@
+-- Whatever is in the trash is gone for good
+DELETE FROM kombucha_image
+    WHERE kombucha_id IN (SELECT id FROM kombucha WHERE deleted_at IS NOT NULL);
+UPDATE kombucha_image SET entry_id = NULL
+    WHERE entry_id IN (SELECT id FROM kombucha_entry WHERE deleted_at IS NOT NULL);
+DELETE FROM kombucha_entry WHERE deleted_at IS NOT NULL
+    OR kombucha_id IN (SELECT id FROM kombucha WHERE deleted_at IS NOT NULL);
+DELETE FROM kombucha_fermentation WHERE deleted_at IS NOT NULL
+    OR kombucha_id IN (SELECT id FROM kombucha WHERE deleted_at IS NOT NULL);
+DELETE FROM kombucha WHERE deleted_at IS NOT NULL;
+
+ALTER TABLE kombucha_fermentation DROP COLUMN deleted_at;
+ALTER TABLE kombucha_entry DROP COLUMN deleted_at;
+ALTER TABLE kombucha DROP COLUMN deleted_at;
@@ -0,0 +1,13 @@
+-- Deleting a kombucha deletes everything that belongs to it
+ALTER TABLE kombucha_entry
+    DROP CONSTRAINT kombucha_entry_kombucha_id_fkey,
+    ADD CONSTRAINT kombucha_entry_kombucha_id_fkey
+        FOREIGN KEY (kombucha_id) REFERENCES kombucha(id) ON DELETE CASCADE;
+ALTER TABLE kombucha_fermentation
+    DROP CONSTRAINT kombucha_fermentation_kombucha_id_fkey,
+    ADD CONSTRAINT kombucha_fermentation_kombucha_id_fkey
+        FOREIGN KEY (kombucha_id) REFERENCES kombucha(id) ON DELETE CASCADE;
+ALTER TABLE kombucha_image
+    DROP CONSTRAINT kombucha_image_kombucha_id_fkey,
+    ADD CONSTRAINT kombucha_image_kombucha_id_fkey
+        FOREIGN KEY (kombucha_id) REFERENCES kombucha(id) ON DELETE CASCADE;
@@ -0,0 +1,2 @@
+ALTER TABLE kombucha_entry DROP COLUMN version;
+ALTER TABLE kombucha DROP COLUMN version;
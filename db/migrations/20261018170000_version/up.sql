@@ -0,0 +1,4 @@
+-- Bumped on every update and handed out as the ETag, clients send it back in
+-- `If-Match` so that they can't overwrite changes they haven't seen
+ALTER TABLE kombucha ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE kombucha_entry ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
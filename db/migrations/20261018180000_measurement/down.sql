@@ -0,0 +1,2 @@
+DROP TABLE kombucha_measurement;
+DROP TYPE measurement_kind;
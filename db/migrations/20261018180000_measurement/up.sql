@@ -0,0 +1,12 @@
+CREATE TYPE measurement_kind AS ENUM ('ph', 'temperature', 'specific_gravity');
+CREATE TABLE kombucha_measurement (
+    id SERIAL PRIMARY KEY,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id) ON DELETE CASCADE,
+    fermentation_id INTEGER REFERENCES kombucha_fermentation(id) ON DELETE SET NULL,
+    kind measurement_kind NOT NULL,
+    value DOUBLE PRECISION NOT NULL,
+    unit TEXT NOT NULL,
+    taken_at TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX kombucha_measurement_kombucha_id_idx
+    ON kombucha_measurement (kombucha_id, taken_at);
@@ -0,0 +1,2 @@
+DROP TABLE kombucha_recipe_tea;
+DROP TABLE kombucha_recipe;
@@ -0,0 +1,2 @@
+DROP INDEX kombucha_fermentation_kombucha_id_idx;
+DROP INDEX kombucha_entry_kombucha_id_idx;
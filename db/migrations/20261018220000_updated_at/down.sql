@@ -0,0 +1,2 @@
+ALTER TABLE kombucha_entry DROP COLUMN updated_at;
+ALTER TABLE kombucha DROP COLUMN updated_at;
@@ -0,0 +1,7 @@
+-- Set whenever the version is bumped, rows from before start out at the date
+-- they were added
+ALTER TABLE kombucha ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE kombucha SET updated_at = added;
+ALTER TABLE kombucha_entry
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE kombucha_entry SET updated_at = added;
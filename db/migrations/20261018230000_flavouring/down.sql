@@ -0,0 +1 @@
+DROP TABLE kombucha_recipe_flavouring;
@@ -0,0 +1,2 @@
+DROP TABLE kombucha_entry;
+DROP TABLE kombucha;
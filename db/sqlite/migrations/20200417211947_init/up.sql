@@ -0,0 +1,13 @@
+-- Timestamps are RFC 3339 text in UTC, which sorts chronologically
+CREATE TABLE kombucha (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    added TEXT NOT NULL
+);
+
+CREATE TABLE kombucha_entry (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    content TEXT NOT NULL,
+    added TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP TABLE kombucha_fermentation;
+
+DROP TABLE fermentation_status;
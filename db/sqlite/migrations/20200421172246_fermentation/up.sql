@@ -0,0 +1,16 @@
+-- SQLite has no enums, the values of `fermentation_status` live in a lookup
+-- table instead, so that new ones can be added without rebuilding tables
+CREATE TABLE fermentation_status (
+    name TEXT PRIMARY KEY
+);
+
+INSERT INTO fermentation_status (name) VALUES ('primary'), ('secondary');
+
+CREATE TABLE kombucha_fermentation (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    start_date TEXT NOT NULL,
+    end_date TEXT,
+    est_end_date TEXT,
+    status TEXT NOT NULL REFERENCES fermentation_status(name)
+);
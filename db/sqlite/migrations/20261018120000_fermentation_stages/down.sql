@@ -0,0 +1,4 @@
+UPDATE kombucha_fermentation SET status = 'secondary'
+WHERE status NOT IN ('primary', 'secondary');
+
+DELETE FROM fermentation_status WHERE name NOT IN ('primary', 'secondary');
@@ -0,0 +1,8 @@
+INSERT INTO fermentation_status (name)
+VALUES
+    ('bottled'),
+    ('conditioning'),
+    ('refrigerated'),
+    ('finished'),
+    ('failed'),
+    ('discarded');
@@ -0,0 +1 @@
+DROP TABLE kombucha_image;
@@ -0,0 +1,46 @@
+-- Whatever is in the trash is gone for good
+DELETE FROM kombucha_image
+    WHERE kombucha_id IN (SELECT id FROM kombucha WHERE deleted_at IS NOT NULL);
+UPDATE kombucha_image SET entry_id = NULL
+    WHERE entry_id IN (SELECT id FROM kombucha_entry WHERE deleted_at IS NOT NULL);
+DELETE FROM kombucha_entry WHERE deleted_at IS NOT NULL
+    OR kombucha_id IN (SELECT id FROM kombucha WHERE deleted_at IS NOT NULL);
+DELETE FROM kombucha_fermentation WHERE deleted_at IS NOT NULL
+    OR kombucha_id IN (SELECT id FROM kombucha WHERE deleted_at IS NOT NULL);
+DELETE FROM kombucha WHERE deleted_at IS NOT NULL;
+
+-- The bundled SQLite predates DROP COLUMN, so the tables are rebuilt
+CREATE TABLE kombucha_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    added TEXT NOT NULL
+);
+INSERT INTO kombucha_new (id, name, added) SELECT id, name, added FROM kombucha;
+DROP TABLE kombucha;
+ALTER TABLE kombucha_new RENAME TO kombucha;
+
+CREATE TABLE kombucha_entry_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    content TEXT NOT NULL,
+    added TEXT NOT NULL
+);
+INSERT INTO kombucha_entry_new (id, kombucha_id, content, added)
+    SELECT id, kombucha_id, content, added FROM kombucha_entry;
+DROP TABLE kombucha_entry;
+ALTER TABLE kombucha_entry_new RENAME TO kombucha_entry;
+
+CREATE TABLE kombucha_fermentation_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    start_date TEXT NOT NULL,
+    end_date TEXT,
+    est_end_date TEXT,
+    status TEXT NOT NULL REFERENCES fermentation_status(name)
+);
+INSERT INTO kombucha_fermentation_new
+    (id, kombucha_id, start_date, end_date, est_end_date, status)
+    SELECT id, kombucha_id, start_date, end_date, est_end_date, status
+    FROM kombucha_fermentation;
+DROP TABLE kombucha_fermentation;
+ALTER TABLE kombucha_fermentation_new RENAME TO kombucha_fermentation;
@@ -0,0 +1,5 @@
+-- Deleted rows stay around until they're purged, everything reading them
+-- skips those with a `deleted_at`
+ALTER TABLE kombucha ADD COLUMN deleted_at TEXT;
+ALTER TABLE kombucha_entry ADD COLUMN deleted_at TEXT;
+ALTER TABLE kombucha_fermentation ADD COLUMN deleted_at TEXT;
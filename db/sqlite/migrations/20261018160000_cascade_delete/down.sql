@@ -0,0 +1,46 @@
+CREATE TABLE kombucha_image_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    entry_id INTEGER REFERENCES kombucha_entry(id) ON DELETE SET NULL,
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    added TEXT NOT NULL
+);
+INSERT INTO kombucha_image_new
+    (id, kombucha_id, entry_id, file_name, content_type, added)
+    SELECT id, kombucha_id, entry_id, file_name, content_type, added
+    FROM kombucha_image;
+DROP TABLE kombucha_image;
+ALTER TABLE kombucha_image_new RENAME TO kombucha_image;
+
+CREATE TABLE kombucha_fermentation_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    start_date TEXT NOT NULL,
+    end_date TEXT,
+    est_end_date TEXT,
+    status TEXT NOT NULL REFERENCES fermentation_status(name),
+    deleted_at TEXT
+);
+INSERT INTO kombucha_fermentation_new
+    (id, kombucha_id, start_date, end_date, est_end_date, status,
+    deleted_at)
+    SELECT id, kombucha_id, start_date, end_date, est_end_date, status,
+    deleted_at
+    FROM kombucha_fermentation;
+DROP TABLE kombucha_fermentation;
+ALTER TABLE kombucha_fermentation_new RENAME TO kombucha_fermentation;
+
+CREATE TABLE kombucha_entry_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id),
+    content TEXT NOT NULL,
+    added TEXT NOT NULL,
+    deleted_at TEXT
+);
+INSERT INTO kombucha_entry_new
+    (id, kombucha_id, content, added, deleted_at)
+    SELECT id, kombucha_id, content, added, deleted_at
+    FROM kombucha_entry;
+DROP TABLE kombucha_entry;
+ALTER TABLE kombucha_entry_new RENAME TO kombucha_entry;
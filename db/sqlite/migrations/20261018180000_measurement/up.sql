@@ -0,0 +1,11 @@
+CREATE TABLE kombucha_measurement (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id) ON DELETE CASCADE,
+    fermentation_id INTEGER REFERENCES kombucha_fermentation(id) ON DELETE SET NULL,
+    kind TEXT NOT NULL,
+    value REAL NOT NULL,
+    unit TEXT NOT NULL,
+    taken_at TEXT NOT NULL
+);
+CREATE INDEX kombucha_measurement_kombucha_id_idx
+    ON kombucha_measurement (kombucha_id, taken_at);
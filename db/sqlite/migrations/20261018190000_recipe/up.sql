@@ -0,0 +1,16 @@
+CREATE TABLE kombucha_recipe (
+    kombucha_id INTEGER PRIMARY KEY REFERENCES kombucha(id) ON DELETE CASCADE,
+    sugar_type TEXT NOT NULL,
+    sugar_grams REAL NOT NULL,
+    water_ml REAL NOT NULL,
+    starter_ml REAL NOT NULL,
+    vessel TEXT NOT NULL,
+    vessel_ml REAL
+);
+CREATE TABLE kombucha_recipe_tea (
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha_recipe(kombucha_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    variety TEXT NOT NULL,
+    grams REAL NOT NULL,
+    PRIMARY KEY (kombucha_id, position)
+);
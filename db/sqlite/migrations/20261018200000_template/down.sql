@@ -0,0 +1,2 @@
+DROP TABLE recipe_template;
+DROP TABLE kombucha_recipe_stage;
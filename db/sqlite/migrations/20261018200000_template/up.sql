@@ -0,0 +1,13 @@
+CREATE TABLE kombucha_recipe_stage (
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha_recipe(kombucha_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    status TEXT NOT NULL REFERENCES fermentation_status(name),
+    days INTEGER NOT NULL,
+    PRIMARY KEY (kombucha_id, position)
+);
+-- Templates are only ever copied whole, so the recipe is kept as JSON
+CREATE TABLE recipe_template (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    recipe TEXT NOT NULL
+);
@@ -0,0 +1,4 @@
+CREATE INDEX kombucha_entry_kombucha_id_idx
+    ON kombucha_entry (kombucha_id);
+CREATE INDEX kombucha_fermentation_kombucha_id_idx
+    ON kombucha_fermentation (kombucha_id, start_date);
@@ -0,0 +1,29 @@
+-- The bundled SQLite predates DROP COLUMN, so the tables are rebuilt
+CREATE TABLE kombucha_entry_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    added TEXT NOT NULL,
+    deleted_at TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO kombucha_entry_new
+    (id, kombucha_id, content, added, deleted_at, version)
+    SELECT id, kombucha_id, content, added, deleted_at, version
+    FROM kombucha_entry;
+DROP TABLE kombucha_entry;
+ALTER TABLE kombucha_entry_new RENAME TO kombucha_entry;
+CREATE INDEX kombucha_entry_kombucha_id_idx
+    ON kombucha_entry (kombucha_id);
+
+CREATE TABLE kombucha_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    added TEXT NOT NULL,
+    deleted_at TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO kombucha_new (id, name, added, deleted_at, version)
+    SELECT id, name, added, deleted_at, version FROM kombucha;
+DROP TABLE kombucha;
+ALTER TABLE kombucha_new RENAME TO kombucha;
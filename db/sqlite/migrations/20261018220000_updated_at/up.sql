@@ -0,0 +1,6 @@
+-- Set whenever the version is bumped, rows from before start out at the date
+-- they were added
+ALTER TABLE kombucha ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';
+UPDATE kombucha SET updated_at = added;
+ALTER TABLE kombucha_entry ADD COLUMN updated_at TEXT NOT NULL DEFAULT '';
+UPDATE kombucha_entry SET updated_at = added;
@@ -0,0 +1,8 @@
+CREATE TABLE kombucha_recipe_flavouring (
+    kombucha_id INTEGER NOT NULL REFERENCES kombucha_recipe(kombucha_id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    ingredient TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    stage TEXT REFERENCES fermentation_status(name),
+    PRIMARY KEY (kombucha_id, position)
+);
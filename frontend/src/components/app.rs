@@ -1,61 +1,281 @@
-use crate::components::{ErrorView, KombuchaPanel, KombuchaView};
+use crate::components::{Edit, ErrorView, KombuchaPanel, KombuchaView};
+use crate::keyboard::{Shortcut, SHORTCUTS};
 use anyhow::Error;
-use data_types::{EntryId, Kombucha, KombuchaId};
-use std::{collections::VecDeque, rc::Rc, sync::Mutex};
+use data_types::{
+    AdvanceFermentation, ApiError, Entry, EntryId, EntryPatch, Fermentation,
+    ImageId, Kombucha, KombuchaId, KombuchaPage, KombuchaSummary,
+    MeasurementId, MeasurementSeries, NewKombucha, NewMeasurement,
+    NewRecipeTemplate, Recipe, RecipeTemplate, SearchHit,
+};
+use serde::de::DeserializeOwned;
+use std::{collections::VecDeque, rc::Rc, sync::Mutex, time::Duration};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{File, FormData, KeyboardEvent, RequestInit};
 use yew::{
     format::{Json, Nothing},
     prelude::*,
     services::{
-        fetch::{Request, Response},
-        FetchService, Task,
+        fetch::{Request, Response, StatusCode},
+        keyboard::{KeyListenerHandle, KeyboardService},
+        timeout::TimeoutTask,
+        FetchService, Task, TimeoutService,
     },
 };
 
+/// Kombuchas fetched per page of the list
+const PAGE_SIZE: u32 = 100;
+/// Points per measurement series, about one per pixel of a chart
+const SERIES_POINTS: u32 = 300;
+/// How long a delete can be undone from the toast, it stays in the trash
+/// after that
+const UNDO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something deleted that the toast offers to restore
+#[derive(Clone, Copy)]
+pub enum Deleted {
+    Kombucha(KombuchaId),
+    Entry(KombuchaId, EntryId),
+}
+
+/// An edit the server refused because it was based on an old version, next
+/// to what's saved now
+pub struct Conflict {
+    edit: Edit,
+    saved_version: i32,
+    saved: String,
+    mine: String,
+}
+
 #[derive(Default)]
 pub struct App {
     fetch_service: FetchService,
+    timeout_service: TimeoutService,
     link: ComponentLink<Self>,
     jobs: VecDeque<Box<dyn Task>>,
+    _key_listener: Option<KeyListenerHandle>,
 
     error: Option<String>,
-    selected_idx: Option<usize>,
-    entries: Rc<Mutex<Vec<Kombucha>>>,
+    show_shortcuts: bool,
+    selected: Option<KombuchaId>,
+    kombucha: Option<Kombucha>,
+    /// Measurement series of the selected kombucha
+    series: Vec<MeasurementSeries>,
+    kombuchas: Rc<Mutex<Vec<KombuchaSummary>>>,
+    /// Where the next page of the list starts, `None` after the last one
+    next_cursor: Option<String>,
+    /// Bumped whenever the list is loaded from the start again, pages
+    /// requested before that are dropped when they arrive
+    list_generation: u32,
+    search: String,
+    search_hits: Rc<Mutex<Vec<SearchHit>>>,
+    templates: Rc<Mutex<Vec<RecipeTemplate>>>,
     delete_kombucha_modal: Option<KombuchaId>,
+    undo: Option<Deleted>,
+    undo_task: Option<TimeoutTask>,
+    conflict: Option<Conflict>,
 }
 
 pub enum Msg {
     Unimplemented,
     Nop,
-    AddKombucha,
-    Reload,
-    LoadKombuchas(Vec<Kombucha>),
+    AddKombucha(NewKombucha),
+    /// Something of the kombucha changed, it's fetched again
+    Refresh(KombuchaId),
+    LoadMore,
+    /// A page of the list with the generation and the cursor it was
+    /// requested with
+    LoadKombuchas(u32, Option<String>, KombuchaPage),
+    LoadKombucha(Kombucha),
+    /// Series and the kombucha they were loaded for
+    LoadSeries(KombuchaId, Vec<MeasurementSeries>),
+    Search(String),
+    /// Hits and the search they were found for
+    LoadSearchHits(String, Vec<SearchHit>),
+    ReloadTemplates,
+    LoadTemplates(Vec<RecipeTemplate>),
     DeleteEntry(KombuchaId, EntryId),
-    NewEntry(KombuchaId),
-    Select(Option<usize>),
-    UpdateKombucha(usize, Kombucha),
+    Deleted(Deleted),
+    Undo,
+    CloseUndo,
+    NewEntry(KombuchaId, EntryPatch),
+    Select(Option<KombuchaId>),
+    Edit(Edit),
+    Conflict(Edit),
+    LoadConflict(Conflict),
+    KeepMine,
+    KeepSaved,
     OpenDeleteKombuchaModal(KombuchaId),
     ConfirmDeleteKombuchaModal,
     CloseDeleteKombuchaModal,
     ShowError(Error),
     CloseError,
+    UpdateFermentation(KombuchaId, Fermentation),
+    NextFermentation(KombuchaId),
+    UploadImages(KombuchaId, Option<EntryId>, Vec<File>),
+    DeleteImage(KombuchaId, ImageId),
+    NewMeasurement(KombuchaId, NewMeasurement),
+    DeleteMeasurement(KombuchaId, MeasurementId),
+    SaveRecipe(KombuchaId, Recipe),
+    DeleteRecipe(KombuchaId),
+    SaveTemplate(NewRecipeTemplate),
+    KeyDown(KeyboardEvent),
+    CloseShortcuts,
 }
 
-fn reload_or_show_error(response: Response<Result<String, Error>>) -> Msg {
+/// The body of a successful response, the error of any other
+fn body_or_error(
+    response: Response<Result<String, Error>>,
+) -> Result<String, Error> {
     let (parts, response) = response.into_parts();
     match response {
-        Ok(_) if parts.status.is_success() => Msg::Reload,
-        Ok(body) => Msg::ShowError(Error::msg(body)),
+        Ok(body) if parts.status.is_success() => Ok(body),
+        Ok(body) => Err(api_error(body)),
+        Err(err) => Err(err),
+    }
+}
+
+fn refresh_or_show_error(
+    id: KombuchaId,
+) -> impl Fn(Response<Result<String, Error>>) -> Msg {
+    move |response| match body_or_error(response) {
+        Ok(_) => Msg::Refresh(id),
         Err(err) => Msg::ShowError(err),
     }
 }
 
+/// Refreshes the kombucha whose id the response is
+fn refresh_created_or_show_error(
+    response: Response<Result<String, Error>>,
+) -> Msg {
+    match body_or_error(response) {
+        Ok(body) => match serde_json::from_str(&body) {
+            Ok(id) => Msg::Refresh(id),
+            Err(error) => Msg::ShowError(error.into()),
+        },
+        Err(err) => Msg::ShowError(err),
+    }
+}
+
+fn deleted_or_show_error(
+    deleted: Deleted,
+) -> impl Fn(Response<Result<String, Error>>) -> Msg {
+    move |response| match body_or_error(response) {
+        Ok(_) => Msg::Deleted(deleted),
+        Err(err) => Msg::ShowError(err),
+    }
+}
+
+/// Fetches what's saved when the edit was based on an old version
+fn saved_or_conflict(
+    edit: Edit,
+) -> impl Fn(Response<Result<String, Error>>) -> Msg {
+    move |response| {
+        if response.status() == StatusCode::PRECONDITION_FAILED {
+            Msg::Conflict(edit.clone())
+        } else {
+            refresh_or_show_error(edit.kombucha_id())(response)
+        }
+    }
+}
+
+/// Where a summary falls in the list, the same order the server pages it in
+fn list_position(summary: &KombuchaSummary) -> impl Ord {
+    (
+        summary.next_milestone.is_none(),
+        summary.next_milestone.map(|milestone| milestone.date),
+        summary.id,
+    )
+}
+
+/// `If-Match` for an edit based on `version`
+fn etag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+/// Turns the body of a failed request into a readable error, falling back to
+/// the raw body when it isn't an `ApiError`
+fn api_error(body: String) -> Error {
+    match serde_json::from_str::<ApiError>(&body) {
+        Ok(ApiError {
+            message,
+            details: Some(details),
+            ..
+        }) => Error::msg(format!("{} ({})", message, details)),
+        Ok(ApiError { message, .. }) => Error::msg(message),
+        Err(_) => Error::msg(body),
+    }
+}
+
+/// Escapes everything but unreserved characters for use in a query string
+fn encode_query_value(value: &str) -> String {
+    value.bytes().fold(String::new(), |mut encoded, byte| {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+        encoded
+    })
+}
+
+fn js_error(value: JsValue) -> Error {
+    Error::msg(format!("{:?}", value))
+}
+
+/// `FetchService` can't send multipart bodies, so uploads go through the
+/// browser's fetch directly
+async fn upload_image(
+    url: String,
+    entry_id: Option<EntryId>,
+    file: File,
+) -> Result<(), Error> {
+    let form = FormData::new().map_err(js_error)?;
+    form.append_with_blob_and_filename("file", &file, &file.name())
+        .map_err(js_error)?;
+    if let Some(entry_id) = entry_id {
+        form.append_with_str("entry_id", &entry_id.to_string())
+            .map_err(js_error)?;
+    }
+
+    let mut init = RequestInit::new();
+    init.method("POST");
+    init.body(Some(&form));
+
+    let window = web_sys::window().ok_or_else(|| Error::msg("No window"))?;
+    let response = JsFuture::from(window.fetch_with_str_and_init(&url, &init))
+        .await
+        .map_err(js_error)?;
+    let response: web_sys::Response = response.dyn_into().map_err(js_error)?;
+
+    if response.ok() {
+        return Ok(());
+    }
+
+    let body = JsFuture::from(response.text().map_err(js_error)?)
+        .await
+        .map_err(js_error)?
+        .as_string()
+        .unwrap_or_default();
+
+    Err(api_error(body).context(format!("Failed to upload {}", file.name())))
+}
+
 impl App {
-    fn add_kombucha(&mut self) {
-        let req = Request::post("/api/1/kombucha").body(Nothing).unwrap();
+    fn add_kombucha(&mut self, new_kombucha: &NewKombucha) {
+        let req = Request::post("/api/1/kombucha")
+            .header("content-type", "application/json")
+            .body(Json(new_kombucha))
+            .unwrap();
 
         let task = self
             .fetch_service
-            .fetch(req, self.link.callback(reload_or_show_error))
+            .fetch(req, self.link.callback(refresh_created_or_show_error))
             .unwrap();
 
         self.jobs.push_front(Box::new(task));
@@ -67,35 +287,74 @@ impl App {
 
         let task = self
             .fetch_service
-            .fetch(req, self.link.callback(reload_or_show_error))
+            .fetch(
+                req,
+                self.link.callback(deleted_or_show_error(Deleted::Entry(
+                    id, entry_id,
+                ))),
+            )
             .unwrap();
 
         self.jobs.push_front(Box::new(task));
     }
 
-    fn new_entry(&mut self, id: KombuchaId) {
+    /// Creates an empty entry and fills it in with `patch`
+    fn new_entry(&mut self, id: KombuchaId, patch: EntryPatch) {
         let url = format!("/api/1/kombucha/{}/entry", id);
         let req = Request::post(url).body(Nothing).unwrap();
 
         let task = self
             .fetch_service
-            .fetch(req, self.link.callback(reload_or_show_error))
+            .fetch(
+                req,
+                self.link.callback(
+                    move |response: Response<Result<String, Error>>| {
+                        let (parts, body) = response.into_parts();
+                        match body {
+                            Ok(body) if parts.status.is_success() => {
+                                match serde_json::from_str(&body) {
+                                    Ok(entry_id) => Msg::Edit(Edit::Entry {
+                                        kombucha_id: id,
+                                        entry_id,
+                                        version: 1,
+                                        patch: patch.clone(),
+                                    }),
+                                    Err(error) => Msg::ShowError(error.into()),
+                                }
+                            }
+                            Ok(body) => Msg::ShowError(api_error(body)),
+                            Err(error) => Msg::ShowError(error),
+                        }
+                    },
+                ),
+            )
             .unwrap();
 
         self.jobs.push_front(Box::new(task));
     }
 
-    fn get_all_kombuchas(&mut self) {
-        let req = Request::get("/api/1/kombucha").body(Nothing).unwrap();
+    fn get_json<T: DeserializeOwned + 'static>(
+        &mut self,
+        url: &str,
+        on_load: impl Fn(T) -> Msg + 'static,
+    ) {
+        let req = Request::get(url).body(Nothing).unwrap();
 
         let task = self
             .fetch_service
             .fetch(
                 req,
                 self.link.callback(
-                    |response: Response<Json<Result<Vec<Kombucha>, Error>>>| {
-                        match response.into_body().0 {
-                            Ok(content) => Msg::LoadKombuchas(content),
+                    move |response: Response<Result<String, Error>>| {
+                        let (parts, body) = response.into_parts();
+                        match body {
+                            Ok(body) if parts.status.is_success() => {
+                                match serde_json::from_str(&body) {
+                                    Ok(content) => on_load(content),
+                                    Err(error) => Msg::ShowError(error.into()),
+                                }
+                            }
+                            Ok(body) => Msg::ShowError(api_error(body)),
                             Err(error) => Msg::ShowError(error),
                         }
                     },
@@ -106,20 +365,324 @@ impl App {
         self.jobs.push_front(Box::new(task));
     }
 
-    fn update_kombucha(&mut self, kombucha: &Kombucha) {
-        let req = Request::put("/api/1/kombucha")
+    /// Loads the page of the list after `cursor`, the first one starts the
+    /// list over
+    fn get_kombuchas(&mut self, cursor: Option<String>) {
+        let mut url =
+            format!("/api/1/kombucha?sort=next_milestone&limit={}", PAGE_SIZE);
+        match &cursor {
+            Some(cursor) => url.push_str(&format!("&cursor={}", cursor)),
+            None => self.list_generation = self.list_generation.wrapping_add(1),
+        }
+
+        let generation = self.list_generation;
+        self.get_json(&url, move |page| {
+            Msg::LoadKombuchas(generation, cursor.clone(), page)
+        });
+    }
+
+    /// Fetches a kombucha after it changed, its row in the list is updated
+    /// once it's loaded
+    fn refresh(&mut self, id: KombuchaId) {
+        let url = format!("/api/1/kombucha/{}", id);
+        self.get_json(&url, Msg::LoadKombucha);
+
+        if self.selected == Some(id) {
+            self.get_series(id);
+        }
+        self.search();
+    }
+
+    /// Replaces the row of the summary, or adds it where it belongs unless
+    /// that's on a page that isn't loaded yet
+    fn update_summary(&mut self, summary: KombuchaSummary) {
+        let mut kombuchas = self.kombuchas.lock().unwrap();
+
+        if let Some(row) = kombuchas.iter_mut().find(|row| row.id == summary.id)
+        {
+            *row = summary;
+            return;
+        }
+
+        let position = kombuchas
+            .iter()
+            .position(|row| list_position(row) > list_position(&summary))
+            .unwrap_or_else(|| kombuchas.len());
+        if position < kombuchas.len() || self.next_cursor.is_none() {
+            kombuchas.insert(position, summary);
+        }
+    }
+
+    fn search(&mut self) {
+        if self.search.trim().is_empty() {
+            return;
+        }
+
+        let url =
+            format!("/api/1/search?q={}", encode_query_value(&self.search));
+        let search = self.search.clone();
+        self.get_json(&url, move |hits| {
+            Msg::LoadSearchHits(search.clone(), hits)
+        });
+    }
+
+    fn get_selected_kombucha(&mut self) {
+        if let Some(id) = self.selected {
+            let url = format!("/api/1/kombucha/{}", id);
+            self.get_json(&url, Msg::LoadKombucha);
+            self.get_series(id);
+        }
+    }
+
+    fn get_series(&mut self, id: KombuchaId) {
+        let url = format!(
+            "/api/1/kombucha/{}/measurement/series?points={}",
+            id, SERIES_POINTS
+        );
+        self.get_json(&url, move |series| Msg::LoadSeries(id, series));
+    }
+
+    /// Sends only what changed, based on the version that was edited
+    fn patch(&mut self, edit: Edit) {
+        let (url, version, body): (_, _, Result<String, Error>) = match &edit {
+            Edit::Kombucha { id, version, patch } => (
+                format!("/api/1/kombucha/{}", id),
+                *version,
+                Json(patch).into(),
+            ),
+            Edit::Entry {
+                kombucha_id,
+                entry_id,
+                version,
+                patch,
+            } => (
+                format!("/api/1/kombucha/{}/entry/{}", kombucha_id, entry_id),
+                *version,
+                Json(patch).into(),
+            ),
+        };
+        let req = Request::patch(url)
+            .header("content-type", "application/json")
+            .header("if-match", etag(version))
+            .body(body)
+            .unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(saved_or_conflict(edit)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    /// Loads what's saved to show it next to the refused edit
+    fn get_conflict(&mut self, edit: Edit) {
+        match &edit {
+            Edit::Kombucha { id, patch, .. } => {
+                let url = format!("/api/1/kombucha/{}", id);
+                let mine = patch.name.clone().unwrap_or_default();
+                self.get_json(&url, move |saved: Kombucha| {
+                    Msg::LoadConflict(Conflict {
+                        edit: edit.clone(),
+                        saved_version: saved.version,
+                        saved: saved.name,
+                        mine: mine.clone(),
+                    })
+                });
+            }
+            Edit::Entry {
+                kombucha_id,
+                entry_id,
+                patch,
+                ..
+            } => {
+                let url = format!(
+                    "/api/1/kombucha/{}/entry/{}",
+                    kombucha_id, entry_id
+                );
+                let patch = patch.clone();
+                self.get_json(&url, move |saved: Entry| {
+                    // The date is only shown when it's all that changed
+                    let (saved_text, mine) = match (&patch.content, patch.added)
+                    {
+                        (Some(content), _) => (saved.content, content.clone()),
+                        (None, Some(added)) => (
+                            saved.added.format("%Y-%m-%d %H:%M").to_string(),
+                            added.format("%Y-%m-%d %H:%M").to_string(),
+                        ),
+                        (None, None) => (saved.content, String::new()),
+                    };
+
+                    Msg::LoadConflict(Conflict {
+                        edit: edit.clone(),
+                        saved_version: saved.version,
+                        saved: saved_text,
+                        mine,
+                    })
+                });
+            }
+        }
+    }
+
+    fn update_fermentation(
+        &mut self,
+        id: KombuchaId,
+        fermentation: &Fermentation,
+    ) {
+        let url =
+            format!("/api/1/kombucha/{}/fermentation/{}", id, fermentation.id);
+        let req = Request::put(url)
+            .header("content-type", "application/json")
+            .body(Json(fermentation))
+            .unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn next_fermentation(&mut self, id: KombuchaId) {
+        let url = format!("/api/1/kombucha/{}/fermentation/advance", id);
+        let advance = AdvanceFermentation::default();
+        let req = Request::post(url)
             .header("content-type", "application/json")
-            .body(Json(&kombucha))
+            .body(Json(&advance))
             .unwrap();
 
         let task = self
             .fetch_service
-            .fetch(req, self.link.callback(reload_or_show_error))
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
             .unwrap();
 
         self.jobs.push_front(Box::new(task));
     }
 
+    fn upload_images(
+        &mut self,
+        id: KombuchaId,
+        entry_id: Option<EntryId>,
+        files: Vec<File>,
+    ) {
+        let url = format!("/api/1/kombucha/{}/image", id);
+
+        for file in files {
+            let callback = self.link.callback(
+                move |result: Result<(), Error>| match result {
+                    Ok(()) => Msg::Refresh(id),
+                    Err(err) => Msg::ShowError(err),
+                },
+            );
+            let url = url.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                callback.emit(upload_image(url, entry_id, file).await);
+            });
+        }
+    }
+
+    fn delete_image(&mut self, id: KombuchaId, image_id: ImageId) {
+        let url = format!("/api/1/kombucha/{}/image/{}", id, image_id);
+        let req = Request::delete(url).body(Nothing).unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn new_measurement(
+        &mut self,
+        id: KombuchaId,
+        measurement: &NewMeasurement,
+    ) {
+        let url = format!("/api/1/kombucha/{}/measurement", id);
+        let req = Request::post(url)
+            .header("content-type", "application/json")
+            .body(Json(measurement))
+            .unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn delete_measurement(
+        &mut self,
+        id: KombuchaId,
+        measurement_id: MeasurementId,
+    ) {
+        let url =
+            format!("/api/1/kombucha/{}/measurement/{}", id, measurement_id);
+        let req = Request::delete(url).body(Nothing).unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn save_recipe(&mut self, id: KombuchaId, recipe: &Recipe) {
+        let url = format!("/api/1/kombucha/{}/recipe", id);
+        let req = Request::put(url)
+            .header("content-type", "application/json")
+            .body(Json(recipe))
+            .unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn delete_recipe(&mut self, id: KombuchaId) {
+        let url = format!("/api/1/kombucha/{}/recipe", id);
+        let req = Request::delete(url).body(Nothing).unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn save_template(&mut self, template: &NewRecipeTemplate) {
+        let req = Request::post("/api/1/template")
+            .header("content-type", "application/json")
+            .body(Json(template))
+            .unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(
+                req,
+                self.link
+                    .callback(|response| match body_or_error(response) {
+                        Ok(_) => Msg::ReloadTemplates,
+                        Err(err) => Msg::ShowError(err),
+                    }),
+            )
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn get_templates(&mut self) {
+        self.get_json("/api/1/template", Msg::LoadTemplates);
+    }
+
     fn delete_kombucha(&mut self, kombucha: KombuchaId) {
         let url = format!("/api/1/kombucha/{}", kombucha);
         let req = Request::delete(url)
@@ -129,7 +692,32 @@ impl App {
 
         let task = self
             .fetch_service
-            .fetch(req, self.link.callback(reload_or_show_error))
+            .fetch(
+                req,
+                self.link.callback(deleted_or_show_error(Deleted::Kombucha(
+                    kombucha,
+                ))),
+            )
+            .unwrap();
+
+        self.jobs.push_front(Box::new(task));
+    }
+
+    fn restore(&mut self, deleted: Deleted) {
+        let url = match deleted {
+            Deleted::Kombucha(id) => format!("/api/1/kombucha/{}/restore", id),
+            Deleted::Entry(id, entry_id) => {
+                format!("/api/1/kombucha/{}/entry/{}/restore", id, entry_id)
+            }
+        };
+        let id = match deleted {
+            Deleted::Kombucha(id) | Deleted::Entry(id, _) => id,
+        };
+        let req = Request::post(url).body(Nothing).unwrap();
+
+        let task = self
+            .fetch_service
+            .fetch(req, self.link.callback(refresh_or_show_error(id)))
             .unwrap();
 
         self.jobs.push_front(Box::new(task));
@@ -141,49 +729,118 @@ impl Component for App {
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
+        // Registered before any child component, so overlays can stop
+        // shortcuts from reaching the components underneath
+        let key_listener = web_sys::window().map(|window| {
+            KeyboardService::register_key_down(
+                &window,
+                link.callback(Msg::KeyDown),
+            )
+        });
+
         let mut app = Self {
             link,
-            entries: Rc::new(Mutex::new(Vec::new())),
+            _key_listener: key_listener,
+            kombuchas: Rc::new(Mutex::new(Vec::new())),
+            search_hits: Rc::new(Mutex::new(Vec::new())),
+            templates: Rc::new(Mutex::new(Vec::new())),
             ..Self::default()
         };
 
-        app.get_all_kombuchas();
+        app.get_kombuchas(None);
+        app.get_templates();
 
         app
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         self.jobs = self.jobs.drain(..).filter(|job| job.is_active()).collect();
-        let mut entries = self.entries.lock().unwrap();
 
         match msg {
-            Msg::Reload => {
-                drop(entries);
-                self.get_all_kombuchas();
+            Msg::Refresh(id) => {
+                self.refresh(id);
+                return false;
             }
             Msg::Nop => return false,
-            Msg::LoadKombuchas(kombuchas) => {
-                *entries = kombuchas;
+            Msg::LoadMore => {
+                // Taken, so that clicking again before the page arrives
+                // doesn't load it twice
+                if let Some(cursor) = self.next_cursor.take() {
+                    self.get_kombuchas(Some(cursor));
+                }
+            }
+            Msg::LoadKombuchas(generation, cursor, page) => {
+                if generation != self.list_generation {
+                    return false;
+                }
+
+                let mut kombuchas = self.kombuchas.lock().unwrap();
+                match cursor {
+                    None => *kombuchas = page.kombuchas,
+                    Some(_) => {
+                        // Rows added since the list was loaded may be on
+                        // this page too
+                        let new: Vec<_> = page
+                            .kombuchas
+                            .into_iter()
+                            .filter(|summary| {
+                                kombuchas.iter().all(|row| row.id != summary.id)
+                            })
+                            .collect();
+                        kombuchas.extend(new);
+                    }
+                }
+                self.next_cursor = page.next_cursor;
+            }
+            Msg::LoadKombucha(kombucha) => {
+                self.update_summary(kombucha.summary());
+
+                // The selection may have changed while it was loading
+                if self.selected == Some(kombucha.id) {
+                    self.kombucha = Some(kombucha);
+                }
+            }
+            Msg::LoadSeries(id, series) => {
+                if self.selected != Some(id) {
+                    return false;
+                }
+                self.series = series;
+            }
+            Msg::Search(search) => {
+                self.search = search;
+                if self.search.trim().is_empty() {
+                    self.search_hits.lock().unwrap().clear();
+                } else {
+                    self.search();
+                }
+            }
+            Msg::LoadSearchHits(search, hits) => {
+                // Answers to searches typed over since are dropped
+                if search != self.search {
+                    return false;
+                }
+                *self.search_hits.lock().unwrap() = hits;
+            }
+            Msg::ReloadTemplates => {
+                self.get_templates();
+                return false;
+            }
+            Msg::LoadTemplates(templates) => {
+                *self.templates.lock().unwrap() = templates;
+            }
+            Msg::Select(id) => {
+                self.selected = id;
+                self.kombucha = None;
+                self.series = Vec::new();
+                self.get_selected_kombucha();
             }
-            Msg::Select(idx) => self.selected_idx = idx,
             Msg::ShowError(err) => {
                 log::error!("Error: {}", err);
                 self.error = Some(err.to_string());
             }
             Msg::CloseError => self.error = None,
-            Msg::AddKombucha => {
-                drop(entries);
-                self.add_kombucha();
-            }
-            Msg::UpdateKombucha(idx, new_kombucha) => {
-                if let Some(kombucha) = entries.get_mut(idx) {
-                    *kombucha = new_kombucha.clone();
-                }
-
-                drop(entries);
-
-                self.update_kombucha(&new_kombucha);
-            }
+            Msg::AddKombucha(new_kombucha) => self.add_kombucha(&new_kombucha),
+            Msg::Edit(edit) => self.patch(edit),
 
             Msg::Unimplemented => {
                 self.error = Some("Unimplemented :(".to_string());
@@ -192,8 +849,12 @@ impl Component for App {
                 self.delete_kombucha_modal = Some(id);
             }
             Msg::ConfirmDeleteKombuchaModal => {
-                drop(entries);
                 if let Some(id) = self.delete_kombucha_modal.take() {
+                    if self.selected == Some(id) {
+                        self.selected = None;
+                        self.kombucha = None;
+                        self.series = Vec::new();
+                    }
                     self.delete_kombucha(id);
                 }
             }
@@ -201,33 +862,110 @@ impl Component for App {
                 self.delete_kombucha_modal = None;
             }
             Msg::DeleteEntry(kombucha_id, entry_id) => {
-                drop(entries);
                 self.delete_entry(kombucha_id, entry_id);
             }
-            Msg::NewEntry(kombucha_id) => {
-                drop(entries);
-                self.new_entry(kombucha_id);
+            Msg::Deleted(deleted) => {
+                self.undo = Some(deleted);
+                self.undo_task = Some(self.timeout_service.spawn(
+                    UNDO_TIMEOUT,
+                    self.link.callback(|_| Msg::CloseUndo),
+                ));
+
+                match deleted {
+                    Deleted::Kombucha(id) => {
+                        self.kombuchas
+                            .lock()
+                            .unwrap()
+                            .retain(|row| row.id != id);
+                        self.search();
+                    }
+                    Deleted::Entry(id, _) => self.refresh(id),
+                }
+            }
+            Msg::Undo => {
+                self.undo_task = None;
+                if let Some(deleted) = self.undo.take() {
+                    if let Deleted::Kombucha(id) = deleted {
+                        self.selected = Some(id);
+                    }
+                    self.restore(deleted);
+                }
+            }
+            Msg::CloseUndo => {
+                self.undo = None;
+                self.undo_task = None;
+            }
+            Msg::NewEntry(kombucha_id, patch) => {
+                self.new_entry(kombucha_id, patch);
+            }
+            Msg::Conflict(edit) => self.get_conflict(edit),
+            Msg::LoadConflict(conflict) => self.conflict = Some(conflict),
+            Msg::KeepMine => {
+                // Only the fields of the edit are sent again, whatever else
+                // was saved meanwhile is kept
+                if let Some(conflict) = self.conflict.take() {
+                    self.patch(conflict.edit.rebase(conflict.saved_version));
+                }
+            }
+            Msg::KeepSaved => {
+                if let Some(conflict) = self.conflict.take() {
+                    self.refresh(conflict.edit.kombucha_id());
+                }
+            }
+            Msg::UpdateFermentation(kombucha_id, fermentation) => {
+                self.update_fermentation(kombucha_id, &fermentation);
             }
+            Msg::NextFermentation(kombucha_id) => {
+                self.next_fermentation(kombucha_id);
+            }
+            Msg::UploadImages(kombucha_id, entry_id, files) => {
+                self.upload_images(kombucha_id, entry_id, files);
+            }
+            Msg::DeleteImage(kombucha_id, image_id) => {
+                self.delete_image(kombucha_id, image_id);
+            }
+            Msg::NewMeasurement(kombucha_id, measurement) => {
+                self.new_measurement(kombucha_id, &measurement);
+            }
+            Msg::DeleteMeasurement(kombucha_id, measurement_id) => {
+                self.delete_measurement(kombucha_id, measurement_id);
+            }
+            Msg::SaveRecipe(kombucha_id, recipe) => {
+                self.save_recipe(kombucha_id, &recipe);
+            }
+            Msg::DeleteRecipe(kombucha_id) => {
+                self.delete_recipe(kombucha_id);
+            }
+            Msg::SaveTemplate(template) => self.save_template(&template),
+            Msg::KeyDown(e) => {
+                return self.handle_key_down(e);
+            }
+            Msg::CloseShortcuts => self.show_shortcuts = false,
         }
         true
     }
 
     fn view(&self) -> Html {
-        let entries = self.entries.lock().unwrap();
-
-        let inner = if let Some(selected_idx) = self.selected_idx {
-            if let Some(kombucha) = entries.get(selected_idx) {
-                html! {
-                    <KombuchaView
-                        kombucha=kombucha
-                        on_change=self.link.callback(move |kombucha| Msg::UpdateKombucha(selected_idx, kombucha))
-                        on_delete_entry=self.link.callback(|(kombucha_id, entry_id)| Msg::DeleteEntry(kombucha_id, entry_id))
-                        on_new_entry=self.link.callback(|kombucha_id| Msg::NewEntry(kombucha_id))
-                        on_delete=self.link.callback(|kombucha_id| Msg::OpenDeleteKombuchaModal(kombucha_id))
-                    />
-                }
-            } else {
-                html! {}
+        let inner = if let Some(kombucha) = &self.kombucha {
+            html! {
+                <KombuchaView
+                    kombucha=kombucha
+                    series=self.series.clone()
+                    on_edit=self.link.callback(Msg::Edit)
+                    on_delete_entry=self.link.callback(|(kombucha_id, entry_id)| Msg::DeleteEntry(kombucha_id, entry_id))
+                    on_new_entry=self.link.callback(|(kombucha_id, patch)| Msg::NewEntry(kombucha_id, patch))
+                    on_delete=self.link.callback(|kombucha_id| Msg::OpenDeleteKombuchaModal(kombucha_id))
+                    on_update_fermentation=self.link.callback(|(kombucha_id, fermentation)| Msg::UpdateFermentation(kombucha_id, fermentation))
+                    on_next_fermentation=self.link.callback(|kombucha_id| Msg::NextFermentation(kombucha_id))
+                    on_upload_images=self.link.callback(|(kombucha_id, entry_id, files)| Msg::UploadImages(kombucha_id, entry_id, files))
+                    on_delete_image=self.link.callback(|(kombucha_id, image_id)| Msg::DeleteImage(kombucha_id, image_id))
+                    on_new_measurement=self.link.callback(|(kombucha_id, measurement)| Msg::NewMeasurement(kombucha_id, measurement))
+                    on_delete_measurement=self.link.callback(|(kombucha_id, measurement_id)| Msg::DeleteMeasurement(kombucha_id, measurement_id))
+                    on_save_recipe=self.link.callback(|(kombucha_id, recipe)| Msg::SaveRecipe(kombucha_id, recipe))
+                    on_delete_recipe=self.link.callback(Msg::DeleteRecipe)
+                    on_brew_again=self.link.callback(|kombucha_id| Msg::AddKombucha(NewKombucha { clone_from: Some(kombucha_id), ..NewKombucha::default() }))
+                    on_save_template=self.link.callback(Msg::SaveTemplate)
+                />
             }
         } else {
             html! {}
@@ -275,9 +1013,15 @@ impl Component for App {
                 <div class="columns">
                     <div class="column is-one-third">
                         <KombuchaPanel
-                            kombuchas=self.entries.clone()
-                            on_select=self.link.callback(|idx| Msg::Select(idx))
-                            on_add=self.link.callback(|_| Msg::AddKombucha)
+                            kombuchas=self.kombuchas.clone()
+                            search_hits=self.search_hits.clone()
+                            on_select=self.link.callback(|id| Msg::Select(id))
+                            templates=self.templates.clone()
+                            has_more=self.next_cursor.is_some()
+                            on_load_more=self.link.callback(|_| Msg::LoadMore)
+                            on_add=self.link.callback(|_| Msg::AddKombucha(NewKombucha::default()))
+                            on_add_from_template=self.link.callback(|template_id| Msg::AddKombucha(NewKombucha { template_id: Some(template_id), ..NewKombucha::default() }))
+                            on_search=self.link.callback(Msg::Search)
                         />
                     </div>
                     <div class="column is-two-thirds">
@@ -286,6 +1030,181 @@ impl Component for App {
                 </div>
                 { error }
                 { modal }
+                { self.view_conflict() }
+                { self.view_undo() }
+                { self.view_shortcuts() }
+            </div>
+        }
+    }
+}
+
+impl App {
+    fn has_overlay(&self) -> bool {
+        self.show_shortcuts
+            || self.error.is_some()
+            || self.delete_kombucha_modal.is_some()
+            || self.conflict.is_some()
+    }
+
+    fn handle_key_down(&mut self, e: KeyboardEvent) -> ShouldRender {
+        if !self.has_overlay() {
+            if Shortcut::from_event(&e) == Some(Shortcut::Help) {
+                e.prevent_default();
+                self.show_shortcuts = true;
+                return true;
+            }
+
+            return false;
+        }
+
+        // Nothing underneath an overlay should react to the keyboard
+        e.stop_immediate_propagation();
+
+        if Shortcut::from_event(&e) == Some(Shortcut::Cancel) {
+            if self.show_shortcuts {
+                self.show_shortcuts = false;
+            } else if self.error.is_some() {
+                self.error = None;
+            } else if self.conflict.is_some() {
+                // One of the versions is lost either way, so it has to be
+                // picked explicitly
+                return false;
+            } else {
+                self.delete_kombucha_modal = None;
+            }
+
+            return true;
+        }
+
+        false
+    }
+
+    fn view_conflict(&self) -> Html {
+        let (message, saved, mine) = match &self.conflict {
+            Some(Conflict {
+                edit: Edit::Kombucha { .. },
+                saved,
+                mine,
+                ..
+            }) => (
+                "This kombucha was changed somewhere else while you were editing it.",
+                saved.clone(),
+                mine.clone(),
+            ),
+            Some(Conflict {
+                edit: Edit::Entry { .. },
+                saved,
+                mine,
+                ..
+            }) => (
+                "This entry was changed somewhere else while you were editing it.",
+                saved.clone(),
+                mine.clone(),
+            ),
+            None => return html! {},
+        };
+
+        html! {
+            <div class="modal is-active">
+                <div class="modal-background"></div>
+                <div class="modal-card">
+                    <header class="modal-card-head">
+                        <p class="modal-card-title">{"Edit conflict"}</p>
+                    </header>
+                    <section class="modal-card-body">
+                        <p>{ message }</p>
+                        <div class="columns">
+                            <div class="column">
+                                <p class="heading">{"Saved"}</p>
+                                <p class="kombucha-conflict-text">{ saved }</p>
+                            </div>
+                            <div class="column">
+                                <p class="heading">{"Your edit"}</p>
+                                <p class="kombucha-conflict-text">{ mine }</p>
+                            </div>
+                        </div>
+                    </section>
+                    <footer class="modal-card-foot">
+                        <button
+                            class="button is-primary"
+                            onclick=self.link.callback(|_| Msg::KeepMine)
+                        >
+                            {"Keep my edit"}
+                        </button>
+                        <button
+                            class="button"
+                            onclick=self.link.callback(|_| Msg::KeepSaved)
+                        >
+                            {"Use the saved version"}
+                        </button>
+                    </footer>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_undo(&self) -> Html {
+        let message = match self.undo {
+            Some(Deleted::Kombucha(_)) => "Kombucha moved to the trash",
+            Some(Deleted::Entry(..)) => "Entry moved to the trash",
+            None => return html! {},
+        };
+
+        html! {
+            <div class="notification is-dark kombucha-undo">
+                <button
+                    class="delete"
+                    aria-label="close"
+                    onclick=self.link.callback(|_| Msg::CloseUndo)
+                ></button>
+                <span>{ message }</span>
+                <button
+                    class="button is-small is-light"
+                    onclick=self.link.callback(|_| Msg::Undo)
+                >
+                    {"Undo"}
+                </button>
+            </div>
+        }
+    }
+
+    fn view_shortcuts(&self) -> Html {
+        if !self.show_shortcuts {
+            return html! {};
+        }
+
+        let rows = SHORTCUTS.iter().map(|(key, description)| {
+            html! {
+                <tr>
+                    <td><kbd>{ key }</kbd></td>
+                    <td>{ description }</td>
+                </tr>
+            }
+        });
+
+        html! {
+            <div class="modal is-active">
+                <div
+                    class="modal-background"
+                    onclick=self.link.callback(|_| Msg::CloseShortcuts)
+                ></div>
+                <div class="modal-card">
+                    <header class="modal-card-head">
+                        <p class="modal-card-title">{"Keyboard shortcuts"}</p>
+                        <button
+                            class="delete"
+                            aria-label="close"
+                            onclick=self.link.callback(|_| Msg::CloseShortcuts)
+                        ></button>
+                    </header>
+                    <section class="modal-card-body">
+                        <table class="table is-fullwidth">
+                            <tbody>
+                                { for rows }
+                            </tbody>
+                        </table>
+                    </section>
+                </div>
             </div>
         }
     }
@@ -1,31 +1,69 @@
-use crate::data::Kombucha;
-use std::{rc::Rc, sync::Mutex};
+use crate::data::{
+    FermentationStatus, KombuchaId, KombuchaSummary, Milestone, RecipeTemplate,
+    SearchHit, TemplateId,
+};
+use crate::keyboard::Shortcut;
+use chrono::{DateTime, Utc};
+use std::{rc::Rc, sync::Mutex, time::Duration};
+use web_sys::{HtmlInputElement, KeyboardEvent};
 use yew::prelude::*;
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
+use yew::services::{timeout::TimeoutTask, TimeoutService};
+
+/// How long typing has to pause before the search is sent
+const SEARCH_DELAY: Duration = Duration::from_millis(300);
 
 pub enum Msg {
     Nop,
     AddKombucha,
+    LoadMore,
+    AddFromTemplate(TemplateId),
     UpdateSearchString(String),
-    Select(usize),
+    Search,
+    Select(KombuchaId),
+    KeyDown(KeyboardEvent),
 }
 
 #[derive(Clone, Properties)]
 pub struct Props {
-    pub kombuchas: Rc<Mutex<Vec<Kombucha>>>,
+    pub kombuchas: Rc<Mutex<Vec<KombuchaSummary>>>,
+    /// Results of the last search sent through `on_search`
+    pub search_hits: Rc<Mutex<Vec<SearchHit>>>,
+    /// Templates new kombuchas can be brewed from
+    pub templates: Rc<Mutex<Vec<RecipeTemplate>>>,
+    /// Whether there are kombuchas after the ones loaded so far
+    #[prop_or_default]
+    pub has_more: bool,
+    #[prop_or_default]
+    pub on_load_more: Callback<()>,
     #[prop_or_default]
-    pub on_select: Callback<Option<usize>>,
+    pub on_select: Callback<Option<KombuchaId>>,
     #[prop_or_default]
     pub on_add: Callback<()>,
+    #[prop_or_default]
+    pub on_add_from_template: Callback<TemplateId>,
+    #[prop_or_default]
+    pub on_search: Callback<String>,
 }
 
 #[derive(Default)]
 pub struct KombuchaPanel {
     link: ComponentLink<Self>,
+    _key_listener: Option<KeyListenerHandle>,
+    timeout_service: TimeoutService,
+    search_task: Option<TimeoutTask>,
+    search_input: NodeRef,
     search_string: String,
-    selected_kombucha: Option<usize>,
-    kombuchas: Rc<Mutex<Vec<Kombucha>>>,
-    on_select: Callback<Option<usize>>,
+    selected_kombucha: Option<KombuchaId>,
+    kombuchas: Rc<Mutex<Vec<KombuchaSummary>>>,
+    search_hits: Rc<Mutex<Vec<SearchHit>>>,
+    templates: Rc<Mutex<Vec<RecipeTemplate>>>,
+    has_more: bool,
+    on_load_more: Callback<()>,
+    on_select: Callback<Option<KombuchaId>>,
     on_add: Callback<()>,
+    on_add_from_template: Callback<TemplateId>,
+    on_search: Callback<String>,
 }
 
 impl Component for KombuchaPanel {
@@ -35,43 +73,91 @@ impl Component for KombuchaPanel {
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         let Props {
             kombuchas,
+            search_hits,
+            templates,
+            has_more,
+            on_load_more,
             on_select,
             on_add,
+            on_add_from_template,
+            on_search,
         } = props;
+        let key_listener = web_sys::window().map(|window| {
+            KeyboardService::register_key_down(
+                &window,
+                link.callback(Msg::KeyDown),
+            )
+        });
+
         Self {
             link,
+            _key_listener: key_listener,
             kombuchas,
+            search_hits,
+            templates,
+            has_more,
+            on_load_more,
             on_select,
             on_add,
+            on_add_from_template,
+            on_search,
             ..Default::default()
         }
     }
 
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.kombuchas = props.kombuchas;
+        self.search_hits = props.search_hits;
+        self.templates = props.templates;
+        self.has_more = props.has_more;
+        self.on_load_more = props.on_load_more;
+        self.on_select = props.on_select;
+        self.on_add = props.on_add;
+        self.on_add_from_template = props.on_add_from_template;
+        self.on_search = props.on_search;
+
+        true
+    }
+
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
             Msg::Nop => (),
             Msg::UpdateSearchString(s) => {
                 self.search_string = s;
+                self.search_task =
+                    Some(self.timeout_service.spawn(
+                        SEARCH_DELAY,
+                        self.link.callback(|_| Msg::Search),
+                    ));
+            }
+            Msg::Search => {
+                self.search_task = None;
+                self.on_search.emit(self.search_string.clone());
+                return false;
             }
             Msg::AddKombucha => self.on_add.emit(()),
-            Msg::Select(idx) => {
-                let select_idx = match self.selected_kombucha {
+            Msg::LoadMore => self.on_load_more.emit(()),
+            Msg::AddFromTemplate(id) => self.on_add_from_template.emit(id),
+            Msg::Select(id) => {
+                #[allow(clippy::collapsible_match)]
+                let select_id = match self.selected_kombucha {
                     None => {
-                        self.selected_kombucha = Some(idx);
-                        Some(idx)
+                        self.selected_kombucha = Some(id);
+                        Some(id)
                     }
-                    Some(old_idx) => {
-                        if old_idx == idx {
+                    Some(old_id) => {
+                        if old_id == id {
                             self.selected_kombucha = None;
                             None
                         } else {
-                            self.selected_kombucha = Some(idx);
-                            Some(idx)
+                            self.selected_kombucha = Some(id);
+                            Some(id)
                         }
                     }
                 };
-                self.on_select.emit(select_idx);
+                self.on_select.emit(select_id);
             }
+            Msg::KeyDown(e) => return self.handle_key_down(e),
         }
 
         true
@@ -88,6 +174,7 @@ impl Component for KombuchaPanel {
                         <input
                             class="input"
                             type="text"
+                            ref=self.search_input.clone()
                             value=self.search_string
                             placeholder="Search"
                             oninput=self.link.callback(|e: InputData| Msg::UpdateSearchString(e.value))
@@ -100,6 +187,7 @@ impl Component for KombuchaPanel {
                 <div class="">
                     { self.view_kombucha_entries() }
                 </div>
+                { self.view_load_more() }
                 <div class="panel-block">
                     <button
                         class="button is-link is-outlined is-fullwidth"
@@ -108,47 +196,242 @@ impl Component for KombuchaPanel {
                         <i class="fa fa-plus fa-lg" aria-hidden="true"></i>
                     </button>
                 </div>
+                { self.view_templates() }
             </nav>
         }
     }
 }
 
 impl KombuchaPanel {
+    fn is_searching(&self) -> bool {
+        !self.search_string.trim().is_empty()
+    }
+
+    /// Kombuchas in the order they're shown, those with search hits while
+    /// searching
+    fn visible_kombuchas(&self) -> Vec<KombuchaId> {
+        if !self.is_searching() {
+            let kombuchas = self.kombuchas.lock().unwrap();
+            return kombuchas.iter().map(|kombucha| kombucha.id).collect();
+        }
+
+        let hits = self.search_hits.lock().unwrap();
+        let mut visible: Vec<KombuchaId> = Vec::new();
+        for hit in hits.iter() {
+            if !visible.contains(&hit.kombucha_id) {
+                visible.push(hit.kombucha_id);
+            }
+        }
+
+        visible
+    }
+
+    fn handle_key_down(&mut self, e: KeyboardEvent) -> ShouldRender {
+        let shortcut = match Shortcut::from_event(&e) {
+            Some(shortcut) => shortcut,
+            None => return false,
+        };
+
+        match shortcut {
+            Shortcut::Next | Shortcut::Previous => {
+                e.prevent_default();
+
+                let visible = self.visible_kombuchas();
+                let position = self.selected_kombucha.and_then(|selected| {
+                    visible.iter().position(|&id| id == selected)
+                });
+
+                let next = match (shortcut, position) {
+                    (Shortcut::Previous, Some(position)) => position
+                        .checked_sub(1)
+                        .and_then(|position| visible.get(position)),
+                    (Shortcut::Previous, None) => visible.last(),
+                    (_, Some(position)) => visible.get(position + 1),
+                    (_, None) => visible.first(),
+                };
+
+                if let Some(&id) = next {
+                    self.selected_kombucha = Some(id);
+                    self.on_select.emit(Some(id));
+                    return true;
+                }
+
+                false
+            }
+            Shortcut::Search => {
+                e.prevent_default();
+
+                if let Some(input) =
+                    self.search_input.cast::<HtmlInputElement>()
+                {
+                    let _ = input.focus();
+                }
+
+                false
+            }
+            _ => false,
+        }
+    }
+
+    fn view_load_more(&self) -> Html {
+        // Search hits aren't paged
+        if !self.has_more || self.is_searching() {
+            return html! {};
+        }
+
+        html! {
+            <div class="panel-block">
+                <button
+                    class="button is-fullwidth"
+                    onclick=self.link.callback(|_| Msg::LoadMore)
+                >
+                    {"Load more"}
+                </button>
+            </div>
+        }
+    }
+
+    fn view_templates(&self) -> Html {
+        let templates = self.templates.lock().unwrap();
+        if templates.is_empty() {
+            return html! {};
+        }
+
+        let options = templates.iter().map(|template| {
+            html! {
+                <option value=template.id.to_string()>{ &template.name }</option>
+            }
+        });
+
+        html! {
+            <div class="panel-block">
+                <div class="select is-fullwidth">
+                    <select
+                        onchange=self.link.callback(|e: ChangeData| match e {
+                            ChangeData::Select(select) => {
+                                let id = select.value().parse().ok();
+                                // Back to the prompt, so the same template
+                                // can be picked again
+                                select.set_selected_index(0);
+                                id.map(Msg::AddFromTemplate).unwrap_or(Msg::Nop)
+                            }
+                            x => { log::error!("Invalid change data, expected select, found {:?}", x); Msg::Nop }
+                        })
+                    >
+                        <option value="" selected=true>{"New from template"}</option>
+                        { for options }
+                    </select>
+                </div>
+            </div>
+        }
+    }
+
     fn view_kombucha_entries(&self) -> Html {
+        if self.is_searching() {
+            let hits = self.search_hits.lock().unwrap();
+            return hits.iter().map(|hit| self.view_search_hit(hit)).collect();
+        }
+
         let kombuchas = self.kombuchas.lock().unwrap();
+        let now = Utc::now();
 
-        let kombuchas = kombuchas.iter().enumerate();
-
-        if self.search_string.is_empty() {
-            kombuchas
-                .map(|(idx, kombucha)| self.view_kombucha_entry(idx, kombucha))
-                .collect()
-        } else {
-            kombuchas
-                .filter(|(_, kombucha)| {
-                    kombucha.name.contains(&self.search_string)
-                })
-                .map(|(idx, kombucha)| self.view_kombucha_entry(idx, kombucha))
-                .collect()
-        }
+        kombuchas
+            .iter()
+            .map(|kombucha| self.view_kombucha_entry(kombucha, now))
+            .collect()
     }
 
-    fn view_kombucha_entry(&self, idx: usize, kombucha: &Kombucha) -> Html {
+    fn view_search_hit(&self, hit: &SearchHit) -> Html {
+        let id = hit.kombucha_id;
         let class = match self.selected_kombucha {
-            Some(selected_idx) if selected_idx == idx => {
-                "panel-block is-active"
+            Some(selected_id) if selected_id == id => "panel-block is-active",
+            _ => "panel-block",
+        };
+        let icon = match hit.entry_id {
+            Some(_) => "fas fa-sticky-note",
+            None => "fas fa-coffee",
+        };
+
+        let snippet = hit.snippet.iter().map(|part| {
+            if part.highlighted {
+                html! { <mark>{ &part.text }</mark> }
+            } else {
+                html! { { &part.text } }
             }
+        });
+
+        // A matched name is the snippet itself
+        let name = match hit.entry_id {
+            Some(_) => html! { <strong>{ &hit.kombucha_name }</strong> },
+            None => html! {},
+        };
+
+        html! {
+            <a
+                class={ class }
+                onclick=self.link.callback(move |_| Msg::Select(id))
+            >
+                <span class="panel-icon"><i class=icon aria-hidden="true"></i></span>
+                <span class="kombucha-search-hit">
+                    { name }
+                    <span class="kombucha-search-snippet">{ for snippet }</span>
+                </span>
+            </a>
+        }
+    }
+
+    fn view_kombucha_entry(
+        &self,
+        kombucha: &KombuchaSummary,
+        now: DateTime<Utc>,
+    ) -> Html {
+        let id = kombucha.id;
+        let class = match self.selected_kombucha {
+            Some(selected_id) if selected_id == id => "panel-block is-active",
             _ => "panel-block",
         };
 
         html! {
             <a
                 class={ class }
-                onclick=self.link.callback(move |_| Msg::Select(idx))
+                onclick=self.link.callback(move |_| Msg::Select(id))
             >
                 <span class="panel-icon"><i class="fas fa-coffee" aria-hidden="true"></i></span>
                 { &kombucha.name }
+                { kombucha.next_milestone.map(|milestone| Self::view_milestone(&milestone, now)).unwrap_or_default() }
             </a>
         }
     }
+
+    fn view_milestone(milestone: &Milestone, now: DateTime<Utc>) -> Html {
+        let days_left = milestone.days_left(now);
+
+        let (class, text) = match days_left {
+            0 => ("tag is-warning", "ends today".to_string()),
+            1 => ("tag is-warning", "ends in 1 day".to_string()),
+            -1 => ("tag is-danger", "overdue by 1 day".to_string()),
+            days if days < 0 => {
+                ("tag is-danger", format!("overdue by {} days", -days))
+            }
+            days => ("tag is-info", format!("ends in {} days", days)),
+        };
+
+        html! {
+            <span
+                class=format!("{} kombucha-milestone", class)
+                title=milestone.date.format("%Y %B %d").to_string()
+            >
+                { format!("{} {}", stage_label(milestone.status), text) }
+            </span>
+        }
+    }
+}
+
+/// Brewers' shorthand for the fermentation stages
+fn stage_label(status: FermentationStatus) -> String {
+    match status {
+        FermentationStatus::Primary => "F1".to_string(),
+        FermentationStatus::Secondary => "F2".to_string(),
+        status => status.to_string(),
+    }
 }
@@ -1,32 +1,162 @@
-use data_types::{Entry, EntryId, Kombucha, KombuchaId};
+use crate::components::{MeasurementChart, RecipeCalculator};
+use crate::keyboard::Shortcut;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use data_types::{
+    Entry, EntryId, EntryPatch, Fermentation, FermentationStatus, Image,
+    ImageId, Kombucha, KombuchaId, KombuchaPatch, Measurement, MeasurementId,
+    MeasurementKind, MeasurementSeries, NewMeasurement, NewRecipeTemplate,
+    PlannedStage, Recipe, RecipeFlavouring, RecipeTea,
+};
+use web_sys::{DragEvent, File, FileList, HtmlInputElement, KeyboardEvent};
 use yew::prelude::*;
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
+
+/// Whether the entry was only added here and isn't saved yet
+fn is_new(entry: &Entry) -> bool {
+    entry.id == Entry::default().id
+}
 
 pub enum Msg {
     Nop,
     UpdateName(String),
+    SelectEntry(EntryId),
     StartEditingSection(usize),
     StopEditingSection,
     UpdateSectionText(usize, String),
+    UpdateSectionAdded(usize, Option<DateTime<Utc>>),
     NewSection,
     DeleteSection(usize),
     DeleteMe,
     StartEditingName,
     StopEditingName,
+    SetEstimatedEnd(usize, Option<DateTime<Utc>>),
+    EndFermentation(usize),
+    NextFermentation,
+    UploadImages(Option<EntryId>, Vec<File>),
+    DeleteImage(ImageId),
+    UpdateMeasurementText(String),
+    LogMeasurement,
+    DeleteMeasurement(MeasurementId),
+    StartEditingRecipe,
+    UpdateRecipe(RecipeField),
+    AddRecipeTea,
+    RemoveRecipeTea(usize),
+    RemoveRecipeStage(usize),
+    AddRecipeFlavouring,
+    RemoveRecipeFlavouring(usize),
+    SaveRecipe,
+    CancelRecipe,
+    DeleteRecipe,
+    BrewAgain,
+    SaveAsTemplate,
+    ToggleCalculator,
+    ApplyScaledRecipe(Recipe),
+    KeyDown(KeyboardEvent),
+}
+
+/// A field of the edited recipe and its new value, amounts that don't parse
+/// are taken as nothing
+pub enum RecipeField {
+    TeaVariety(usize, String),
+    TeaGrams(usize, String),
+    SugarType(String),
+    SugarGrams(String),
+    WaterMl(String),
+    StarterMl(String),
+    Vessel(String),
+    VesselMl(String),
+    StageDays(usize, String),
+    FlavouringIngredient(usize, String),
+    FlavouringAmount(usize, String),
+    FlavouringStage(usize, Option<FermentationStatus>),
+}
+
+/// A change to the kombucha or one of its entries, `version` is the one it's
+/// based on
+#[derive(Clone)]
+pub enum Edit {
+    Kombucha {
+        id: KombuchaId,
+        version: i32,
+        patch: KombuchaPatch,
+    },
+    Entry {
+        kombucha_id: KombuchaId,
+        entry_id: EntryId,
+        version: i32,
+        patch: EntryPatch,
+    },
+}
+
+impl Edit {
+    pub fn kombucha_id(&self) -> KombuchaId {
+        match self {
+            Edit::Kombucha { id, .. } => *id,
+            Edit::Entry { kombucha_id, .. } => *kombucha_id,
+        }
+    }
+
+    /// The same change based on another version
+    pub fn rebase(self, version: i32) -> Self {
+        match self {
+            Edit::Kombucha { id, patch, .. } => {
+                Edit::Kombucha { id, version, patch }
+            }
+            Edit::Entry {
+                kombucha_id,
+                entry_id,
+                patch,
+                ..
+            } => Edit::Entry {
+                kombucha_id,
+                entry_id,
+                version,
+                patch,
+            },
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct Props {
     pub kombucha: Kombucha,
-    pub on_change: Callback<Kombucha>,
-    pub on_new_entry: Callback<KombuchaId>,
+    /// Downsampled measurements of the kombucha for the charts
+    #[prop_or_default]
+    pub series: Vec<MeasurementSeries>,
+    pub on_edit: Callback<Edit>,
+    /// Creates an entry with what was typed into a new one, which only
+    /// happens once it's saved
+    pub on_new_entry: Callback<(KombuchaId, EntryPatch)>,
     pub on_delete_entry: Callback<(KombuchaId, EntryId)>,
     pub on_delete: Callback<KombuchaId>,
+    pub on_update_fermentation: Callback<(KombuchaId, Fermentation)>,
+    pub on_next_fermentation: Callback<KombuchaId>,
+    pub on_upload_images: Callback<(KombuchaId, Option<EntryId>, Vec<File>)>,
+    pub on_delete_image: Callback<(KombuchaId, ImageId)>,
+    pub on_new_measurement: Callback<(KombuchaId, NewMeasurement)>,
+    pub on_delete_measurement: Callback<(KombuchaId, MeasurementId)>,
+    pub on_save_recipe: Callback<(KombuchaId, Recipe)>,
+    pub on_delete_recipe: Callback<KombuchaId>,
+    /// Starts a new batch with the recipe of this one
+    pub on_brew_again: Callback<KombuchaId>,
+    pub on_save_template: Callback<NewRecipeTemplate>,
 }
 
 pub struct KombuchaView {
     link: ComponentLink<Self>,
+    _key_listener: Option<KeyListenerHandle>,
     is_editing_name: bool,
+    /// Edited by `e`, moved by `J` and `K` or by clicking an entry
+    selected_entry: Option<EntryId>,
     edited_entry: Option<usize>,
+    /// Kombucha as it was before editing started, restored on cancel
+    before_edit: Option<Kombucha>,
+    /// A reading like `3.2 ph` typed into the measurement field
+    measurement_text: String,
+    measurement_input: NodeRef,
+    /// Copy of the recipe being edited, saved as a whole
+    edited_recipe: Option<Recipe>,
+    show_calculator: bool,
     props: Props,
 }
 
@@ -35,11 +165,25 @@ impl Component for KombuchaView {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let key_listener = web_sys::window().map(|window| {
+            KeyboardService::register_key_down(
+                &window,
+                link.callback(Msg::KeyDown),
+            )
+        });
+
         Self {
             link,
+            _key_listener: key_listener,
             props,
             is_editing_name: false,
+            selected_entry: None,
             edited_entry: None,
+            before_edit: None,
+            measurement_text: String::new(),
+            measurement_input: NodeRef::default(),
+            edited_recipe: None,
+            show_calculator: false,
         }
     }
 
@@ -49,19 +193,60 @@ impl Component for KombuchaView {
             Msg::UpdateName(new_name) => {
                 self.props.kombucha.name = new_name;
             }
-            Msg::StartEditingSection(idx) => match self.edited_entry {
-                None => {
-                    if idx < self.props.kombucha.entries.len() {
-                        self.edited_entry = Some(idx);
+            Msg::SelectEntry(entry_id) => {
+                self.selected_entry = Some(entry_id);
+            }
+            Msg::StartEditingSection(idx) => {
+                self.save_before_edit();
+                if let Some(entry) = self.props.kombucha.entries.get(idx) {
+                    if !is_new(entry) {
+                        self.selected_entry = Some(entry.id);
                     }
                 }
-                Some(_) => {
-                    self.edited_entry = Some(idx);
+
+                match self.edited_entry {
+                    None => {
+                        if idx < self.props.kombucha.entries.len() {
+                            self.edited_entry = Some(idx);
+                        }
+                    }
+                    Some(_) => {
+                        self.edited_entry = Some(idx);
+                    }
                 }
-            },
+            }
             Msg::StopEditingSection => {
-                self.edited_entry = None;
-                self.props.on_change.emit(self.props.kombucha.clone());
+                let before_edit = self.before_edit.take();
+                if let Some(entry) = self
+                    .edited_entry
+                    .take()
+                    .and_then(|idx| self.props.kombucha.entries.get(idx))
+                {
+                    if is_new(entry) {
+                        self.props.on_new_entry.emit((
+                            self.props.kombucha.id,
+                            EntryPatch::from(entry),
+                        ));
+                        return true;
+                    }
+
+                    let before = before_edit.as_ref().and_then(|kombucha| {
+                        kombucha
+                            .entries
+                            .iter()
+                            .find(|before| before.id == entry.id)
+                    });
+                    let patch = Self::entry_patch(before, entry);
+
+                    if patch != EntryPatch::default() {
+                        self.props.on_edit.emit(Edit::Entry {
+                            kombucha_id: self.props.kombucha.id,
+                            entry_id: entry.id,
+                            version: entry.version,
+                            patch,
+                        });
+                    }
+                }
             }
             Msg::UpdateSectionText(idx, new_text) => {
                 if let Some(section) = self.props.kombucha.entries.get_mut(idx)
@@ -69,11 +254,24 @@ impl Component for KombuchaView {
                     section.content = new_text;
                 }
             }
+            Msg::UpdateSectionAdded(idx, added) => {
+                match (self.props.kombucha.entries.get_mut(idx), added) {
+                    (Some(section), Some(added)) => section.added = added,
+                    _ => return false,
+                }
+            }
             Msg::NewSection => {
-                self.props.on_new_entry.emit(self.props.kombucha.id);
+                self.save_before_edit();
                 self.props.kombucha.entries.push(Entry::default());
                 self.edited_entry = Some(self.props.kombucha.entries.len() - 1);
             }
+            // An unsaved new entry is simply dropped, as on cancel
+            Msg::DeleteSection(idx)
+                if self.edited_entry == Some(idx)
+                    && self.is_editing_new_entry() =>
+            {
+                self.cancel_editing();
+            }
             Msg::DeleteSection(idx) => {
                 if let Some(entry) = self.props.kombucha.entries.get(idx) {
                     self.props
@@ -85,18 +283,188 @@ impl Component for KombuchaView {
                 self.props.on_delete.emit(self.props.kombucha.id);
             }
             Msg::StartEditingName => {
+                self.save_before_edit();
                 self.is_editing_name = true;
             }
             Msg::StopEditingName => {
+                let before_edit = self.before_edit.take();
                 self.is_editing_name = false;
-                self.props.on_change.emit(self.props.kombucha.clone());
+
+                let kombucha = &self.props.kombucha;
+                if before_edit.map(|before| before.name)
+                    != Some(kombucha.name.clone())
+                {
+                    self.props.on_edit.emit(Edit::Kombucha {
+                        id: kombucha.id,
+                        version: kombucha.version,
+                        patch: KombuchaPatch {
+                            name: Some(kombucha.name.clone()),
+                            ..KombuchaPatch::default()
+                        },
+                    });
+                }
+            }
+            Msg::SetEstimatedEnd(idx, est_end_date) => {
+                if let Some(fermentation) =
+                    self.props.kombucha.fermentations.get_mut(idx)
+                {
+                    fermentation.est_end_date = est_end_date;
+                    self.props
+                        .on_update_fermentation
+                        .emit((self.props.kombucha.id, fermentation.clone()));
+                }
             }
+            Msg::EndFermentation(idx) => {
+                if let Some(fermentation) =
+                    self.props.kombucha.fermentations.get_mut(idx)
+                {
+                    fermentation.end_date = Some(Utc::now());
+                    self.props
+                        .on_update_fermentation
+                        .emit((self.props.kombucha.id, fermentation.clone()));
+                }
+            }
+            Msg::NextFermentation => {
+                self.props.on_next_fermentation.emit(self.props.kombucha.id);
+            }
+            Msg::UploadImages(entry_id, files) => {
+                if !files.is_empty() {
+                    self.props.on_upload_images.emit((
+                        self.props.kombucha.id,
+                        entry_id,
+                        files,
+                    ));
+                }
+            }
+            Msg::DeleteImage(image_id) => {
+                self.props
+                    .on_delete_image
+                    .emit((self.props.kombucha.id, image_id));
+            }
+            Msg::UpdateMeasurementText(text) => self.measurement_text = text,
+            Msg::LogMeasurement => {
+                let fermentation_id = self
+                    .props
+                    .kombucha
+                    .current_fermentation()
+                    .map(|fermentation| fermentation.id);
+
+                if let Some(measurement) =
+                    Self::parse_measurement(&self.measurement_text)
+                {
+                    self.measurement_text.clear();
+                    self.props.on_new_measurement.emit((
+                        self.props.kombucha.id,
+                        NewMeasurement {
+                            fermentation_id,
+                            ..measurement
+                        },
+                    ));
+                }
+            }
+            Msg::DeleteMeasurement(measurement_id) => {
+                self.props
+                    .on_delete_measurement
+                    .emit((self.props.kombucha.id, measurement_id));
+            }
+            Msg::StartEditingRecipe => {
+                self.edited_recipe =
+                    Some(self.props.kombucha.recipe.clone().unwrap_or_else(
+                        || Recipe {
+                            teas: vec![RecipeTea::default()],
+                            water_ml: 1000.0,
+                            stages: PlannedStage::from_fermentations(
+                                &self.props.kombucha.fermentations,
+                            ),
+                            ..Recipe::default()
+                        },
+                    ));
+            }
+            Msg::UpdateRecipe(field) => match &mut self.edited_recipe {
+                Some(recipe) => Self::update_recipe(recipe, field),
+                None => return false,
+            },
+            Msg::AddRecipeTea => match &mut self.edited_recipe {
+                Some(recipe) => recipe.teas.push(RecipeTea::default()),
+                None => return false,
+            },
+            Msg::RemoveRecipeTea(idx) => match &mut self.edited_recipe {
+                Some(recipe) if idx < recipe.teas.len() => {
+                    recipe.teas.remove(idx);
+                }
+                _ => return false,
+            },
+            Msg::RemoveRecipeStage(idx) => match &mut self.edited_recipe {
+                Some(recipe) if idx < recipe.stages.len() => {
+                    recipe.stages.remove(idx);
+                }
+                _ => return false,
+            },
+            Msg::AddRecipeFlavouring => match &mut self.edited_recipe {
+                Some(recipe) => {
+                    recipe.flavourings.push(RecipeFlavouring::default())
+                }
+                None => return false,
+            },
+            Msg::RemoveRecipeFlavouring(idx) => match &mut self.edited_recipe {
+                Some(recipe) if idx < recipe.flavourings.len() => {
+                    recipe.flavourings.remove(idx);
+                }
+                _ => return false,
+            },
+            Msg::SaveRecipe => match self.edited_recipe.take() {
+                Some(recipe) if recipe.check().is_ok() => {
+                    self.props
+                        .on_save_recipe
+                        .emit((self.props.kombucha.id, recipe));
+                }
+                recipe => {
+                    self.edited_recipe = recipe;
+                    return false;
+                }
+            },
+            Msg::CancelRecipe => self.edited_recipe = None,
+            Msg::DeleteRecipe => {
+                self.edited_recipe = None;
+                self.props.on_delete_recipe.emit(self.props.kombucha.id);
+            }
+            Msg::BrewAgain => {
+                self.props.on_brew_again.emit(self.props.kombucha.id);
+            }
+            Msg::SaveAsTemplate => match &self.props.kombucha.recipe {
+                Some(recipe) => {
+                    self.props.on_save_template.emit(NewRecipeTemplate {
+                        name: self.props.kombucha.name.clone(),
+                        recipe: recipe.clone(),
+                    });
+                }
+                None => return false,
+            },
+            Msg::ToggleCalculator => {
+                self.show_calculator = !self.show_calculator
+            }
+            Msg::ApplyScaledRecipe(recipe) => {
+                self.show_calculator = false;
+                self.props
+                    .on_save_recipe
+                    .emit((self.props.kombucha.id, recipe));
+            }
+            Msg::KeyDown(e) => return self.handle_key_down(e),
         }
 
         true
     }
 
-    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+    fn change(&mut self, mut props: Self::Properties) -> ShouldRender {
+        if props.kombucha.id != self.props.kombucha.id {
+            self.selected_entry = None;
+        } else if self.is_editing_new_entry() {
+            // The new entry isn't saved yet, so it's kept over a reload
+            if let Some(entry) = self.props.kombucha.entries.pop() {
+                props.kombucha.entries.push(entry);
+                self.edited_entry = Some(props.kombucha.entries.len() - 1);
+            }
+        }
         self.props = props;
 
         true
@@ -129,16 +497,13 @@ impl Component for KombuchaView {
                             </p>
                         </div>
                         <hr />
-                        <p>
-                            <p class="title is-6">{"Fermentation status"}</p>
-                            <p>{"Primary"}</p>
-                                <progress class="progress is-primary" value="100" max="100">{"15%"}</progress>
-                            <p>{"1 Jan 2016 - 7 Jan 2016"}</p>
-                            <hr />
-                            <p>{"Secondary"}</p>
-                                <progress class="progress is-info" value="15" max="100">{"15%"}</progress>
-                            <p>{"7 Jan 2016 - 10 Jan 2016"}</p>
-                        </p>
+                        { self.view_recipe() }
+                        <hr />
+                        { self.view_gallery() }
+                        <hr />
+                        { self.view_fermentations() }
+                        <hr />
+                        { self.view_measurements() }
                     </div>
                 </div>
             </div>
@@ -147,16 +512,134 @@ impl Component for KombuchaView {
 }
 
 impl KombuchaView {
+    fn is_editing_new_entry(&self) -> bool {
+        self.edited_entry
+            .and_then(|idx| self.props.kombucha.entries.get(idx))
+            .is_some_and(is_new)
+    }
+
+    /// Restores the kombucha as it was before editing, which also drops a new
+    /// entry that wasn't saved
+    fn cancel_editing(&mut self) {
+        if let Some(kombucha) = self.before_edit.take() {
+            self.props.kombucha = kombucha;
+        }
+        self.is_editing_name = false;
+        self.edited_entry = None;
+        self.edited_recipe = None;
+    }
+
+    /// Index of the selected entry, the latest one without a selection
+    fn selected_entry_idx(&self) -> Option<usize> {
+        let entries = &self.props.kombucha.entries;
+
+        self.selected_entry
+            .and_then(|id| entries.iter().position(|entry| entry.id == id))
+            .or_else(|| entries.len().checked_sub(1))
+    }
+
+    fn select_next_entry(&mut self, shortcut: Shortcut) -> ShouldRender {
+        let entries = &self.props.kombucha.entries;
+        let position = self
+            .selected_entry
+            .and_then(|id| entries.iter().position(|entry| entry.id == id));
+
+        let next = match (shortcut, position) {
+            (Shortcut::PreviousEntry, Some(position)) => position
+                .checked_sub(1)
+                .and_then(|position| entries.get(position)),
+            (Shortcut::PreviousEntry, None) => entries.last(),
+            (_, Some(position)) => entries.get(position + 1),
+            (_, None) => entries.first(),
+        };
+
+        match next {
+            Some(entry) => {
+                self.selected_entry = Some(entry.id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn save_before_edit(&mut self) {
+        if self.before_edit.is_none() {
+            self.before_edit = Some(self.props.kombucha.clone());
+        }
+    }
+
+    /// Only what changed since editing started, everything without `before`
+    fn entry_patch(before: Option<&Entry>, entry: &Entry) -> EntryPatch {
+        EntryPatch {
+            added: Some(entry.added).filter(|added| {
+                before.is_none_or(|before| before.added != *added)
+            }),
+            content: Some(entry.content.clone()).filter(|content| {
+                before.is_none_or(|before| before.content != *content)
+            }),
+        }
+    }
+
+    fn handle_key_down(&mut self, e: KeyboardEvent) -> ShouldRender {
+        let is_editing = self.is_editing_name
+            || self.edited_entry.is_some()
+            || self.edited_recipe.is_some();
+
+        let msg = match Shortcut::from_event(&e) {
+            Some(Shortcut::NewEntry) if !is_editing => Msg::NewSection,
+            Some(Shortcut::EditEntry) if !is_editing => {
+                match self.selected_entry_idx() {
+                    Some(idx) => Msg::StartEditingSection(idx),
+                    None => return false,
+                }
+            }
+            Some(shortcut @ Shortcut::NextEntry)
+            | Some(shortcut @ Shortcut::PreviousEntry)
+                if !is_editing =>
+            {
+                e.prevent_default();
+                return self.select_next_entry(shortcut);
+            }
+            Some(Shortcut::LogMeasurement) if !is_editing => {
+                e.prevent_default();
+
+                if let Some(input) =
+                    self.measurement_input.cast::<HtmlInputElement>()
+                {
+                    let _ = input.focus();
+                }
+
+                return false;
+            }
+            Some(Shortcut::Save) if self.edited_entry.is_some() => {
+                Msg::StopEditingSection
+            }
+            Some(Shortcut::Save) if self.is_editing_name => {
+                Msg::StopEditingName
+            }
+            Some(Shortcut::Save) if self.edited_recipe.is_some() => {
+                Msg::SaveRecipe
+            }
+            Some(Shortcut::Cancel) if is_editing => {
+                e.prevent_default();
+                self.cancel_editing();
+
+                return true;
+            }
+            _ => return false,
+        };
+
+        e.prevent_default();
+        self.update(msg)
+    }
+
     fn view_name(&self) -> Html {
         if self.is_editing_name {
             html! {
                 <div>
                     <input
                         class="title is-4" value=self.props.kombucha.name
-                        onchange=self.link.callback(|e: ChangeData| match e {
-                            ChangeData::Value(value) => Msg::UpdateName(value),
-                            x => { log::error!("Invalid change data, expected value, found {:?}", x); Msg::Nop }
-                        })
+                        oninput=self.link.callback(|e: InputData| Msg::UpdateName(e.value))
                     />
 
                     <a
@@ -246,8 +729,18 @@ impl KombuchaView {
                         { &entry.content }
                     </textarea>
                     <br />
-                    { Self::view_entry_added(entry) }
+                    <input
+                        class="input is-small kombucha-entry-added"
+                        type="datetime-local"
+                        value=entry.added.with_timezone(&Local).format("%Y-%m-%dT%H:%M").to_string()
+                        onchange=self.link.callback(move |e: ChangeData| match e {
+                            ChangeData::Value(value) => Msg::UpdateSectionAdded(idx, Self::parse_date_time(&value)),
+                            x => { log::error!("Invalid change data, expected value, found {:?}", x); Msg::Nop }
+                        })
+                    />
                 </p>
+                { self.view_images(Some(entry.id)) }
+                { self.view_image_upload(Some(entry.id)) }
                 <p class="kombucha-content-control">
                     <div class="field is-grouped is-grouped-centered">
                         <p class="control">
@@ -277,13 +770,21 @@ impl KombuchaView {
     }
 
     fn view_regular_entry(&self, idx: usize, entry: &Entry) -> Html {
+        let class = if self.selected_entry == Some(entry.id) {
+            "kombucha-entry is-selected"
+        } else {
+            "kombucha-entry"
+        };
+        let entry_id = entry.id;
+
         html! {
-            <div class="kombucha-entry">
+            <div class=class onclick=self.link.callback(move |_| Msg::SelectEntry(entry_id))>
                 <p>
                     { for entry.content.split('\n').filter(|s| !s.is_empty()).map(|s| html! { <p>{ s }</p>}) }
                     <br />
                     { Self::view_entry_added(entry) }
                 </p>
+                { self.view_images(Some(entry.id)) }
                 <p class="kombucha-content-control">
                     <div class="field is-grouped is-grouped-centered">
                         <p class="control">
@@ -311,4 +812,712 @@ impl KombuchaView {
             </div>
         }
     }
+
+    fn view_fermentations(&self) -> Html {
+        let fermentations = &self.props.kombucha.fermentations;
+        let now = Utc::now();
+
+        let can_advance = !matches!(
+            fermentations.last(),
+            Some(fermentation) if fermentation.status.is_terminal()
+        );
+
+        let next_button_text = if fermentations.is_empty() {
+            "Start fermentation"
+        } else {
+            "Start next stage"
+        };
+
+        html! {
+            <div class="kombucha-fermentations">
+                <p class="title is-6">{"Fermentation status"}</p>
+                { for fermentations.iter().enumerate().map(|(idx, fermentation)| self.view_fermentation(idx, fermentation, now)) }
+                <div class="field is-grouped is-grouped-centered">
+                    <p class="control">
+                        <button
+                            class="button is-primary is-outlined"
+                            disabled=!can_advance
+                            onclick=self.link.callback(|_| Msg::NextFermentation)
+                        >
+                            { next_button_text }
+                        </button>
+                    </p>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_fermentation(
+        &self,
+        idx: usize,
+        fermentation: &Fermentation,
+        now: DateTime<Utc>,
+    ) -> Html {
+        let is_active = fermentation.is_active();
+
+        let (class, progress_class) = if is_active {
+            ("kombucha-fermentation is-active", "progress is-primary")
+        } else {
+            ("kombucha-fermentation", "progress is-info")
+        };
+
+        // Without an estimated end a running fermentation has indeterminate
+        // progress, an ended one is simply done
+        let progress = match fermentation.progress(now) {
+            Some(progress) => html! {
+                <progress class=progress_class value=(progress * 100.0).round() max="100">
+                    { format!("{:.0}%", progress * 100.0) }
+                </progress>
+            },
+            None if is_active => html! {
+                <progress class=progress_class max="100"></progress>
+            },
+            None => html! {
+                <progress class=progress_class value="100" max="100">{"100%"}</progress>
+            },
+        };
+
+        let end = match (fermentation.end_date, fermentation.est_end_date) {
+            (Some(end_date), _) => Self::format_date(end_date),
+            (None, Some(est_end_date)) => {
+                format!("~{}", Self::format_date(est_end_date))
+            }
+            (None, None) => "?".to_string(),
+        };
+
+        let est_end_value = fermentation
+            .est_end_date
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+
+        let controls = if is_active {
+            html! {
+                <div class="field is-grouped">
+                    <p class="control">
+                        <input
+                            class="input is-small"
+                            type="date"
+                            title="Estimated end"
+                            value=est_end_value
+                            onchange=self.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Value(value) => Msg::SetEstimatedEnd(idx, Self::parse_date(&value)),
+                                x => { log::error!("Invalid change data, expected value, found {:?}", x); Msg::Nop }
+                            })
+                        />
+                    </p>
+                    <p class="control">
+                        <button
+                            class="button is-small"
+                            onclick=self.link.callback(move |_| Msg::EndFermentation(idx))
+                        >
+                            {"End stage"}
+                        </button>
+                    </p>
+                </div>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <div class=class>
+                <p>{ fermentation.status }</p>
+                { progress }
+                <p>{ format!("{} - {}", Self::format_date(fermentation.start_date), end) }</p>
+                { controls }
+                <hr />
+            </div>
+        }
+    }
+
+    fn update_recipe(recipe: &mut Recipe, field: RecipeField) {
+        let amount = |value: String| value.trim().parse().unwrap_or(0.0);
+
+        match field {
+            RecipeField::TeaVariety(idx, variety) => {
+                if let Some(tea) = recipe.teas.get_mut(idx) {
+                    tea.variety = variety;
+                }
+            }
+            RecipeField::TeaGrams(idx, grams) => {
+                if let Some(tea) = recipe.teas.get_mut(idx) {
+                    tea.grams = amount(grams);
+                }
+            }
+            RecipeField::SugarType(sugar_type) => {
+                recipe.sugar_type = sugar_type
+            }
+            RecipeField::SugarGrams(grams) => {
+                recipe.sugar_grams = amount(grams)
+            }
+            RecipeField::WaterMl(ml) => recipe.water_ml = amount(ml),
+            RecipeField::StarterMl(ml) => recipe.starter_ml = amount(ml),
+            RecipeField::Vessel(vessel) => recipe.vessel = vessel,
+            RecipeField::VesselMl(ml) => {
+                recipe.vessel_ml = ml.trim().parse().ok()
+            }
+            RecipeField::StageDays(idx, days) => {
+                if let Some(stage) = recipe.stages.get_mut(idx) {
+                    stage.days = days.trim().parse().unwrap_or(0);
+                }
+            }
+            RecipeField::FlavouringIngredient(idx, ingredient) => {
+                if let Some(flavouring) = recipe.flavourings.get_mut(idx) {
+                    flavouring.ingredient = ingredient;
+                }
+            }
+            RecipeField::FlavouringAmount(idx, amount) => {
+                if let Some(flavouring) = recipe.flavourings.get_mut(idx) {
+                    flavouring.amount = amount;
+                }
+            }
+            RecipeField::FlavouringStage(idx, stage) => {
+                if let Some(flavouring) = recipe.flavourings.get_mut(idx) {
+                    flavouring.stage = stage;
+                }
+            }
+        }
+    }
+
+    fn view_recipe(&self) -> Html {
+        if let Some(recipe) = &self.edited_recipe {
+            return self.view_edited_recipe(recipe);
+        }
+
+        let recipe = match &self.props.kombucha.recipe {
+            Some(recipe) => recipe,
+            None => {
+                return html! {
+                    <div class="kombucha-recipe">
+                        <p class="title is-6">{"Recipe"}</p>
+                        <button
+                            class="button is-small"
+                            onclick=self.link.callback(|_| Msg::StartEditingRecipe)
+                        >
+                            {"Add recipe"}
+                        </button>
+                    </div>
+                }
+            }
+        };
+
+        let teas = recipe
+            .teas
+            .iter()
+            .map(|tea| format!("{} g {}", tea.grams, tea.variety))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let vessel = match recipe.vessel_ml {
+            Some(ml) => format!("{} ({} ml)", recipe.vessel, ml),
+            None => recipe.vessel.clone(),
+        };
+        let stages = recipe
+            .stages
+            .iter()
+            .map(|stage| match stage.days {
+                1 => format!("{} 1 day", stage.status),
+                days => format!("{} {} days", stage.status, days),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let flavourings = recipe
+            .flavourings
+            .iter()
+            .map(|flavouring| match flavouring.stage {
+                Some(stage) => format!(
+                    "{} {} in {}",
+                    flavouring.amount, flavouring.ingredient, stage
+                ),
+                None => {
+                    format!("{} {}", flavouring.amount, flavouring.ingredient)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        html! {
+            <div class="kombucha-recipe">
+                <p class="title is-6">{"Recipe"}</p>
+                <table class="table is-narrow is-fullwidth">
+                    <tbody>
+                        <tr><th>{"Tea"}</th><td>{ teas }</td></tr>
+                        <tr><th>{"Sugar"}</th><td>{ format!("{} g {}", recipe.sugar_grams, recipe.sugar_type) }</td></tr>
+                        <tr><th>{"Water"}</th><td>{ format!("{} ml", recipe.water_ml) }</td></tr>
+                        <tr><th>{"Starter"}</th><td>{ format!("{} ml", recipe.starter_ml) }</td></tr>
+                        <tr><th>{"Vessel"}</th><td>{ vessel }</td></tr>
+                        <tr><th>{"Stages"}</th><td>{ stages }</td></tr>
+                        <tr><th>{"Flavourings"}</th><td>{ flavourings }</td></tr>
+                    </tbody>
+                </table>
+                { Self::view_recipe_warnings(recipe) }
+                <div class="buttons">
+                    <button
+                        class="button is-small"
+                        onclick=self.link.callback(|_| Msg::StartEditingRecipe)
+                    >
+                        {"Edit recipe"}
+                    </button>
+                    <button
+                        class="button is-small is-primary"
+                        onclick=self.link.callback(|_| Msg::BrewAgain)
+                    >
+                        {"Brew again"}
+                    </button>
+                    <button
+                        class="button is-small"
+                        onclick=self.link.callback(|_| Msg::SaveAsTemplate)
+                    >
+                        {"Save as template"}
+                    </button>
+                    <button
+                        class="button is-small"
+                        onclick=self.link.callback(|_| Msg::ToggleCalculator)
+                    >
+                        {"Scale"}
+                    </button>
+                    <button
+                        class="button is-small is-danger is-outlined"
+                        onclick=self.link.callback(|_| Msg::DeleteRecipe)
+                    >
+                        {"Remove recipe"}
+                    </button>
+                </div>
+                {
+                    if self.show_calculator {
+                        html! {
+                            <RecipeCalculator
+                                recipe=recipe.clone()
+                                on_apply=self.link.callback(Msg::ApplyScaledRecipe)
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </div>
+        }
+    }
+
+    fn view_edited_recipe(&self, recipe: &Recipe) -> Html {
+        let error = recipe.check().err();
+        let vessel_ml = recipe
+            .vessel_ml
+            .map(|ml| ml.to_string())
+            .unwrap_or_default();
+
+        html! {
+            <div class="kombucha-recipe">
+                <p class="title is-6">{"Recipe"}</p>
+                { for recipe.teas.iter().enumerate().map(|(idx, tea)| self.view_recipe_tea(idx, tea)) }
+                <div class="field">
+                    <button
+                        class="button is-small"
+                        onclick=self.link.callback(|_| Msg::AddRecipeTea)
+                    >
+                        {"Add tea"}
+                    </button>
+                </div>
+                <div class="field is-grouped">
+                    <p class="control is-expanded">
+                        <input
+                            class="input is-small" type="text" placeholder="Sugar"
+                            value=&recipe.sugar_type
+                            oninput=self.link.callback(|e: InputData| Msg::UpdateRecipe(RecipeField::SugarType(e.value)))
+                        />
+                    </p>
+                    { self.view_recipe_amount("g", recipe.sugar_grams, RecipeField::SugarGrams) }
+                </div>
+                <div class="field is-grouped">
+                    <p class="control is-expanded">{"Water"}</p>
+                    { self.view_recipe_amount("ml", recipe.water_ml, RecipeField::WaterMl) }
+                </div>
+                <div class="field is-grouped">
+                    <p class="control is-expanded">{"Starter liquid"}</p>
+                    { self.view_recipe_amount("ml", recipe.starter_ml, RecipeField::StarterMl) }
+                </div>
+                <div class="field is-grouped">
+                    <p class="control is-expanded">
+                        <input
+                            class="input is-small" type="text" placeholder="Vessel"
+                            value=&recipe.vessel
+                            oninput=self.link.callback(|e: InputData| Msg::UpdateRecipe(RecipeField::Vessel(e.value)))
+                        />
+                    </p>
+                    <p class="control">
+                        <input
+                            class="input is-small" type="number" min="0" placeholder="ml"
+                            value=vessel_ml
+                            oninput=self.link.callback(|e: InputData| Msg::UpdateRecipe(RecipeField::VesselMl(e.value)))
+                        />
+                    </p>
+                </div>
+                { for recipe.stages.iter().enumerate().map(|(idx, stage)| self.view_recipe_stage(idx, stage)) }
+                { self.view_recipe_flavourings(recipe) }
+                {
+                    match &error {
+                        Some(error) => html! { <p class="help is-danger">{ error }</p> },
+                        None => Self::view_recipe_warnings(recipe),
+                    }
+                }
+                <div class="buttons">
+                    <button
+                        class="button is-small is-info"
+                        disabled=error.is_some()
+                        onclick=self.link.callback(|_| Msg::SaveRecipe)
+                    >
+                        {"Save"}
+                    </button>
+                    <button
+                        class="button is-small"
+                        onclick=self.link.callback(|_| Msg::CancelRecipe)
+                    >
+                        {"Cancel"}
+                    </button>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_recipe_tea(&self, idx: usize, tea: &RecipeTea) -> Html {
+        html! {
+            <div class="field is-grouped">
+                <p class="control is-expanded">
+                    <input
+                        class="input is-small" type="text" placeholder="Tea variety"
+                        value=&tea.variety
+                        oninput=self.link.callback(move |e: InputData| Msg::UpdateRecipe(RecipeField::TeaVariety(idx, e.value)))
+                    />
+                </p>
+                { self.view_recipe_amount("g", tea.grams, move |grams| RecipeField::TeaGrams(idx, grams)) }
+                <p class="control">
+                    <button
+                        class="delete"
+                        aria-label="remove"
+                        onclick=self.link.callback(move |_| Msg::RemoveRecipeTea(idx))
+                    ></button>
+                </p>
+            </div>
+        }
+    }
+
+    fn view_recipe_stage(&self, idx: usize, stage: &PlannedStage) -> Html {
+        html! {
+            <div class="field is-grouped">
+                <p class="control is-expanded">{ stage.status.to_string() }</p>
+                { self.view_recipe_amount("days", stage.days.into(), move |days| RecipeField::StageDays(idx, days)) }
+                <p class="control">
+                    <button
+                        class="delete"
+                        aria-label="remove"
+                        onclick=self.link.callback(move |_| Msg::RemoveRecipeStage(idx))
+                    ></button>
+                </p>
+            </div>
+        }
+    }
+
+    fn view_recipe_flavourings(&self, recipe: &Recipe) -> Html {
+        html! {
+            <>
+                { for recipe.flavourings.iter().enumerate().map(|(idx, flavouring)| self.view_recipe_flavouring(idx, flavouring)) }
+                <div class="field">
+                    <button
+                        class="button is-small"
+                        onclick=self.link.callback(|_| Msg::AddRecipeFlavouring)
+                    >
+                        {"Add flavouring"}
+                    </button>
+                </div>
+            </>
+        }
+    }
+
+    fn view_recipe_flavouring(
+        &self,
+        idx: usize,
+        flavouring: &RecipeFlavouring,
+    ) -> Html {
+        let options = FermentationStatus::ALL.iter().map(|status| {
+            html! {
+                <option
+                    value=status.to_string()
+                    selected=flavouring.stage == Some(*status)
+                >
+                    { status.to_string() }
+                </option>
+            }
+        });
+
+        html! {
+            <div class="field is-grouped">
+                <p class="control is-expanded">
+                    <input
+                        class="input is-small" type="text" placeholder="Flavouring"
+                        value=&flavouring.ingredient
+                        oninput=self.link.callback(move |e: InputData| Msg::UpdateRecipe(RecipeField::FlavouringIngredient(idx, e.value)))
+                    />
+                </p>
+                <p class="control">
+                    <input
+                        class="input is-small" type="text" placeholder="Amount"
+                        value=&flavouring.amount
+                        oninput=self.link.callback(move |e: InputData| Msg::UpdateRecipe(RecipeField::FlavouringAmount(idx, e.value)))
+                    />
+                </p>
+                <p class="control">
+                    <span class="select is-small">
+                        <select
+                            onchange=self.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Select(select) => Msg::UpdateRecipe(RecipeField::FlavouringStage(idx, select.value().parse().ok())),
+                                x => { log::error!("Invalid change data, expected select, found {:?}", x); Msg::Nop }
+                            })
+                        >
+                            <option value="" selected=flavouring.stage.is_none()>{"Any stage"}</option>
+                            { for options }
+                        </select>
+                    </span>
+                </p>
+                <p class="control">
+                    <button
+                        class="delete"
+                        aria-label="remove"
+                        onclick=self.link.callback(move |_| Msg::RemoveRecipeFlavouring(idx))
+                    ></button>
+                </p>
+            </div>
+        }
+    }
+
+    fn view_recipe_amount(
+        &self,
+        unit: &str,
+        amount: f64,
+        field: impl Fn(String) -> RecipeField + 'static,
+    ) -> Html {
+        html! {
+            <p class="control">
+                <span class="field has-addons">
+                    <span class="control">
+                        <input
+                            class="input is-small" type="number" min="0"
+                            value=amount.to_string()
+                            oninput=self.link.callback(move |e: InputData| Msg::UpdateRecipe(field(e.value)))
+                        />
+                    </span>
+                    <span class="control">
+                        <span class="button is-small is-static">{ unit }</span>
+                    </span>
+                </span>
+            </p>
+        }
+    }
+
+    fn view_recipe_warnings(recipe: &Recipe) -> Html {
+        html! {
+            { for recipe.warnings().iter().map(|warning| html! {
+                <p class="help is-warning">{ warning }</p>
+            }) }
+        }
+    }
+
+    fn view_measurements(&self) -> Html {
+        let text = self.measurement_text.trim();
+        let input_class =
+            if text.is_empty() || Self::parse_measurement(text).is_some() {
+                "input is-small"
+            } else {
+                "input is-small is-danger"
+            };
+
+        html! {
+            <div class="kombucha-measurements">
+                <p class="title is-6">{"Measurements"}</p>
+                <MeasurementChart
+                    series=self.props.series.clone()
+                    fermentations=self.props.kombucha.fermentations.clone()
+                />
+                <table class="table is-narrow is-fullwidth">
+                    <tbody>
+                        { for self.props.kombucha.measurements.iter().rev().map(|measurement| self.view_measurement(measurement)) }
+                    </tbody>
+                </table>
+                <div class="field">
+                    <p class="control">
+                        <input
+                            class=input_class
+                            type="text"
+                            ref=self.measurement_input.clone()
+                            value=&self.measurement_text
+                            placeholder="3.2 pH, 24 °C or 1.012 SG, then Enter"
+                            oninput=self.link.callback(|e: InputData| Msg::UpdateMeasurementText(e.value))
+                            onkeypress=self.link.callback(|e: KeyboardEvent| {
+                                if e.key() == "Enter" { Msg::LogMeasurement } else { Msg::Nop }
+                            })
+                        />
+                    </p>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_measurement(&self, measurement: &Measurement) -> Html {
+        let measurement_id = measurement.id;
+
+        html! {
+            <tr class="kombucha-measurement">
+                <td>{ measurement.taken_at.with_timezone(&Local).format("%-d %b %H:%M") }</td>
+                <td>{ measurement.kind }</td>
+                <td>{ format!("{} {}", measurement.value, measurement.unit) }</td>
+                <td>
+                    <button
+                        class="delete is-small"
+                        aria-label="delete"
+                        onclick=self.link.callback(move |_| Msg::DeleteMeasurement(measurement_id))
+                    ></button>
+                </td>
+            </tr>
+        }
+    }
+
+    /// Reads a value followed by its unit, `ph`, `c`, `f` or `sg` in any case
+    /// and with or without a space or degree sign
+    fn parse_measurement(text: &str) -> Option<NewMeasurement> {
+        let text = text.trim();
+        let split = text
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+            .unwrap_or(text.len());
+        let (value, unit) = text.split_at(split);
+        let value = value.parse::<f64>().ok()?;
+
+        let unit = unit.trim().trim_start_matches('°').to_lowercase();
+        let (kind, unit) = match unit.as_str() {
+            "ph" => (MeasurementKind::Ph, "pH"),
+            "c" => (MeasurementKind::Temperature, "°C"),
+            "f" => (MeasurementKind::Temperature, "°F"),
+            "sg" => (MeasurementKind::SpecificGravity, "SG"),
+            _ => return None,
+        };
+        kind.check(value, unit).ok()?;
+
+        Some(NewMeasurement {
+            kind,
+            value,
+            unit: Some(unit.to_string()),
+            taken_at: None,
+            fermentation_id: None,
+        })
+    }
+
+    fn format_date(date: DateTime<Utc>) -> String {
+        date.format("%-d %b %Y").to_string()
+    }
+
+    fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .map(|date| DateTime::from_utc(date.and_hms(0, 0, 0), Utc))
+    }
+
+    /// Parses the value of a `datetime-local` input, which is in local time
+    fn parse_date_time(value: &str) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+            .ok()
+            .and_then(|date_time| {
+                Local.from_local_datetime(&date_time).single()
+            })
+            .map(|date_time| date_time.with_timezone(&Utc))
+    }
+
+    fn view_gallery(&self) -> Html {
+        html! {
+            <div class="kombucha-gallery">
+                <p class="title is-6">{"Images"}</p>
+                { self.view_images(None) }
+                { self.view_image_upload(None) }
+            </div>
+        }
+    }
+
+    /// Images of an entry, or every image of the kombucha when `entry_id` is
+    /// `None`
+    fn view_images(&self, entry_id: Option<EntryId>) -> Html {
+        let images = self
+            .props
+            .kombucha
+            .images
+            .iter()
+            .filter(|image| entry_id.is_none() || image.entry_id == entry_id)
+            .map(|image| self.view_image(image));
+
+        html! {
+            <div class="kombucha-images">
+                { for images }
+            </div>
+        }
+    }
+
+    fn view_image(&self, image: &Image) -> Html {
+        let url = format!(
+            "/api/1/kombucha/{}/image/{}",
+            self.props.kombucha.id, image.id
+        );
+        let image_id = image.id;
+
+        html! {
+            <figure class="image is-128x128 kombucha-image">
+                <a href=url.clone() target="_blank">
+                    <img src=format!("{}/thumbnail", url) />
+                </a>
+                <button
+                    class="delete is-small kombucha-image-delete"
+                    onclick=self.link.callback(move |_| Msg::DeleteImage(image_id))
+                >
+                </button>
+            </figure>
+        }
+    }
+
+    fn view_image_upload(&self, entry_id: Option<EntryId>) -> Html {
+        html! {
+            <div
+                class="kombucha-image-drop"
+                ondragover=self.link.callback(|e: DragEvent| { e.prevent_default(); Msg::Nop })
+                ondrop=self.link.callback(move |e: DragEvent| Msg::UploadImages(entry_id, Self::dropped_files(e)))
+            >
+                <div class="file is-small is-centered">
+                    <label class="file-label">
+                        <input
+                            class="file-input"
+                            type="file"
+                            accept="image/*"
+                            multiple=true
+                            onchange=self.link.callback(move |e: ChangeData| match e {
+                                ChangeData::Files(files) => Msg::UploadImages(entry_id, Self::files(Some(files))),
+                                x => { log::error!("Invalid change data, expected files, found {:?}", x); Msg::Nop }
+                            })
+                        />
+                        <span class="file-cta">
+                            <span class="file-icon">
+                                <i class="fas fa-upload"></i>
+                            </span>
+                            <span class="file-label">{"Drop images or browse"}</span>
+                        </span>
+                    </label>
+                </div>
+            </div>
+        }
+    }
+
+    fn dropped_files(e: DragEvent) -> Vec<File> {
+        e.prevent_default();
+
+        Self::files(e.data_transfer().and_then(|data| data.files()))
+    }
+
+    fn files(list: Option<FileList>) -> Vec<File> {
+        list.map(|list| {
+            (0..list.length()).filter_map(|idx| list.get(idx)).collect()
+        })
+        .unwrap_or_default()
+    }
 }
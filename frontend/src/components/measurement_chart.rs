@@ -0,0 +1,244 @@
+use chrono::{DateTime, Duration, Utc};
+use data_types::{Fermentation, MeasurementSeries, SeriesPoint};
+use yew::prelude::*;
+
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 160.0;
+const LEFT: f64 = 40.0;
+const RIGHT: f64 = 10.0;
+const TOP: f64 = 16.0;
+const BOTTOM: f64 = 20.0;
+
+/// A line chart per measured kind over a shared time axis, fermentation
+/// stages are drawn behind the lines as bands
+pub struct MeasurementChart {
+    props: Props,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct Props {
+    pub series: Vec<MeasurementSeries>,
+    pub fermentations: Vec<Fermentation>,
+}
+
+/// Maps times and values of one series into the plot area
+struct Scale {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    min: f64,
+    max: f64,
+}
+
+impl Scale {
+    fn x(&self, date: DateTime<Utc>) -> f64 {
+        let span = (self.end - self.start).num_seconds() as f64;
+        let offset = (date - self.start).num_seconds() as f64;
+
+        LEFT + (offset / span).clamp(0.0, 1.0) * (WIDTH - LEFT - RIGHT)
+    }
+
+    fn y(&self, value: f64) -> f64 {
+        let share = (value - self.min) / (self.max - self.min);
+
+        HEIGHT - BOTTOM - share * (HEIGHT - TOP - BOTTOM)
+    }
+}
+
+impl Component for MeasurementChart {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false;
+        }
+        self.props = props;
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let (start, end) = match self.time_range(Utc::now()) {
+            Some(range) => range,
+            None => return html! {},
+        };
+
+        html! {
+            <div class="kombucha-charts">
+                { for self.props.series.iter().map(|series| self.view_series(series, start, end)) }
+            </div>
+        }
+    }
+}
+
+impl MeasurementChart {
+    /// Spans every point and stage, stages that are still running end now
+    fn time_range(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let points = self.props.series.iter().flat_map(|series| {
+            series.points.iter().map(|point| point.taken_at)
+        });
+        let start = points.clone().min()?;
+        let end = points.max()?;
+
+        let stages = self.props.fermentations.iter().flat_map(|fermentation| {
+            vec![
+                fermentation.start_date,
+                fermentation.end_date.unwrap_or(now),
+            ]
+        });
+        let start = stages.clone().fold(start, DateTime::min);
+        let end = stages.fold(end, DateTime::max);
+
+        // A single reading still needs some width to be drawn at
+        Some((start, end.max(start + Duration::hours(1))))
+    }
+
+    fn view_series(
+        &self,
+        series: &MeasurementSeries,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Html {
+        let (min, max) = Self::value_range(&series.points);
+        let scale = Scale {
+            start,
+            end,
+            min,
+            max,
+        };
+
+        let line = series
+            .points
+            .iter()
+            .map(|point| {
+                format!(
+                    "{:.1},{:.1}",
+                    scale.x(point.taken_at),
+                    scale.y(point.value)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let title = if series.total as usize > series.points.len() {
+            format!(
+                "{} ({}, {} of {} readings)",
+                series.kind,
+                series.unit,
+                series.points.len(),
+                series.total
+            )
+        } else {
+            format!("{} ({})", series.kind, series.unit)
+        };
+
+        html! {
+            <div class="kombucha-chart">
+                <p class="heading">{ title }</p>
+                <svg
+                    viewBox=format!("0 0 {} {}", WIDTH, HEIGHT)
+                    preserveAspectRatio="none"
+                >
+                    { for self.props.fermentations.iter().enumerate().map(|(idx, fermentation)| Self::view_band(&scale, idx, fermentation)) }
+                    <line
+                        class="kombucha-chart-axis"
+                        x1=LEFT y1=HEIGHT - BOTTOM
+                        x2=WIDTH - RIGHT y2=HEIGHT - BOTTOM
+                    />
+                    <text class="kombucha-chart-label" x=LEFT - 4.0 y=TOP + 4.0 text-anchor="end">
+                        { Self::format_value(max) }
+                    </text>
+                    <text class="kombucha-chart-label" x=LEFT - 4.0 y=HEIGHT - BOTTOM text-anchor="end">
+                        { Self::format_value(min) }
+                    </text>
+                    <text class="kombucha-chart-label" x=LEFT y=HEIGHT - 4.0>
+                        { start.format("%-d %b").to_string() }
+                    </text>
+                    <text class="kombucha-chart-label" x=WIDTH - RIGHT y=HEIGHT - 4.0 text-anchor="end">
+                        { end.format("%-d %b").to_string() }
+                    </text>
+                    <polyline class="kombucha-chart-line" points=line />
+                    { for series.points.iter().map(|point| html! {
+                        <circle
+                            class="kombucha-chart-point"
+                            cx=format!("{:.1}", scale.x(point.taken_at))
+                            cy=format!("{:.1}", scale.y(point.value))
+                            r="2"
+                        >
+                            <title>{ format!("{}: {}", point.taken_at.format("%-d %b %H:%M"), Self::format_value(point.value)) }</title>
+                        </circle>
+                    }) }
+                </svg>
+            </div>
+        }
+    }
+
+    fn view_band(
+        scale: &Scale,
+        idx: usize,
+        fermentation: &Fermentation,
+    ) -> Html {
+        let x = scale.x(fermentation.start_date);
+        let end = scale.x(fermentation.end_date.unwrap_or(scale.end));
+
+        // `is_multiple_of` is too recent for the toolchains this builds on
+        #[allow(clippy::manual_is_multiple_of)]
+        let class = if idx % 2 == 0 {
+            "kombucha-chart-band"
+        } else {
+            "kombucha-chart-band is-odd"
+        };
+
+        html! {
+            <g>
+                <rect
+                    class=class
+                    x=format!("{:.1}", x)
+                    y=TOP
+                    width=format!("{:.1}", (end - x).max(0.0))
+                    height=HEIGHT - TOP - BOTTOM
+                />
+                <text class="kombucha-chart-label" x=format!("{:.1}", x + 2.0) y=TOP - 4.0>
+                    { fermentation.status }
+                </text>
+            </g>
+        }
+    }
+
+    /// Lowest and highest value with a little room above and below, so that
+    /// a flat line doesn't sit on the edge
+    fn value_range(points: &[SeriesPoint]) -> (f64, f64) {
+        let min = points
+            .iter()
+            .map(|point| point.value)
+            .fold(f64::INFINITY, f64::min);
+        let max = points
+            .iter()
+            .map(|point| point.value)
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        let margin = ((max - min) * 0.1).max(0.01);
+
+        (min - margin, max + margin)
+    }
+
+    /// Specific gravity needs three decimals, everything else one
+    fn format_value(value: f64) -> String {
+        if value.abs() < 2.0 {
+            format!("{:.3}", value)
+        } else {
+            format!("{:.1}", value)
+        }
+    }
+}
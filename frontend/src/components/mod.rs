@@ -2,8 +2,12 @@ mod app;
 mod error_view;
 mod kombucha_panel;
 mod kombucha_view;
+mod measurement_chart;
+mod recipe_calculator;
 
 pub use self::app::App;
 pub use self::error_view::ErrorView;
 pub use self::kombucha_panel::KombuchaPanel;
-pub use self::kombucha_view::KombuchaView;
+pub use self::kombucha_view::{Edit, KombuchaView};
+pub use self::measurement_chart::MeasurementChart;
+pub use self::recipe_calculator::RecipeCalculator;
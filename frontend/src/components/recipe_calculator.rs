@@ -0,0 +1,213 @@
+use data_types::calculator::{self, MassUnit, Strength, VolumeUnit};
+use data_types::Recipe;
+use std::str::FromStr;
+use yew::prelude::*;
+
+pub enum Msg {
+    Nop,
+    UpdateTarget(String),
+    SetMassUnit(MassUnit),
+    SetVolumeUnit(VolumeUnit),
+    Apply,
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct Props {
+    pub recipe: Recipe,
+    /// Receives the recipe scaled to the target volume
+    #[prop_or_default]
+    pub on_apply: Callback<Recipe>,
+}
+
+/// Shows a recipe scaled to another batch size in the units picked, amounts
+/// are shown as they are until a target volume is typed in
+pub struct RecipeCalculator {
+    link: ComponentLink<Self>,
+    /// Water and starter together, in `volume_unit`
+    target: String,
+    mass_unit: MassUnit,
+    volume_unit: VolumeUnit,
+    props: Props,
+}
+
+impl Component for RecipeCalculator {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            link,
+            target: String::new(),
+            mass_unit: MassUnit::Gram,
+            volume_unit: VolumeUnit::Litre,
+            props,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Nop => return false,
+            Msg::UpdateTarget(target) => self.target = target,
+            Msg::SetMassUnit(unit) => self.mass_unit = unit,
+            Msg::SetVolumeUnit(unit) => self.volume_unit = unit,
+            Msg::Apply => match self.scaled() {
+                Some(Ok(recipe)) => self.props.on_apply.emit(recipe),
+                _ => return false,
+            },
+        }
+
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false;
+        }
+        self.props = props;
+
+        true
+    }
+
+    fn view(&self) -> Html {
+        let scaled = self.scaled();
+        let (recipe, error) = match &scaled {
+            Some(Ok(recipe)) => (recipe, None),
+            Some(Err(err)) => (&self.props.recipe, Some(err.to_string())),
+            None => (&self.props.recipe, None),
+        };
+        let total = self.volume_unit.from_ml(self.props.recipe.total_ml());
+
+        html! {
+            <div class="box kombucha-recipe-calculator">
+                <div class="field is-grouped">
+                    <p class="control is-expanded">
+                        <input
+                            class="input is-small" type="number" min="0"
+                            placeholder=format!("Batch size, now {:.2}", total)
+                            value=&self.target
+                            oninput=self.link.callback(|e: InputData| Msg::UpdateTarget(e.value))
+                        />
+                    </p>
+                    <p class="control">
+                        { self.view_unit_select(VolumeUnit::ALL.iter(), self.volume_unit, Msg::SetVolumeUnit) }
+                    </p>
+                    <p class="control">
+                        { self.view_unit_select(MassUnit::ALL.iter(), self.mass_unit, Msg::SetMassUnit) }
+                    </p>
+                </div>
+                { error.map(|error| html! { <p class="help is-danger">{ error }</p> }).unwrap_or_default() }
+                { self.view_amounts(recipe) }
+                <button
+                    class="button is-small is-info"
+                    disabled=!matches!(scaled, Some(Ok(_)))
+                    onclick=self.link.callback(|_| Msg::Apply)
+                >
+                    {"Use scaled recipe"}
+                </button>
+            </div>
+        }
+    }
+}
+
+impl RecipeCalculator {
+    /// `None` until a target volume is typed in
+    fn scaled(&self) -> Option<Result<Recipe, anyhow::Error>> {
+        if self.target.trim().is_empty() {
+            return None;
+        }
+
+        let target = match self.target.trim().parse::<f64>() {
+            Ok(target) => target,
+            Err(_) => {
+                return Some(Err(anyhow::anyhow!(
+                    "{} isn't a volume",
+                    self.target
+                )))
+            }
+        };
+
+        Some(calculator::scale(
+            &self.props.recipe,
+            self.volume_unit.to_ml(target),
+        ))
+    }
+
+    fn view_unit_select<U>(
+        &self,
+        units: impl Iterator<Item = &'static U>,
+        selected: U,
+        msg: fn(U) -> Msg,
+    ) -> Html
+    where
+        U: ToString + FromStr + PartialEq + Copy + 'static,
+    {
+        let options = units.map(|&unit| {
+            html! {
+                <option value=unit.to_string() selected=unit == selected>
+                    { unit.to_string() }
+                </option>
+            }
+        });
+
+        html! {
+            <span class="select is-small">
+                <select
+                    onchange=self.link.callback(move |e: ChangeData| match e {
+                        ChangeData::Select(select) => match select.value().parse() {
+                            Ok(unit) => msg(unit),
+                            Err(_) => Msg::Nop,
+                        },
+                        x => { log::error!("Invalid change data, expected select, found {:?}", x); Msg::Nop }
+                    })
+                >
+                    { for options }
+                </select>
+            </span>
+        }
+    }
+
+    fn view_amounts(&self, recipe: &Recipe) -> Html {
+        let mass = |grams: f64| {
+            format!(
+                "{:.1} {}",
+                self.mass_unit.from_grams(grams),
+                self.mass_unit
+            )
+        };
+        let volume = |ml: f64| {
+            format!("{:.2} {}", self.volume_unit.from_ml(ml), self.volume_unit)
+        };
+        // Per unit of volume picked rather than per litre
+        let per_volume = |per_litre: f64| {
+            let per_unit = per_litre * self.volume_unit.to_ml(1.0) / 1000.0;
+            format!(
+                "{:.1} {}/{}",
+                self.mass_unit.from_grams(per_unit),
+                self.mass_unit,
+                self.volume_unit
+            )
+        };
+
+        let teas = recipe
+            .teas
+            .iter()
+            .map(|tea| format!("{} {}", mass(tea.grams), tea.variety))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let strength = Strength::of(recipe);
+
+        html! {
+            <table class="table is-narrow is-fullwidth">
+                <tbody>
+                    <tr><th>{"Tea"}</th><td>{ teas }</td></tr>
+                    <tr><th>{"Sugar"}</th><td>{ format!("{} {}", mass(recipe.sugar_grams), recipe.sugar_type) }</td></tr>
+                    <tr><th>{"Water"}</th><td>{ volume(recipe.water_ml) }</td></tr>
+                    <tr><th>{"Starter"}</th><td>{ volume(recipe.starter_ml) }</td></tr>
+                    <tr><th>{"Sugar per water"}</th><td>{ per_volume(strength.sugar_per_litre) }</td></tr>
+                    <tr><th>{"Tea per water"}</th><td>{ per_volume(strength.tea_per_litre) }</td></tr>
+                    <tr><th>{"Starter share"}</th><td>{ format!("{:.1}%", strength.starter_percent()) }</td></tr>
+                </tbody>
+            </table>
+        }
+    }
+}
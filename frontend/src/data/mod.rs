@@ -1 +1,4 @@
-pub use data_types::{Entry, Fermentation, Kombucha};
+pub use data_types::{
+    Entry, Fermentation, FermentationStatus, Kombucha, KombuchaId,
+    KombuchaSummary, Milestone, RecipeTemplate, SearchHit, TemplateId,
+};
@@ -0,0 +1,77 @@
+use wasm_bindgen::JsCast;
+use web_sys::{Element, KeyboardEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shortcut {
+    Next,
+    Previous,
+    Search,
+    NextEntry,
+    PreviousEntry,
+    NewEntry,
+    EditEntry,
+    LogMeasurement,
+    Save,
+    Cancel,
+    Help,
+}
+
+/// Keys and descriptions listed in the shortcut overlay
+pub const SHORTCUTS: &[(&str, &str)] = &[
+    ("j / ↓", "Select the next kombucha"),
+    ("k / ↑", "Select the previous kombucha"),
+    ("/", "Search kombuchas"),
+    ("J / K", "Select the next or previous entry"),
+    ("n", "Add a new entry"),
+    ("e", "Edit the selected entry, the latest without one"),
+    ("m", "Log a measurement"),
+    ("Ctrl + Enter", "Save the edited entry or name"),
+    ("Esc", "Cancel editing, close dialogs"),
+    ("?", "Show this help"),
+];
+
+impl Shortcut {
+    /// Single key shortcuts are ignored while typing into a form field, so
+    /// only `Save` and `Cancel` work there
+    pub fn from_event(e: &KeyboardEvent) -> Option<Shortcut> {
+        let key = e.key();
+
+        if key == "Escape" {
+            return Some(Shortcut::Cancel);
+        }
+
+        if key == "Enter" && (e.ctrl_key() || e.meta_key()) {
+            return Some(Shortcut::Save);
+        }
+
+        if e.ctrl_key() || e.meta_key() || e.alt_key() || is_typing(e) {
+            return None;
+        }
+
+        match key.as_str() {
+            "j" | "ArrowDown" => Some(Shortcut::Next),
+            "k" | "ArrowUp" => Some(Shortcut::Previous),
+            "/" => Some(Shortcut::Search),
+            "J" => Some(Shortcut::NextEntry),
+            "K" => Some(Shortcut::PreviousEntry),
+            "n" => Some(Shortcut::NewEntry),
+            "e" => Some(Shortcut::EditEntry),
+            "m" => Some(Shortcut::LogMeasurement),
+            "?" => Some(Shortcut::Help),
+            _ => None,
+        }
+    }
+}
+
+fn is_typing(e: &KeyboardEvent) -> bool {
+    match e
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+    {
+        Some(element) => matches!(
+            element.tag_name().as_str(),
+            "INPUT" | "TEXTAREA" | "SELECT"
+        ),
+        None => false,
+    }
+}
@@ -1,7 +1,8 @@
-#![recursion_limit = "256"]
+#![recursion_limit = "512"]
 
 pub mod components;
 pub mod data;
+pub mod keyboard;
 
 use wasm_bindgen::prelude::*;
 
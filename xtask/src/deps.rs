@@ -0,0 +1,36 @@
+use cmd_lib::{run_cmd, run_fun};
+
+const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// Checks for and installs everything `dev` needs to build the frontend
+pub fn install() -> Result<(), anyhow::Error> {
+    let targets = run_fun!("rustup target list --installed")?;
+    if targets.lines().any(|target| target.trim() == WASM_TARGET) {
+        log::info!("Rust target {} is installed", WASM_TARGET);
+    } else {
+        log::info!("Installing rust target {}", WASM_TARGET);
+        run_cmd!("rustup target add {}", WASM_TARGET)?;
+    }
+
+    match run_fun!("wasm-pack --version") {
+        Ok(version) => log::info!("Found {}", version.trim()),
+        Err(_) => {
+            log::info!("Installing wasm-pack");
+            run_cmd!("cargo install wasm-pack")?;
+        }
+    }
+
+    match run_fun!("rollup --version") {
+        Ok(version) => log::info!("Found {}", version.trim()),
+        Err(_) => {
+            if run_fun!("npm --version").is_err() {
+                anyhow::bail!("rollup is missing and npm isn't available to install it, install Node.js first");
+            }
+
+            log::info!("Installing rollup");
+            run_cmd!("npm install -g rollup")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use cmd_lib::{CmdResult, Process};
+use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::mpsc::channel,
+    time::Duration,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Rebuild {
+    frontend: bool,
+    backend: bool,
+}
+
+/// Directories that trigger a rebuild and which side they belong to,
+/// `data` is shared by both
+const WATCHED: &[(&str, Rebuild)] = &[
+    (
+        "frontend/src",
+        Rebuild {
+            frontend: true,
+            backend: false,
+        },
+    ),
+    (
+        "frontend/www",
+        Rebuild {
+            frontend: true,
+            backend: false,
+        },
+    ),
+    (
+        "backend/src",
+        Rebuild {
+            frontend: false,
+            backend: true,
+        },
+    ),
+    (
+        "data/src",
+        Rebuild {
+            frontend: true,
+            backend: true,
+        },
+    ),
+];
+
+/// Builds the frontend and backend, starts the server and rebuilds whatever
+/// changed until interrupted
+pub fn run(root: &Path) -> Result<(), anyhow::Error> {
+    build_frontend(root)?;
+    build_backend(root)?;
+
+    let mut server = start_server(root)?;
+
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, DEBOUNCE)?;
+
+    for (dir, _) in WATCHED {
+        watcher.watch(root.join(dir), RecursiveMode::Recursive)?;
+    }
+
+    log::info!("Watching for changes");
+
+    loop {
+        let mut rebuild = rebuild_for(root, rx.recv()?);
+        // Coalesce everything that changed while the previous build ran
+        for event in rx.try_iter() {
+            let other = rebuild_for(root, event);
+            rebuild.frontend |= other.frontend;
+            rebuild.backend |= other.backend;
+        }
+
+        if rebuild.frontend {
+            if let Err(err) = build_frontend(root) {
+                log::error!("Frontend build failed: {}", err);
+            }
+        }
+
+        if rebuild.backend {
+            match build_backend(root) {
+                Ok(()) => {
+                    stop_server(&mut server);
+                    server = start_server(root)?;
+                }
+                Err(err) => log::error!(
+                    "Backend build failed, keeping the old server running: {}",
+                    err
+                ),
+            }
+        }
+    }
+}
+
+fn rebuild_for(root: &Path, event: DebouncedEvent) -> Rebuild {
+    let paths: Vec<PathBuf> = match event {
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Remove(path) => vec![path],
+        DebouncedEvent::Rename(from, to) => vec![from, to],
+        _ => vec![],
+    };
+
+    let mut rebuild = Rebuild::default();
+
+    for path in paths {
+        for (dir, side) in WATCHED {
+            if path.starts_with(root.join(dir)) {
+                log::debug!("{} changed", path.display());
+                rebuild.frontend |= side.frontend;
+                rebuild.backend |= side.backend;
+            }
+        }
+    }
+
+    rebuild
+}
+
+fn build_frontend(root: &Path) -> Result<(), anyhow::Error> {
+    log::info!("Building frontend");
+
+    Process::new("./rebuild.sh")
+        .current_dir(root.join("frontend").to_string_lossy())
+        .wait::<CmdResult>()?;
+
+    Ok(())
+}
+
+fn build_backend(root: &Path) -> Result<(), anyhow::Error> {
+    log::info!("Building backend");
+
+    Process::new("cargo build -p kombucha-tracker-server")
+        .current_dir(root.to_string_lossy())
+        .wait::<CmdResult>()?;
+
+    Ok(())
+}
+
+fn start_server(root: &Path) -> Result<Child, anyhow::Error> {
+    log::info!("Starting server");
+
+    // Run from the backend directory so that its .env is picked up, the app
+    // dir set here takes precedence over the one in .env
+    let server = Command::new(root.join("target/debug/kombucha-tracker-server"))
+        .current_dir(root.join("backend"))
+        .env("KOMBUCHA_APP_DIR", root.join("frontend/target-www"))
+        .spawn()?;
+
+    Ok(server)
+}
+
+fn stop_server(server: &mut Child) {
+    log::info!("Stopping server");
+
+    if let Err(err) = server.kill() {
+        log::warn!("Failed to stop the server: {}", err);
+    }
+
+    let _ = server.wait();
+}
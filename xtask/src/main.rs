@@ -1,22 +1,33 @@
-use cmd_lib::run_cmd;
+use std::path::PathBuf;
 use structopt::StructOpt;
 
+mod deps;
+mod dev;
+
 #[derive(Debug, StructOpt)]
 #[structopt(rename_all = "kebab-case")]
 enum Opt {
-    StartAll,
+    /// Builds the frontend and the backend, serves them and rebuilds on
+    /// changes
+    Dev,
+    /// Checks for and installs the tools needed to build the frontend
     InstallDeps,
 }
 
-fn main() {
+fn main() -> Result<(), anyhow::Error> {
     dotenv::dotenv().ok();
     std::env::set_var("RUST_LOG", "xtask=trace,$RUST_LOG");
     pretty_env_logger::init();
 
     let args = Opt::from_args();
 
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask is in the workspace root")
+        .to_path_buf();
+
     match args {
-        Opt::StartAll => run_cmd!("").unwrap(),
-        Opt::InstallDeps => run_cmd!("").unwrap(),
+        Opt::Dev => dev::run(&root),
+        Opt::InstallDeps => deps::install(),
     }
 }